@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use crate::{
+    bitstream::BitStream,
+    ir::{
+        attributes::{Attribute, AttributeList, AttributeSet, ConstantRange},
+        types::TypeId,
+    },
+};
+
+use super::constants::{AttributeCode, BlockId};
+
+const ATTR_ABBR_ID_WIDTH: u32 = 3;
+
+/// Index of the function attributes in a group record.
+pub const FUNCTION_INDEX: u32 = u32::MAX;
+/// Index of the return value attributes in a group record.
+pub const RETURN_INDEX: u32 = 0;
+/// Index of the first parameter attributes in a group record.
+pub const FIRST_PARAM_INDEX: u32 = 1;
+
+/// Deduplicates attribute lists into the attribute groups and lists written to the
+/// PARAMATTR_GROUP and PARAMATTR blocks.
+#[derive(Debug, Clone, Default)]
+pub struct AttributeTable {
+    // Group ids are the 1-based position.
+    groups: Vec<(u32, AttributeSet)>,
+    group_ids: HashMap<(u32, AttributeSet), u32>,
+    // List ids are the 1-based position, 0 means no attributes.
+    lists: Vec<Vec<u32>>,
+    list_ids: HashMap<AttributeList, u32>,
+}
+
+impl AttributeTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the list if not present and returns the id used to reference it from
+    /// function and call records.
+    pub fn add(&mut self, list: &AttributeList) -> u32 {
+        if list.is_empty() {
+            return 0;
+        }
+
+        if let Some(id) = self.list_ids.get(list) {
+            return *id;
+        }
+
+        let mut groups = Vec::new();
+
+        if !list.function().is_empty() {
+            groups.push(self.add_group(FUNCTION_INDEX, list.function()));
+        }
+
+        if !list.ret().is_empty() {
+            groups.push(self.add_group(RETURN_INDEX, list.ret()));
+        }
+
+        for (i, set) in list.params().iter().enumerate() {
+            if !set.is_empty() {
+                groups.push(self.add_group(FIRST_PARAM_INDEX + i as u32, set));
+            }
+        }
+
+        self.lists.push(groups);
+        let id = self.lists.len() as u32;
+        self.list_ids.insert(list.clone(), id);
+        id
+    }
+
+    fn add_group(&mut self, index: u32, set: &AttributeSet) -> u32 {
+        let key = (index, set.clone());
+
+        if let Some(id) = self.group_ids.get(&key) {
+            return *id;
+        }
+
+        self.groups.push(key.clone());
+        let id = self.groups.len() as u32;
+        self.group_ids.insert(key, id);
+        id
+    }
+
+    /// The id of a list previously added.
+    pub fn get(&self, list: &AttributeList) -> u32 {
+        if list.is_empty() {
+            return 0;
+        }
+
+        *self.list_ids.get(list).expect("attribute list was not added")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+
+    /// Writes the PARAMATTR_GROUP and PARAMATTR blocks, `type_index` maps types
+    /// to their index in the type table.
+    pub fn write(&self, stream: &mut BitStream, type_index: impl Fn(TypeId) -> u32) {
+        if self.is_empty() {
+            return;
+        }
+
+        stream.enter_block(BlockId::ParamAttrGroup as u32, ATTR_ABBR_ID_WIDTH);
+
+        for (i, (index, set)) in self.groups.iter().enumerate() {
+            // [grpid, paramidx, attr0, attr1, ...]
+            let mut record = vec![i as u64 + 1, *index as u64];
+
+            for attr in set.iter() {
+                encode_attribute(&mut record, attr, &type_index);
+            }
+
+            stream.write_unabrr_record_u64(AttributeCode::GrpCodeEntry as u32, &record);
+        }
+
+        stream.end_block(BlockId::ParamAttrGroup as u32);
+
+        stream.enter_block(BlockId::ParamAttr as u32, ATTR_ABBR_ID_WIDTH);
+
+        for groups in &self.lists {
+            // [grpid...]
+            stream.write_unabrr_record(AttributeCode::CodeEntry as u32, groups);
+        }
+
+        stream.end_block(BlockId::ParamAttr as u32);
+    }
+}
+
+// https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L965
+fn encode_attribute(record: &mut Vec<u64>, attr: &Attribute, type_index: &impl Fn(TypeId) -> u32) {
+    match attr {
+        Attribute::Enum(kind) => {
+            record.push(0);
+            record.push(*kind as u64);
+        }
+        Attribute::Int(kind, value) => {
+            record.push(1);
+            record.push(*kind as u64);
+            record.push(*value);
+        }
+        Attribute::String(key, value) => {
+            record.push(if value.is_empty() { 3 } else { 4 });
+            record.extend(key.bytes().map(u64::from));
+            record.push(0);
+
+            if !value.is_empty() {
+                record.extend(value.bytes().map(u64::from));
+                record.push(0);
+            }
+        }
+        Attribute::Type(kind, ty) => {
+            record.push(if ty.is_some() { 6 } else { 5 });
+            record.push(*kind as u64);
+
+            if let Some(ty) = ty {
+                record.push(type_index(*ty) as u64);
+            }
+        }
+        Attribute::Range(kind, range) => {
+            record.push(7);
+            record.push(*kind as u64);
+            record.push(range.bit_width as u64);
+            encode_range(record, range);
+        }
+        Attribute::RangeList(kind, ranges) => {
+            record.push(8);
+            record.push(*kind as u64);
+            record.push(ranges.len() as u64);
+            record.push(ranges[0].bit_width as u64);
+
+            for range in ranges {
+                encode_range(record, range);
+            }
+        }
+    }
+}
+
+fn encode_range(record: &mut Vec<u64>, range: &ConstantRange) {
+    record.push(encode_signed(range.lower));
+    record.push(encode_signed(range.upper));
+}
+
+/// Signed values are rotated so the sign is in the lowest bit.
+pub fn encode_signed(value: i64) -> u64 {
+    if value >= 0 {
+        (value as u64) << 1
+    } else {
+        (value.unsigned_abs() << 1) | 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::AttributeKindCode,
+        bitstream::BitStream,
+        ir::{
+            attributes::{Attribute, AttributeList, ConstantRange},
+            types::Types,
+        },
+    };
+
+    use super::{encode_signed, AttributeTable};
+
+    #[test]
+    fn signed_encoding() {
+        assert_eq!(encode_signed(0), 0);
+        assert_eq!(encode_signed(5), 10);
+        assert_eq!(encode_signed(-5), 11);
+        assert_eq!(encode_signed(i64::MIN), 1);
+    }
+
+    #[test]
+    fn lists_and_groups_are_deduplicated() {
+        let mut table = AttributeTable::new();
+        assert_eq!(table.add(&AttributeList::new()), 0);
+
+        let mut a = AttributeList::new();
+        a.add_function(AttributeKindCode::NO_UNWIND);
+        a.add_param(0, AttributeKindCode::NOUNDEF);
+
+        let mut b = AttributeList::new();
+        b.add_function(AttributeKindCode::NO_UNWIND);
+        b.add_param(1, AttributeKindCode::NOUNDEF);
+
+        assert_eq!(table.add(&a), 1);
+        assert_eq!(table.add(&b), 2);
+        assert_eq!(table.add(&a.clone()), 1);
+
+        // nounwind is shared, noundef is a different group per parameter index.
+        assert_eq!(table.groups.len(), 3);
+        assert_eq!(table.lists, vec![vec![1, 2], vec![1, 3]]);
+    }
+
+    #[test]
+    fn write_attributes() {
+        let mut types = Types::new();
+        let i32 = types.i32();
+
+        let mut list = AttributeList::new();
+        list.add_function(AttributeKindCode::NO_UNWIND);
+        list.add_function(Attribute::alloc_size(0, None));
+        list.add_function(Attribute::string("frame-pointer", "all"));
+        list.add_ret(Attribute::range(ConstantRange::new(32, -1, 10)));
+        list.add_param(0, Attribute::sret(i32));
+        list.add_param(0, Attribute::initializes(&[ConstantRange::new(64, 0, 4)]));
+
+        let mut table = AttributeTable::new();
+        table.add(&list);
+
+        let mut writer = BitStream::new(0xdec04342);
+        writer.enter_block(8, 3);
+        table.write(&mut writer, |ty| ty.index() as u32);
+        writer.end_block(8);
+        writer.writer.flush();
+
+        let content = hex::encode(&writer.writer.buffer[4..]);
+        assert_eq!(
+            content,
+            "210c00001700000051180000100000001bb4e0ffffffff07400ae6e0ffffffff07c21ce4211cdaa11cda001ede211ddc811eca411e807060077600361c0270f002180cd48665088c0e407c21000500014918000002000000138640180000000000000000"
+        );
+    }
+}
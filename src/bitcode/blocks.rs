@@ -33,3 +33,36 @@ impl ConstantBlock {
         ]);
     }
 }
+
+pub struct ValueSymtabBlock {
+
+}
+
+impl ValueSymtabBlock {
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L3800
+    pub fn build_info(info: &mut BlockInfoMap) {
+        info.insert(BlockId::ValueSymtab as u32, vec![
+            // The code is not a literal so the same abbr serves both entries and bb entries.
+            Abbr::new("entry8", &[
+                OperandDef::Fixed(3),
+                OperandDef::Vbr(vbr_widths::VALUE_INDEX),
+                OperandDef::Array(Box::new(OperandDef::Fixed(8))),
+            ]),
+            Abbr::new("entry7", &[
+                OperandDef::Literal(ValueSymtabCode::Entry as u32),
+                OperandDef::Vbr(vbr_widths::VALUE_INDEX),
+                OperandDef::Array(Box::new(OperandDef::Fixed(7))),
+            ]),
+            Abbr::new("entry6", &[
+                OperandDef::Literal(ValueSymtabCode::Entry as u32),
+                OperandDef::Vbr(vbr_widths::VALUE_INDEX),
+                OperandDef::Array(Box::new(OperandDef::Char6)),
+            ]),
+            Abbr::new("bbentry6", &[
+                OperandDef::Literal(ValueSymtabCode::BbEntry as u32),
+                OperandDef::Vbr(vbr_widths::VALUE_INDEX),
+                OperandDef::Array(Box::new(OperandDef::Char6)),
+            ]),
+        ]);
+    }
+}
@@ -89,6 +89,7 @@ pub enum TypeCode {
     StructName = 19,
     StructNamed = 20,
     Function = 21,
+    Token = 22,
     Bfloat = 23,
     X86Amx = 24,
     OpaquePointer = 25,
@@ -165,7 +166,7 @@ pub enum ConstantsCode {
     PtrAuth = 33,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CastCodes {
     Trunc = 0,
     Zext = 1,
@@ -182,12 +183,12 @@ pub enum CastCodes {
     AddspaceCast = 12,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UnaryOpCodes {
     Fneg = 0,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BinaryOpCode {
     Add = 0,
     Sub = 1,
@@ -204,7 +205,7 @@ pub enum BinaryOpCode {
     Xor = 12,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RMWOperation {
     XCHG = 0,
     ADD = 1,
@@ -232,7 +233,7 @@ pub enum GetElementPtrOptionalFlag {
     GEP_NUW = 2,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AtomicOrderingCode {
     ORDERING_NOTATOMIC = 0,
     ORDERING_UNORDERED = 1,
@@ -352,7 +353,7 @@ pub enum UseListCode {
     CODE_BB = 2,      // BB: [index..., bb-id]
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum AttributeKindCode {
     // = 0 is unused
     ALIGNMENT = 1,
@@ -452,7 +453,7 @@ pub enum AttributeKindCode {
     HYBRID_PATCHABLE = 95,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ComdatSelectionKindCode {
     ANY = 1,
     EXACT_MATCH = 2,
@@ -490,6 +491,21 @@ pub enum OverFlowingBinOpOptionalFlag {
     NO_SIGNED_WRAP = 1,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum PossiblyExactOperatorOptionalFlag {
+    EXACT = 0,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PossiblyDisjointInstOptionalFlag {
+    DISJOINT = 0,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PossiblyNonNegInstOptionalFlag {
+    NON_NEG = 0,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum MetadataCode {
     STRING_OLD = 1,              // MDSTRING:      [values]
@@ -541,7 +557,6 @@ pub enum MetadataCode {
     ASSIGN_ID = 47,        // [distinct, ...]
 }
 
-
 pub mod vbr_widths {
     pub const ALIGNMENT: u32 = 3;
     pub const ARRAY_LENGTH: u32 = 8;
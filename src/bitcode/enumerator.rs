@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+
+use crate::ir::{
+    function::Function,
+    instructions::{DebugRecord, DebugRecordKind, InstructionKind},
+    metadata::{Attachments, Metadata, MetadataId},
+    module::Module,
+    types::{Type, TypeId},
+    value::{ConstantId, Value, ValueRef},
+};
+
+use super::attributes::AttributeTable;
+
+/// Assigns the ids used in bitcode records to types, values and attribute lists.
+///
+/// Module values are numbered globals first, then functions, aliases, ifuncs and
+/// then the constants they use. Metadata is numbered strings first, then values
+/// and nodes. When a function is incorporated its arguments,
+/// constants and instructions are numbered after the module values, and its local
+/// metadata after the module metadata.
+// https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/ValueEnumerator.cpp
+pub struct ValueEnumerator<'a> {
+    module: &'a Module,
+    types: Vec<TypeId>,
+    type_indices: Vec<u32>,
+    values: HashMap<ValueRef, u32>,
+    num_values: u32,
+    num_module_values: u32,
+    module_constants: Vec<ConstantId>,
+    function_constants: Vec<ConstantId>,
+    metadata: Vec<MetadataId>,
+    metadata_ids: HashMap<MetadataId, u32>,
+    num_md_strings: u32,
+    function_metadata: Vec<MetadataId>,
+    attributes: AttributeTable,
+}
+
+impl<'a> ValueEnumerator<'a> {
+    pub fn new(module: &'a Module) -> Self {
+        let mut s = Self {
+            module,
+            types: Vec::new(),
+            type_indices: vec![u32::MAX; module.types.len()],
+            values: HashMap::new(),
+            num_values: 0,
+            num_module_values: 0,
+            module_constants: Vec::new(),
+            function_constants: Vec::new(),
+            metadata: Vec::new(),
+            metadata_ids: HashMap::new(),
+            num_md_strings: 0,
+            function_metadata: Vec::new(),
+            attributes: AttributeTable::new(),
+        };
+
+        for (id, _) in module.types.iter() {
+            s.enumerate_type(id);
+        }
+
+        for (id, _) in module.globals() {
+            s.push_value(ValueRef::Global(id));
+        }
+
+        for (id, _) in module.functions() {
+            s.push_value(ValueRef::Function(id));
+        }
+
+        for (id, _) in module.aliases() {
+            s.push_value(ValueRef::Alias(id));
+        }
+
+        for (id, _) in module.ifuncs() {
+            s.push_value(ValueRef::IFunc(id));
+        }
+
+        let mut constants = Vec::new();
+        for (_, global) in module.globals() {
+            if let Some(init) = global.initializer {
+                s.enumerate_constant(init, &mut constants);
+            }
+        }
+        for (_, alias) in module.aliases() {
+            s.enumerate_constant(alias.aliasee, &mut constants);
+        }
+        for (_, ifunc) in module.ifuncs() {
+            s.enumerate_constant(ifunc.resolver, &mut constants);
+        }
+        for (_, function) in module.functions() {
+            if let Some(personality) = function.personality {
+                s.enumerate_constant(personality, &mut constants);
+            }
+        }
+
+        let mut metadata = Vec::new();
+        for named in module.named_metadatas() {
+            for node in &named.operands {
+                s.enumerate_metadata(*node, &mut metadata, &mut constants);
+            }
+        }
+        for (_, global) in module.globals() {
+            s.enumerate_attachments(&global.metadata, &mut metadata, &mut constants);
+        }
+        for (_, function) in module.functions() {
+            s.enumerate_attachments(&function.metadata, &mut metadata, &mut constants);
+            for inst in &function.instructions {
+                s.enumerate_attachments(&inst.metadata, &mut metadata, &mut constants);
+
+                // Locations have their own record, only their operands are numbered.
+                if let Some(loc) = inst.debug_loc {
+                    for operand in module.metadata.get(loc).operands() {
+                        s.enumerate_metadata(operand, &mut metadata, &mut constants);
+                    }
+                }
+
+                for operand in inst.kind.operands() {
+                    if let ValueRef::Metadata(id) = operand.kind {
+                        s.enumerate_metadata(id, &mut metadata, &mut constants);
+                    }
+                }
+                for record in &inst.debug_records {
+                    for id in record.metadata() {
+                        s.enumerate_metadata(id, &mut metadata, &mut constants);
+                    }
+                }
+            }
+        }
+        s.organize_metadata(metadata);
+
+        s.module_constants = constants;
+        s.num_module_values = s.num_values;
+
+        for (_, function) in module.functions() {
+            s.attributes.add(&function.attributes);
+
+            for inst in &function.instructions {
+                if let InstructionKind::Call(call) | InstructionKind::Invoke { call, .. } =
+                    &inst.kind
+                {
+                    s.attributes.add(&call.attributes);
+                }
+            }
+        }
+
+        s
+    }
+
+    // Types are numbered after the types they contain, so only identified structs
+    // can be forward referenced.
+    fn enumerate_type(&mut self, id: TypeId) {
+        if self.type_indices[id.index()] != u32::MAX {
+            return;
+        }
+
+        let ty = self.module.types.get(id);
+
+        let is_identified = matches!(ty, Type::Struct(st) if st.name.is_some());
+        if is_identified {
+            self.push_type(id);
+        }
+
+        match ty {
+            Type::Array { element, .. } | Type::Vector { element, .. } => {
+                self.enumerate_type(*element)
+            }
+            Type::Struct(st) => {
+                for field in &st.fields {
+                    self.enumerate_type(*field);
+                }
+            }
+            Type::Function(fn_ty) => {
+                self.enumerate_type(fn_ty.ret);
+                for param in &fn_ty.params {
+                    self.enumerate_type(*param);
+                }
+            }
+            _ => {}
+        }
+
+        if !is_identified {
+            self.push_type(id);
+        }
+    }
+
+    fn push_type(&mut self, id: TypeId) {
+        self.type_indices[id.index()] = self.types.len() as u32;
+        self.types.push(id);
+    }
+
+    fn push_value(&mut self, value: ValueRef) {
+        self.values.insert(value, self.num_values);
+        self.num_values += 1;
+    }
+
+    // Constants are numbered after the constants they use.
+    fn enumerate_constant(&mut self, value: Value, out: &mut Vec<ConstantId>) {
+        let ValueRef::Constant(id) = value.kind else {
+            return;
+        };
+
+        if self.values.contains_key(&value.kind) {
+            return;
+        }
+
+        for operand in self.module.constants.get(id).kind.operands() {
+            self.enumerate_constant(operand, out);
+        }
+
+        self.push_value(value.kind);
+        out.push(id);
+    }
+
+    // Metadata is numbered after the metadata it uses, cycles aside. The values it
+    // wraps become module constants.
+    fn enumerate_metadata(
+        &mut self,
+        id: MetadataId,
+        out: &mut Vec<MetadataId>,
+        constants: &mut Vec<ConstantId>,
+    ) {
+        if self.metadata_ids.contains_key(&id) {
+            return;
+        }
+
+        let module = self.module;
+        let md = module.metadata.get(id);
+        // Numbered by `incorporate_function`, only what it uses is module metadata.
+        if md.is_function_local() {
+            for operand in md.operands() {
+                self.enumerate_metadata(operand, out, constants);
+            }
+            return;
+        }
+
+        // Marks it as visited, the final id is assigned by `organize_metadata`.
+        self.metadata_ids.insert(id, u32::MAX);
+
+        if let Metadata::Value(value) = md {
+            self.enumerate_constant(*value, constants);
+        }
+
+        for operand in md.operands() {
+            self.enumerate_metadata(operand, out, constants);
+        }
+
+        out.push(id);
+    }
+
+    fn enumerate_attachments(
+        &mut self,
+        attachments: &Attachments,
+        out: &mut Vec<MetadataId>,
+        constants: &mut Vec<ConstantId>,
+    ) {
+        for (_, node) in attachments.iter() {
+            assert!(
+                self.module.metadata.get(node).is_node(),
+                "attachments must be nodes"
+            );
+            self.enumerate_metadata(node, out, constants);
+        }
+    }
+
+    // Strings come first so they can be written in bulk, then values, then distinct
+    // nodes and uniqued nodes. The sort is stable so each group stays in enumeration
+    // order.
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/ValueEnumerator.cpp
+    fn organize_metadata(&mut self, mut metadata: Vec<MetadataId>) {
+        let module = self.module;
+        let table = &module.metadata;
+        metadata.sort_by_key(|id| match table.get(*id) {
+            Metadata::String(_) => 0,
+            Metadata::Value(_) => 1,
+            md if md.is_distinct() => 2,
+            _ => 3,
+        });
+
+        self.num_md_strings = metadata
+            .iter()
+            .take_while(|x| matches!(table.get(**x), Metadata::String(_)))
+            .count() as u32;
+
+        for (i, id) in metadata.iter().enumerate() {
+            self.metadata_ids.insert(*id, i as u32);
+        }
+        self.metadata = metadata;
+    }
+
+    /// Types in the order they are written to the type table.
+    pub fn types(&self) -> &[TypeId] {
+        &self.types
+    }
+
+    pub fn type_index(&self, ty: TypeId) -> u32 {
+        self.type_indices[ty.index()]
+    }
+
+    pub fn value_id(&self, value: ValueRef) -> u32 {
+        if let ValueRef::Metadata(id) = value {
+            return self.metadata_id(id);
+        }
+
+        *self
+            .values
+            .get(&value)
+            .unwrap_or_else(|| panic!("value {value:?} was not enumerated"))
+    }
+
+    pub fn num_module_values(&self) -> u32 {
+        self.num_module_values
+    }
+
+    pub fn module_constants(&self) -> &[ConstantId] {
+        &self.module_constants
+    }
+
+    pub fn function_constants(&self) -> &[ConstantId] {
+        &self.function_constants
+    }
+
+    /// Metadata in the order it is written, strings first.
+    pub fn metadata(&self) -> &[MetadataId] {
+        &self.metadata
+    }
+
+    /// The local metadata of the incorporated function, numbered after the module
+    /// metadata.
+    pub fn function_metadata(&self) -> &[MetadataId] {
+        &self.function_metadata
+    }
+
+    pub fn num_md_strings(&self) -> u32 {
+        self.num_md_strings
+    }
+
+    pub fn metadata_id(&self, id: MetadataId) -> u32 {
+        *self
+            .metadata_ids
+            .get(&id)
+            .unwrap_or_else(|| panic!("metadata {id:?} was not enumerated"))
+    }
+
+    pub fn attributes(&self) -> &AttributeTable {
+        &self.attributes
+    }
+
+    /// Numbers the arguments, constants and instructions of the function.
+    pub fn incorporate_function(&mut self, function: &Function) {
+        for (i, _) in function.params.iter().enumerate() {
+            self.push_value(ValueRef::Argument(i as u32));
+        }
+
+        let mut constants = Vec::new();
+        for (_, block) in function.blocks() {
+            for inst in block.instructions() {
+                for operand in function.instruction(*inst).kind.operands() {
+                    self.enumerate_constant(operand, &mut constants);
+                }
+            }
+        }
+        self.function_constants = constants;
+
+        // Local values are numbered before the arg lists using them.
+        let table = &self.module.metadata;
+        let mut local_values = Vec::new();
+        let mut arg_lists = Vec::new();
+        let mut add_local = |id: MetadataId| match table.get(id) {
+            Metadata::Value(value) if value.kind.is_local() => local_values.push(id),
+            Metadata::ArgList(list) => {
+                arg_lists.push(id);
+                local_values.extend(
+                    list.args
+                        .iter()
+                        .filter(|x| table.get(**x).is_function_local()),
+                );
+            }
+            _ => {}
+        };
+
+        for (_, block) in function.blocks() {
+            for inst in block.instructions() {
+                let inst = function.instruction(*inst);
+                for operand in inst.kind.operands() {
+                    if let ValueRef::Metadata(id) = operand.kind {
+                        add_local(id);
+                    }
+                }
+                for record in &inst.debug_records {
+                    if let DebugRecord::Variable { kind, location, .. } = record {
+                        add_local(*location);
+                        if let DebugRecordKind::Assign { address, .. } = kind {
+                            add_local(*address);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (_, block) in function.blocks() {
+            for inst in block.instructions() {
+                if function.instruction(*inst).ty.is_some() {
+                    self.push_value(ValueRef::Instruction(*inst));
+                }
+            }
+        }
+
+        for id in local_values.into_iter().chain(arg_lists) {
+            if !self.metadata_ids.contains_key(&id) {
+                let md_id = (self.metadata.len() + self.function_metadata.len()) as u32;
+                self.metadata_ids.insert(id, md_id);
+                self.function_metadata.push(id);
+            }
+        }
+    }
+
+    /// Forgets the values of the last incorporated function.
+    pub fn purge_function(&mut self) {
+        self.values.retain(|_, id| *id < self.num_module_values);
+        self.num_values = self.num_module_values;
+        self.function_constants.clear();
+        for id in self.function_metadata.drain(..) {
+            self.metadata_ids.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::{
+        instructions::{Instruction, InstructionKind},
+        module::Module,
+        value::ValueRef,
+    };
+
+    use super::ValueEnumerator;
+
+    #[test]
+    fn named_structs_are_ordered_before_their_elements() {
+        let mut module = Module::new("test");
+        let named = module.types.named_struct("T");
+        let i32 = module.types.i32();
+        let arr = module.types.array(i32, 4);
+        module.types.set_body(named, &[arr], false);
+
+        let enumerator = ValueEnumerator::new(&module);
+        assert_eq!(enumerator.types(), &[named, i32, arr]);
+        assert_eq!(enumerator.type_index(arr), 2);
+    }
+
+    #[test]
+    fn value_numbering() {
+        let mut module = Module::new("test");
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32], false);
+
+        let g = module.add_global("g", i32);
+        let five = module.const_int(i32, 5);
+        module.global_mut(g).initializer = Some(five);
+
+        let f = module.add_function("f", fn_ty);
+        let one = module.const_int(i32, 1);
+        let func = module.function_mut(f);
+        let entry = func.append_block("entry");
+        let arg = func.arg(0);
+        let add = func.push_instruction(
+            entry,
+            Instruction::new(
+                InstructionKind::BinOp {
+                    op: crate::bitcode::constants::BinaryOpCode::Add,
+                    lhs: arg,
+                    rhs: one,
+                    flags: 0,
+                },
+                Some(i32),
+            ),
+        );
+        let add = func.instruction_value(add).unwrap();
+        func.push_instruction(
+            entry,
+            Instruction::new(InstructionKind::Ret(Some(add)), None),
+        );
+
+        let mut enumerator = ValueEnumerator::new(&module);
+        assert_eq!(enumerator.value_id(ValueRef::Global(g)), 0);
+        assert_eq!(enumerator.value_id(ValueRef::Function(f)), 1);
+        assert_eq!(enumerator.value_id(five.kind()), 2);
+        assert_eq!(enumerator.num_module_values(), 3);
+
+        enumerator.incorporate_function(module.function(f));
+        assert_eq!(enumerator.value_id(arg.kind()), 3);
+        assert_eq!(enumerator.value_id(one.kind()), 4);
+        assert_eq!(enumerator.value_id(add.kind()), 5);
+
+        enumerator.purge_function();
+        assert_eq!(enumerator.function_constants(), &[]);
+        assert!(!enumerator.values.contains_key(&one.kind()));
+    }
+}
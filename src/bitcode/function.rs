@@ -0,0 +1,700 @@
+use crate::ir::{
+    function::Function,
+    global::encode_alignment,
+    instructions::{
+        CallInst, DebugRecord, DebugRecordKind, Instruction, InstructionKind, LandingPadClause,
+        OperandBundle, TailCallKind,
+    },
+    metadata::{Metadata, MetadataId},
+    types::{Type, TypeId},
+    value::{Value, ValueRef},
+};
+
+use super::{
+    attributes::encode_signed,
+    constants::{BlockId, CallMarkersFlag, FunctionCide},
+    value_symtab::ValueSymtab,
+    writer::ModuleWriter,
+};
+
+const FUNCTION_ABBR_ID_WIDTH: u32 = 4;
+
+// Bits of the ALLOCA record packed operand.
+const ALLOCA_EXPLICIT_TYPE: u64 = 1 << 6;
+const ALLOCA_ALIGN_UPPER_SHIFT: u64 = 8;
+
+// Bit of the INVOKE record calling convention operand.
+const INVOKE_EXPLICIT_TYPE: u64 = 1 << 13;
+
+impl ModuleWriter<'_> {
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L3920
+    pub(crate) fn write_function(&mut self, function: &Function) {
+        self.enumerator.incorporate_function(function);
+
+        self.stream
+            .enter_block(BlockId::Function as u32, FUNCTION_ABBR_ID_WIDTH);
+        self.stream.write_unabrr_record(
+            FunctionCide::DECLAREBLOCKS as u32,
+            &[function.blocks.len() as u32],
+        );
+
+        let constants = self.enumerator.function_constants().to_vec();
+        self.write_constants(&constants);
+        self.write_function_metadata();
+
+        // The id the next value producing instruction gets.
+        let mut inst_id = self.enumerator.num_module_values()
+            + function.params.len() as u32
+            + constants.len() as u32;
+
+        let mut last_loc = None;
+        for (_, block) in function.blocks() {
+            for id in block.instructions() {
+                let inst = function.instruction(*id);
+                self.write_instruction(inst, inst_id);
+                if inst.ty.is_some() {
+                    inst_id += 1;
+                }
+
+                self.write_debug_loc(inst, &mut last_loc);
+                self.write_debug_records(&inst.debug_records, inst_id);
+            }
+        }
+
+        let mut symtab = ValueSymtab::new();
+        for (i, name) in function.arg_names.iter().enumerate() {
+            if let Some(name) = name {
+                let id = self.enumerator.value_id(ValueRef::Argument(i as u32));
+                symtab.add_value(id, name);
+            }
+        }
+        for (id, block) in function.blocks() {
+            for inst in block.instructions() {
+                if let Some(name) = &function.instruction(*inst).name {
+                    let value_id = self.enumerator.value_id(ValueRef::Instruction(*inst));
+                    symtab.add_value(value_id, name);
+                }
+            }
+
+            if let Some(name) = &block.name {
+                symtab.add_block(id.0, name);
+            }
+        }
+        symtab.write(&mut self.stream);
+        self.write_function_attachments(function);
+
+        self.stream.end_block(BlockId::Function as u32);
+
+        self.enumerator.purge_function();
+    }
+
+    // Repeated locations are written as DEBUG_LOC_AGAIN.
+    fn write_debug_loc(&mut self, inst: &Instruction, last_loc: &mut Option<MetadataId>) {
+        let Some(loc) = inst.debug_loc else {
+            return;
+        };
+
+        if *last_loc == Some(loc) {
+            self.stream
+                .write_unabrr_record(FunctionCide::DEBUG_LOC_AGAIN as u32, &[]);
+            return;
+        }
+
+        let Metadata::Location(loc_md) = self.module.metadata.get(loc) else {
+            panic!("debug locations must be DILocations");
+        };
+        // [line, column, scope, inlined at, implicit code]
+        let record = [
+            loc_md.line,
+            loc_md.column,
+            self.metadata_or_null_id(Some(loc_md.scope)),
+            self.metadata_or_null_id(loc_md.inlined_at),
+            loc_md.implicit_code as u32,
+        ];
+        self.stream
+            .write_unabrr_record(FunctionCide::DEBUG_LOC as u32, &record);
+        *last_loc = Some(loc);
+    }
+
+    // Written after the instruction they precede, so the reader can insert them
+    // before the last instruction read. Metadata ids aren't shifted.
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp
+    fn write_debug_records(&mut self, records: &[DebugRecord], inst_id: u32) {
+        for record in records {
+            let ids: Vec<u64> = record
+                .metadata()
+                .into_iter()
+                .map(|x| self.enumerator.metadata_id(x) as u64)
+                .collect();
+
+            let code = match record {
+                // [loc, label]
+                DebugRecord::Label { .. } => FunctionCide::DEBUG_RECORD_LABEL,
+                // [loc, variable, expression, location]
+                DebugRecord::Variable {
+                    kind: DebugRecordKind::Value,
+                    location,
+                    ..
+                } => {
+                    // Values defined before are written like instruction operands.
+                    if let Metadata::Value(value) = self.module.metadata.get(*location) {
+                        let id = self.enumerator.value_id(value.kind);
+                        if id < inst_id {
+                            let mut record = ids[..3].to_vec();
+                            record.push((inst_id - id) as u64);
+                            self.stream.write_unabrr_record_u64(
+                                FunctionCide::DEBUG_RECORD_VALUE_SIMPLE as u32,
+                                &record,
+                            );
+                            continue;
+                        }
+                    }
+                    FunctionCide::DEBUG_RECORD_VALUE
+                }
+                DebugRecord::Variable {
+                    kind: DebugRecordKind::Declare,
+                    ..
+                } => FunctionCide::DEBUG_RECORD_DECLARE,
+                // [loc, variable, expression, location, assign id, address expression,
+                //  address]
+                DebugRecord::Variable {
+                    kind: DebugRecordKind::Assign { .. },
+                    ..
+                } => FunctionCide::DEBUG_RECORD_ASSIGN,
+            };
+            self.stream.write_unabrr_record_u64(code as u32, &ids);
+        }
+    }
+
+    fn type_index(&self, ty: TypeId) -> u64 {
+        self.enumerator.type_index(ty) as u64
+    }
+
+    /// Pushes the value id relative to the instruction, followed by its type
+    /// when it is a forward reference.
+    fn push_value_and_type(&self, value: Value, inst_id: u32, record: &mut Vec<u64>) {
+        let id = self.enumerator.value_id(value.kind);
+        record.push(inst_id.wrapping_sub(id) as u64);
+
+        if id >= inst_id {
+            record.push(self.type_index(value.ty));
+        }
+    }
+
+    fn push_value(&self, value: Value, inst_id: u32, record: &mut Vec<u64>) {
+        let id = self.enumerator.value_id(value.kind);
+        record.push(inst_id.wrapping_sub(id) as u64);
+    }
+
+    // Phi operands may be forward references, so the relative id is signed.
+    fn push_value_signed(&self, value: Value, inst_id: u32, record: &mut Vec<u64>) {
+        let id = self.enumerator.value_id(value.kind);
+        record.push(encode_signed(inst_id as i64 - id as i64));
+    }
+
+    // Fixed arguments have the type of the parameter, varargs carry their type.
+    fn push_callee_and_args(&self, call: &CallInst, inst_id: u32, record: &mut Vec<u64>) {
+        record.push(self.type_index(call.function_type));
+        self.push_value_and_type(call.callee, inst_id, record);
+
+        let Type::Function(fn_ty) = self.module.types.get(call.function_type) else {
+            unreachable!()
+        };
+        let (fixed, varargs) = call.args.split_at(fn_ty.params.len());
+        for arg in fixed {
+            self.push_value(*arg, inst_id, record);
+        }
+        for arg in varargs {
+            self.push_value_and_type(*arg, inst_id, record);
+        }
+    }
+
+    // Written as records preceding the call.
+    fn write_operand_bundles(&mut self, bundles: &[OperandBundle], inst_id: u32) {
+        for bundle in bundles {
+            let mut record = vec![bundle.tag.0 as u64];
+            for input in &bundle.inputs {
+                self.push_value_and_type(*input, inst_id, &mut record);
+            }
+
+            self.stream
+                .write_unabrr_record_u64(FunctionCide::OPERAND_BUNDLE as u32, &record);
+        }
+    }
+
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L2995
+    fn write_instruction(&mut self, inst: &Instruction, inst_id: u32) {
+        let mut record = Vec::new();
+
+        let code = match &inst.kind {
+            InstructionKind::Ret(value) => {
+                if let Some(value) = value {
+                    self.push_value_and_type(*value, inst_id, &mut record);
+                }
+                FunctionCide::INST_RET
+            }
+            InstructionKind::Br(dest) => {
+                record.push(dest.0 as u64);
+                FunctionCide::INST_BR
+            }
+            InstructionKind::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => {
+                record.extend([then_dest.0 as u64, else_dest.0 as u64]);
+                self.push_value(*cond, inst_id, &mut record);
+                FunctionCide::INST_BR
+            }
+            InstructionKind::Switch {
+                value,
+                default,
+                cases,
+            } => {
+                record.push(self.type_index(value.ty));
+                self.push_value(*value, inst_id, &mut record);
+                record.push(default.0 as u64);
+
+                // Case values are absolute ids.
+                for (value, dest) in cases {
+                    record.push(self.enumerator.value_id(value.kind) as u64);
+                    record.push(dest.0 as u64);
+                }
+                FunctionCide::INST_SWITCH
+            }
+            InstructionKind::IndirectBr { address, dests } => {
+                record.push(self.type_index(address.ty));
+                self.push_value(*address, inst_id, &mut record);
+                record.extend(dests.iter().map(|x| x.0 as u64));
+                FunctionCide::INST_INDIRECTBR
+            }
+            InstructionKind::Unreachable => FunctionCide::INST_UNREACHABLE,
+            InstructionKind::UnOp { op, value, flags } => {
+                self.push_value_and_type(*value, inst_id, &mut record);
+                record.push(*op as u64);
+                if *flags != 0 {
+                    record.push(*flags);
+                }
+                FunctionCide::INST_UNOP
+            }
+            InstructionKind::BinOp {
+                op,
+                lhs,
+                rhs,
+                flags,
+            } => {
+                self.push_value_and_type(*lhs, inst_id, &mut record);
+                self.push_value(*rhs, inst_id, &mut record);
+                record.push(*op as u64);
+                if *flags != 0 {
+                    record.push(*flags);
+                }
+                FunctionCide::INST_BINOP
+            }
+            InstructionKind::Cast { op, value, flags } => {
+                self.push_value_and_type(*value, inst_id, &mut record);
+                record.push(self.type_index(inst.ty.unwrap()));
+                record.push(*op as u64);
+                if *flags != 0 {
+                    record.push(*flags);
+                }
+                FunctionCide::INST_CAST
+            }
+            InstructionKind::ICmp {
+                predicate,
+                lhs,
+                rhs,
+            } => {
+                self.push_value_and_type(*lhs, inst_id, &mut record);
+                self.push_value(*rhs, inst_id, &mut record);
+                record.push(*predicate as u64);
+                FunctionCide::INST_CMP2
+            }
+            InstructionKind::FCmp {
+                predicate,
+                lhs,
+                rhs,
+                flags,
+            } => {
+                self.push_value_and_type(*lhs, inst_id, &mut record);
+                self.push_value(*rhs, inst_id, &mut record);
+                record.push(*predicate as u64);
+                if *flags != 0 {
+                    record.push(*flags);
+                }
+                FunctionCide::INST_CMP2
+            }
+            InstructionKind::Select {
+                cond,
+                then_value,
+                else_value,
+                flags,
+            } => {
+                self.push_value_and_type(*then_value, inst_id, &mut record);
+                self.push_value(*else_value, inst_id, &mut record);
+                self.push_value_and_type(*cond, inst_id, &mut record);
+                if *flags != 0 {
+                    record.push(*flags);
+                }
+                FunctionCide::INST_VSELECT
+            }
+            InstructionKind::ExtractElement { vector, index } => {
+                self.push_value_and_type(*vector, inst_id, &mut record);
+                self.push_value_and_type(*index, inst_id, &mut record);
+                FunctionCide::INST_EXTRACTELT
+            }
+            InstructionKind::InsertElement {
+                vector,
+                element,
+                index,
+            } => {
+                self.push_value_and_type(*vector, inst_id, &mut record);
+                self.push_value(*element, inst_id, &mut record);
+                self.push_value_and_type(*index, inst_id, &mut record);
+                FunctionCide::INST_INSERTELT
+            }
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => {
+                self.push_value_and_type(*lhs, inst_id, &mut record);
+                self.push_value(*rhs, inst_id, &mut record);
+                self.push_value(*mask, inst_id, &mut record);
+                FunctionCide::INST_SHUFFLEVEC
+            }
+            InstructionKind::ExtractValue { aggregate, indices } => {
+                self.push_value_and_type(*aggregate, inst_id, &mut record);
+                record.extend(indices.iter().map(|x| *x as u64));
+                FunctionCide::INST_EXTRACTVAL
+            }
+            InstructionKind::InsertValue {
+                aggregate,
+                value,
+                indices,
+            } => {
+                self.push_value_and_type(*aggregate, inst_id, &mut record);
+                self.push_value_and_type(*value, inst_id, &mut record);
+                record.extend(indices.iter().map(|x| *x as u64));
+                FunctionCide::INST_INSERTVAL
+            }
+            InstructionKind::Phi { incoming, flags } => {
+                record.push(self.type_index(inst.ty.unwrap()));
+                for (value, block) in incoming {
+                    self.push_value_signed(*value, inst_id, &mut record);
+                    record.push(block.0 as u64);
+                }
+                if *flags != 0 {
+                    record.push(*flags);
+                }
+                FunctionCide::INST_PHI
+            }
+            InstructionKind::Alloca {
+                allocated_type,
+                size,
+                align,
+            } => {
+                record.push(self.type_index(*allocated_type));
+                record.push(self.type_index(size.ty));
+                record.push(self.enumerator.value_id(size.kind) as u64);
+
+                let align = encode_alignment(*align);
+                let packed = (align & 0x1f)
+                    | ALLOCA_EXPLICIT_TYPE
+                    | (align >> 5) << ALLOCA_ALIGN_UPPER_SHIFT;
+                record.push(packed);
+
+                let Type::Pointer { address_space } = self.module.types.get(inst.ty.unwrap())
+                else {
+                    unreachable!()
+                };
+                if *address_space != 0 {
+                    record.push(*address_space as u64);
+                }
+                FunctionCide::INST_ALLOCA
+            }
+            InstructionKind::Load {
+                ptr,
+                align,
+                volatile,
+                atomic,
+            } => {
+                self.push_value_and_type(*ptr, inst_id, &mut record);
+                record.push(self.type_index(inst.ty.unwrap()));
+                record.push(encode_alignment(*align));
+                record.push(*volatile as u64);
+
+                match atomic {
+                    Some(atomic) => {
+                        record.push(atomic.ordering as u64);
+                        record.push(atomic.sync_scope.0 as u64);
+                        FunctionCide::INST_LOADATOMIC
+                    }
+                    None => FunctionCide::INST_LOAD,
+                }
+            }
+            InstructionKind::Store {
+                ptr,
+                value,
+                align,
+                volatile,
+                atomic,
+            } => {
+                self.push_value_and_type(*ptr, inst_id, &mut record);
+                self.push_value_and_type(*value, inst_id, &mut record);
+                record.push(encode_alignment(*align));
+                record.push(*volatile as u64);
+
+                match atomic {
+                    Some(atomic) => {
+                        record.push(atomic.ordering as u64);
+                        record.push(atomic.sync_scope.0 as u64);
+                        FunctionCide::INST_STOREATOMIC
+                    }
+                    None => FunctionCide::INST_STORE,
+                }
+            }
+            InstructionKind::Fence {
+                ordering,
+                sync_scope,
+            } => {
+                record.push(*ordering as u64);
+                record.push(sync_scope.0 as u64);
+                FunctionCide::INST_FENCE
+            }
+            InstructionKind::CmpXchg {
+                ptr,
+                cmp,
+                new,
+                align,
+                volatile,
+                weak,
+                success_ordering,
+                failure_ordering,
+                sync_scope,
+            } => {
+                self.push_value_and_type(*ptr, inst_id, &mut record);
+                self.push_value_and_type(*cmp, inst_id, &mut record);
+                self.push_value(*new, inst_id, &mut record);
+                record.push(*volatile as u64);
+                record.push(*success_ordering as u64);
+                record.push(sync_scope.0 as u64);
+                record.push(*failure_ordering as u64);
+                record.push(*weak as u64);
+                record.push(encode_alignment(*align));
+                FunctionCide::INST_CMPXCHG
+            }
+            InstructionKind::AtomicRmw {
+                op,
+                ptr,
+                value,
+                align,
+                volatile,
+                ordering,
+                sync_scope,
+            } => {
+                self.push_value_and_type(*ptr, inst_id, &mut record);
+                self.push_value_and_type(*value, inst_id, &mut record);
+                record.push(*op as u64);
+                record.push(*volatile as u64);
+                record.push(*ordering as u64);
+                record.push(sync_scope.0 as u64);
+                record.push(encode_alignment(*align));
+                FunctionCide::INST_ATOMICRMW
+            }
+            InstructionKind::GetElementPtr {
+                source_type,
+                ptr,
+                indices,
+                flags,
+            } => {
+                record.push(*flags);
+                record.push(self.type_index(*source_type));
+                self.push_value_and_type(*ptr, inst_id, &mut record);
+                for index in indices {
+                    self.push_value_and_type(*index, inst_id, &mut record);
+                }
+                FunctionCide::INST_GEP
+            }
+            InstructionKind::Call(call) => {
+                self.write_operand_bundles(&call.bundles, inst_id);
+
+                let mut markers = call.calling_conv.code() << CallMarkersFlag::CALL_CCONV as u64
+                    | 1 << CallMarkersFlag::CALL_EXPLICIT_TYPE as u64;
+                markers |= match call.tail {
+                    TailCallKind::None => 0,
+                    TailCallKind::Tail => 1 << CallMarkersFlag::CALL_TAIL as u64,
+                    TailCallKind::MustTail => {
+                        1 << CallMarkersFlag::CALL_TAIL as u64
+                            | 1 << CallMarkersFlag::CALL_MUSTTAIL as u64
+                    }
+                    TailCallKind::NoTail => 1 << CallMarkersFlag::CALL_NOTAIL as u64,
+                };
+                if call.flags != 0 {
+                    markers |= 1 << CallMarkersFlag::CALL_FMF as u64;
+                }
+
+                record.push(self.enumerator.attributes().get(&call.attributes) as u64);
+                record.push(markers);
+                if call.flags != 0 {
+                    record.push(call.flags);
+                }
+                self.push_callee_and_args(call, inst_id, &mut record);
+                FunctionCide::INST_CALL
+            }
+            InstructionKind::Invoke {
+                call,
+                normal_dest,
+                unwind_dest,
+            } => {
+                self.write_operand_bundles(&call.bundles, inst_id);
+
+                record.push(self.enumerator.attributes().get(&call.attributes) as u64);
+                record.push(call.calling_conv.code() | INVOKE_EXPLICIT_TYPE);
+                record.extend([normal_dest.0 as u64, unwind_dest.0 as u64]);
+                self.push_callee_and_args(call, inst_id, &mut record);
+                FunctionCide::INST_INVOKE
+            }
+            InstructionKind::Resume(value) => {
+                self.push_value_and_type(*value, inst_id, &mut record);
+                FunctionCide::INST_RESUME
+            }
+            InstructionKind::LandingPad { cleanup, clauses } => {
+                record.push(self.type_index(inst.ty.unwrap()));
+                record.push(*cleanup as u64);
+                record.push(clauses.len() as u64);
+                for clause in clauses {
+                    record.push(match clause {
+                        LandingPadClause::Catch(_) => 0,
+                        LandingPadClause::Filter(_) => 1,
+                    });
+                    self.push_value_and_type(clause.value(), inst_id, &mut record);
+                }
+                FunctionCide::INST_LANDINGPAD
+            }
+            InstructionKind::CatchSwitch {
+                parent_pad,
+                handlers,
+                unwind_dest,
+            } => {
+                self.push_value(*parent_pad, inst_id, &mut record);
+                record.push(handlers.len() as u64);
+                record.extend(handlers.iter().map(|x| x.0 as u64));
+                record.extend(unwind_dest.map(|x| x.0 as u64));
+                FunctionCide::INST_CATCHSWITCH
+            }
+            InstructionKind::CatchPad {
+                catch_switch: pad,
+                args,
+            }
+            | InstructionKind::CleanupPad {
+                parent_pad: pad,
+                args,
+            } => {
+                self.push_value(*pad, inst_id, &mut record);
+                record.push(args.len() as u64);
+                for arg in args {
+                    self.push_value_and_type(*arg, inst_id, &mut record);
+                }
+                if matches!(inst.kind, InstructionKind::CatchPad { .. }) {
+                    FunctionCide::INST_CATCHPAD
+                } else {
+                    FunctionCide::INST_CLEANUPPAD
+                }
+            }
+            InstructionKind::CatchRet { catch_pad, dest } => {
+                self.push_value(*catch_pad, inst_id, &mut record);
+                record.push(dest.0 as u64);
+                FunctionCide::INST_CATCHRET
+            }
+            InstructionKind::CleanupRet {
+                cleanup_pad,
+                unwind_dest,
+            } => {
+                self.push_value(*cleanup_pad, inst_id, &mut record);
+                record.extend(unwind_dest.map(|x| x.0 as u64));
+                FunctionCide::INST_CLEANUPRET
+            }
+            InstructionKind::VaArg { list } => {
+                record.push(self.type_index(list.ty));
+                self.push_value(*list, inst_id, &mut record);
+                record.push(self.type_index(inst.ty.unwrap()));
+                FunctionCide::INST_VAARG
+            }
+            InstructionKind::Freeze(value) => {
+                self.push_value_and_type(*value, inst_id, &mut record);
+                FunctionCide::INST_FREEZE
+            }
+        };
+
+        self.stream.write_unabrr_record_u64(code as u32, &record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::{BinaryOpCode, BlockId, FunctionCide},
+        bitstream::reader::read,
+        ir::{
+            instructions::{Instruction, InstructionKind},
+            module::Module,
+        },
+    };
+
+    // The use of %x comes before its definition so it carries an explicit type.
+    #[test]
+    fn forward_references() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32], false);
+        let one = module.const_int(i32, 1);
+
+        let f = module.add_function("f", fn_ty);
+        let func = module.function_mut(f);
+        let entry = func.append_block("entry");
+        let uses = func.append_block("uses");
+        let defs = func.append_block("defs");
+        let a = func.arg(0);
+
+        let x = func.push_instruction(
+            defs,
+            Instruction::new(
+                InstructionKind::BinOp {
+                    op: BinaryOpCode::Add,
+                    lhs: a,
+                    rhs: one,
+                    flags: 0,
+                },
+                Some(i32),
+            ),
+        );
+        let x = func.instruction_value(x).unwrap();
+        func.set_name(x, "x");
+        func.push_instruction(defs, Instruction::new(InstructionKind::Br(uses), None));
+
+        func.push_instruction(entry, Instruction::new(InstructionKind::Br(defs), None));
+        let r = func.push_instruction(
+            uses,
+            Instruction::new(
+                InstructionKind::BinOp {
+                    op: BinaryOpCode::Mul,
+                    lhs: x,
+                    rhs: x,
+                    flags: 0,
+                },
+                Some(i32),
+            ),
+        );
+        let r = func.instruction_value(r).unwrap();
+        func.push_instruction(uses, Instruction::new(InstructionKind::Ret(Some(r)), None));
+
+        // %r is the value 3 and %x the value 4, so %x is -1 relative to %r and
+        // wraps around as a 32-bit int. Only the first operand has a type.
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let body = module_block.block(BlockId::Function as u32);
+        let binops = body.records(FunctionCide::INST_BINOP as u32);
+        let forward = u32::MAX as u64;
+        let mul = BinaryOpCode::Mul as u64;
+        assert_eq!(binops[0].ops, [forward, 0, forward, mul]);
+        // The argument is the value 1 and the constant the value 2.
+        assert_eq!(binops[1].ops, [3, 2, BinaryOpCode::Add as u64]);
+    }
+}
@@ -0,0 +1,988 @@
+use crate::{
+    bitstream::{
+        abbrv::Abbr,
+        bitstream_writer::BitStreamWriter,
+        operand::{FixedValue, OperandDef, OperandValue},
+    },
+    ir::{
+        function::Function,
+        metadata::{Attachments, Metadata, MetadataId},
+        value::ValueRef,
+    },
+};
+
+use super::{
+    attributes::encode_signed,
+    constants::{vbr_widths, BlockId, MetadataCode},
+    value_symtab::fixed_chars,
+    writer::{vbr, ModuleWriter},
+};
+
+const METADATA_ABBR_ID_WIDTH: u32 = 4;
+const METADATA_KIND_ABBR_ID_WIDTH: u32 = 3;
+const METADATA_ATTACHMENT_ABBR_ID_WIDTH: u32 = 3;
+const FUNCTION_METADATA_ABBR_ID_WIDTH: u32 = 3;
+
+// Modules with more non-string metadata get an index of the records, letting LLVM
+// load them lazily. Same as the default of `-bitcode-mdindex-threshold`.
+const METADATA_INDEX_THRESHOLD: usize = 25;
+
+// Bits of the first operand of SUBPROGRAM records, telling apart the older layouts.
+const SUBPROGRAM_HAS_UNIT: u64 = 1 << 1;
+const SUBPROGRAM_HAS_SP_FLAGS: u64 = 1 << 2;
+// Same for the other records: type references are nodes and not strings, subranges
+// have bounds nodes, enumerators have arbitrary precision values, local variables
+// have an alignment, and so on.
+const TYPE_HAS_NO_OLD_TYPE_REFS: u64 = 1 << 1;
+const SUBRANGE_VERSION_2: u64 = 2 << 1;
+const ENUMERATOR_IS_BIG_INT: u64 = 1 << 2;
+const LOCAL_VAR_HAS_ALIGNMENT: u64 = 1 << 1;
+const GLOBAL_VAR_VERSION_2: u64 = 2 << 1;
+const EXPRESSION_VERSION_3: u64 = 3 << 1;
+
+/// Pushes the active 64-bit words of `value` truncated to `bit_width` bits, each
+/// as a signed VBR value.
+fn push_wide_int(record: &mut Vec<u64>, value: i128, bit_width: u32) {
+    assert!(
+        (1..=128).contains(&bit_width),
+        "enumerators have at most 128 bits"
+    );
+    let value = value as u128 & (u128::MAX >> (128 - bit_width));
+    let words = [value as u64, (value >> 64) as u64];
+    let active = if words[1] != 0 { 2 } else { 1 };
+    for word in &words[..active] {
+        record.push(encode_signed(*word as i64));
+    }
+}
+
+impl ModuleWriter<'_> {
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp
+    pub(crate) fn write_metadata_kinds(&mut self) {
+        self.stream
+            .enter_block(BlockId::MetadataKind as u32, METADATA_KIND_ABBR_ID_WIDTH);
+
+        for (kind, name) in self.module.md_kinds() {
+            // [id, name chars]
+            let record: Vec<u32> = std::iter::once(kind.0)
+                .chain(name.bytes().map(u32::from))
+                .collect();
+            self.stream
+                .write_unabrr_record(MetadataCode::KIND as u32, &record);
+        }
+
+        self.stream.end_block(BlockId::MetadataKind as u32);
+    }
+
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp
+    pub(crate) fn write_module_metadata(&mut self) {
+        let metadata = self.enumerator.metadata().to_vec();
+        if metadata.is_empty() && self.module.named_metadata.is_empty() {
+            return;
+        }
+
+        self.stream
+            .enter_block(BlockId::Metadata as u32, METADATA_ABBR_ID_WIDTH);
+
+        let (strings, rest) = metadata.split_at(self.enumerator.num_md_strings() as usize);
+        self.write_metadata_strings(strings);
+        if rest.len() > METADATA_INDEX_THRESHOLD {
+            self.write_indexed_metadata(rest);
+        } else {
+            for id in rest {
+                self.write_metadata(*id);
+            }
+        }
+        self.write_named_metadata();
+
+        // Definitions have their attachments in their function block.
+        let module = self.module;
+        for (id, function) in module.functions() {
+            if function.is_declaration() && !function.metadata.is_empty() {
+                self.write_decl_attachment(ValueRef::Function(id), &function.metadata);
+            }
+        }
+        for (id, global) in module.globals() {
+            if !global.metadata.is_empty() {
+                self.write_decl_attachment(ValueRef::Global(id), &global.metadata);
+            }
+        }
+
+        self.stream.end_block(BlockId::Metadata as u32);
+    }
+
+    // The records are preceded by the offset of the index from the end of the
+    // offset record, backpatched once the records are written. The index holds the
+    // position of each record as the delta from the previous one.
+    fn write_indexed_metadata(&mut self, metadata: &[MetadataId]) {
+        self.stream.define_abbr(&Abbr::new(
+            "index offset",
+            &[
+                OperandDef::Literal(MetadataCode::INDEX_OFFSET as u32),
+                OperandDef::Fixed(32),
+                OperandDef::Fixed(32),
+            ],
+        ));
+        self.stream.define_abbr(&Abbr::new(
+            "index",
+            &[
+                OperandDef::Literal(MetadataCode::INDEX as u32),
+                OperandDef::Array(Box::new(OperandDef::Vbr(vbr_widths::METADATA_INDEX))),
+            ],
+        ));
+
+        // [offset low, offset high]
+        let placeholder = OperandValue::Fixed(FixedValue {
+            value: 0,
+            width: 32,
+        });
+        self.stream
+            .write_record("index offset", &[placeholder.clone(), placeholder]);
+        let start = self.stream.bit_offset();
+
+        let mut previous = start;
+        let mut index = Vec::with_capacity(metadata.len());
+        for id in metadata {
+            let offset = self.stream.bit_offset();
+            index.push(vbr((offset - previous) as u64, vbr_widths::METADATA_INDEX));
+            previous = offset;
+            self.write_metadata(*id);
+        }
+
+        let offset = self.stream.bit_offset() - start;
+        self.stream.backpatch_u64(start - 64, offset as u64);
+        // [bitpos]
+        self.stream
+            .write_record("index", &[OperandValue::Array(index)]);
+    }
+
+    // Local values and arg lists, the rest of the metadata is written with the
+    // module.
+    pub(crate) fn write_function_metadata(&mut self) {
+        let metadata = self.enumerator.function_metadata().to_vec();
+        if metadata.is_empty() {
+            return;
+        }
+
+        self.stream
+            .enter_block(BlockId::Metadata as u32, FUNCTION_METADATA_ABBR_ID_WIDTH);
+        for id in metadata {
+            self.write_metadata(id);
+        }
+        self.stream.end_block(BlockId::Metadata as u32);
+    }
+
+    // All the strings in one record, the blob holds their VBR6 lengths padded to 32
+    // bits followed by the characters.
+    fn write_metadata_strings(&mut self, strings: &[MetadataId]) {
+        if strings.is_empty() {
+            return;
+        }
+
+        self.stream.define_abbr(&Abbr::new(
+            "strings",
+            &[
+                OperandDef::Literal(MetadataCode::STRINGS as u32),
+                OperandDef::Vbr(vbr_widths::METADATA_STRING_COUNT),
+                OperandDef::Vbr(vbr_widths::METADATA_STRING_OFF),
+                OperandDef::Blob,
+            ],
+        ));
+
+        let mut lengths = BitStreamWriter::new();
+        let mut chars = Vec::new();
+        for id in strings {
+            let Metadata::String(value) = self.module.metadata.get(*id) else {
+                unreachable!("strings are numbered first");
+            };
+            lengths.write_vbr(value.len() as u32, 6);
+            chars.extend_from_slice(value.as_bytes());
+        }
+        lengths.align(32);
+
+        let mut blob = lengths.buffer;
+        let offset = blob.len() as u64;
+        blob.extend(chars);
+
+        self.stream.write_record(
+            "strings",
+            &[
+                vbr(strings.len() as u64, vbr_widths::METADATA_STRING_COUNT),
+                vbr(offset, vbr_widths::METADATA_STRING_OFF),
+                OperandValue::Blob(blob),
+            ],
+        );
+    }
+
+    fn write_metadata(&mut self, id: MetadataId) {
+        match self.module.metadata.get(id) {
+            Metadata::String(_) => unreachable!("strings are written in bulk"),
+            Metadata::Value(value) => {
+                let record = [
+                    self.enumerator.type_index(value.ty),
+                    self.enumerator.value_id(value.kind),
+                ];
+                self.stream
+                    .write_unabrr_record(MetadataCode::VALUE as u32, &record);
+            }
+            Metadata::Node(node) => {
+                let code = if node.distinct {
+                    MetadataCode::DISTINCT_NODE
+                } else {
+                    MetadataCode::NODE
+                };
+                let record: Vec<u32> = node
+                    .operands
+                    .iter()
+                    .map(|x| self.metadata_or_null_id(*x))
+                    .collect();
+                self.stream.write_unabrr_record(code as u32, &record);
+            }
+            Metadata::File(x) => {
+                let (kind, checksum) = match x.checksum {
+                    Some((kind, value)) => (kind as u64, Some(value)),
+                    None => (0, None),
+                };
+                let mut record = vec![
+                    x.distinct as u64,
+                    self.md(x.filename),
+                    self.md(x.directory),
+                    kind,
+                    self.md(checksum),
+                ];
+                if x.source.is_some() {
+                    record.push(self.md(x.source));
+                }
+                self.write_md_record(MetadataCode::FILE, &record);
+            }
+            Metadata::CompileUnit(x) => {
+                let record = [
+                    1,
+                    x.language as u64,
+                    self.md(x.file),
+                    self.md(x.producer),
+                    x.optimized as u64,
+                    self.md(x.flags),
+                    x.runtime_version as u64,
+                    self.md(x.split_debug_filename),
+                    x.emission_kind as u64,
+                    self.md(x.enum_types),
+                    self.md(x.retained_types),
+                    // The subprograms, they point to their unit instead.
+                    0,
+                    self.md(x.global_variables),
+                    self.md(x.imported_entities),
+                    x.dwo_id,
+                    self.md(x.macros),
+                    x.split_debug_inlining as u64,
+                    x.debug_info_for_profiling as u64,
+                    x.name_table_kind as u64,
+                    x.ranges_base_address as u64,
+                    self.md(x.sysroot),
+                    self.md(x.sdk),
+                ];
+                self.write_md_record(MetadataCode::COMPILE_UNIT, &record);
+            }
+            Metadata::Subprogram(x) => {
+                let record = [
+                    x.distinct as u64 | SUBPROGRAM_HAS_UNIT | SUBPROGRAM_HAS_SP_FLAGS,
+                    self.md(x.scope),
+                    self.md(x.name),
+                    self.md(x.linkage_name),
+                    self.md(x.file),
+                    x.line as u64,
+                    self.md(x.ty),
+                    x.scope_line as u64,
+                    self.md(x.containing_type),
+                    x.sp_flags as u64,
+                    x.virtual_index as u64,
+                    x.flags as u64,
+                    self.md(x.unit),
+                    self.md(x.template_params),
+                    self.md(x.declaration),
+                    self.md(x.retained_nodes),
+                    x.this_adjustment as i64 as u64,
+                    self.md(x.thrown_types),
+                    self.md(x.annotations),
+                    self.md(x.target_func_name),
+                ];
+                self.write_md_record(MetadataCode::SUBPROGRAM, &record);
+            }
+            Metadata::LexicalBlock(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.scope),
+                    self.md(x.file),
+                    x.line as u64,
+                    x.column as u64,
+                ];
+                self.write_md_record(MetadataCode::LEXICAL_BLOCK, &record);
+            }
+            Metadata::LexicalBlockFile(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.scope),
+                    self.md(x.file),
+                    x.discriminator as u64,
+                ];
+                self.write_md_record(MetadataCode::LEXICAL_BLOCK_FILE, &record);
+            }
+            Metadata::Location(x) => {
+                // The scope can't be null so it isn't shifted.
+                let record = [
+                    x.distinct as u64,
+                    x.line as u64,
+                    x.column as u64,
+                    self.enumerator.metadata_id(x.scope) as u64,
+                    self.md(x.inlined_at),
+                    x.implicit_code as u64,
+                ];
+                self.write_md_record(MetadataCode::LOCATION, &record);
+            }
+            Metadata::BasicType(x) => {
+                let record = [
+                    x.distinct as u64,
+                    x.tag as u64,
+                    self.md(x.name),
+                    x.size_in_bits,
+                    x.align_in_bits as u64,
+                    x.encoding as u64,
+                    x.flags as u64,
+                ];
+                self.write_md_record(MetadataCode::BASIC_TYPE, &record);
+            }
+            Metadata::DerivedType(x) => {
+                let mut record = vec![
+                    x.distinct as u64,
+                    x.tag as u64,
+                    self.md(x.name),
+                    self.md(x.file),
+                    x.line as u64,
+                    self.md(x.scope),
+                    self.md(x.base_type),
+                    x.size_in_bits,
+                    x.align_in_bits as u64,
+                    x.offset_in_bits,
+                    x.flags as u64,
+                    self.md(x.extra_data),
+                    // Shifted, 0 is no address space.
+                    x.dwarf_address_space.map_or(0, |x| x as u64 + 1),
+                    self.md(x.annotations),
+                ];
+                // The last operand is optional, older readers reject it.
+                if x.ptr_auth_data != 0 {
+                    record.push(x.ptr_auth_data as u64);
+                }
+                self.write_md_record(MetadataCode::DERIVED_TYPE, &record);
+            }
+            Metadata::CompositeType(x) => {
+                let record = [
+                    x.distinct as u64 | TYPE_HAS_NO_OLD_TYPE_REFS,
+                    x.tag as u64,
+                    self.md(x.name),
+                    self.md(x.file),
+                    x.line as u64,
+                    self.md(x.scope),
+                    self.md(x.base_type),
+                    x.size_in_bits,
+                    x.align_in_bits as u64,
+                    x.offset_in_bits,
+                    x.flags as u64,
+                    self.md(x.elements),
+                    x.runtime_lang as u64,
+                    self.md(x.vtable_holder),
+                    self.md(x.template_params),
+                    self.md(x.identifier),
+                    self.md(x.discriminator),
+                    self.md(x.data_location),
+                    self.md(x.associated),
+                    self.md(x.allocated),
+                    self.md(x.rank),
+                    self.md(x.annotations),
+                ];
+                self.write_md_record(MetadataCode::COMPOSITE_TYPE, &record);
+            }
+            Metadata::SubroutineType(x) => {
+                let record = [
+                    x.distinct as u64 | TYPE_HAS_NO_OLD_TYPE_REFS,
+                    x.flags as u64,
+                    self.md(x.types),
+                    x.cc as u64,
+                ];
+                self.write_md_record(MetadataCode::SUBROUTINE_TYPE, &record);
+            }
+            Metadata::Subrange(x) => {
+                let record = [
+                    x.distinct as u64 | SUBRANGE_VERSION_2,
+                    self.md(x.count),
+                    self.md(x.lower_bound),
+                    self.md(x.upper_bound),
+                    self.md(x.stride),
+                ];
+                self.write_md_record(MetadataCode::SUBRANGE, &record);
+            }
+            Metadata::GenericSubrange(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.count),
+                    self.md(x.lower_bound),
+                    self.md(x.upper_bound),
+                    self.md(x.stride),
+                ];
+                self.write_md_record(MetadataCode::GENERIC_SUBRANGE, &record);
+            }
+            Metadata::Enumerator(x) => {
+                let mut record = vec![
+                    x.distinct as u64 | (x.unsigned as u64) << 1 | ENUMERATOR_IS_BIG_INT,
+                    x.bit_width as u64,
+                    self.md(x.name),
+                ];
+                push_wide_int(&mut record, x.value, x.bit_width);
+                self.write_md_record(MetadataCode::ENUMERATOR, &record);
+            }
+            Metadata::StringType(x) => {
+                let record = [
+                    x.distinct as u64,
+                    x.tag as u64,
+                    self.md(x.name),
+                    self.md(x.string_length),
+                    self.md(x.string_length_exp),
+                    self.md(x.string_location_exp),
+                    x.size_in_bits,
+                    x.align_in_bits as u64,
+                    x.encoding as u64,
+                ];
+                self.write_md_record(MetadataCode::STRING_TYPE, &record);
+            }
+            Metadata::TemplateTypeParameter(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.name),
+                    self.md(x.ty),
+                    x.is_default as u64,
+                ];
+                self.write_md_record(MetadataCode::TEMPLATE_TYPE, &record);
+            }
+            Metadata::TemplateValueParameter(x) => {
+                let record = [
+                    x.distinct as u64,
+                    x.tag as u64,
+                    self.md(x.name),
+                    self.md(x.ty),
+                    x.is_default as u64,
+                    self.md(x.value),
+                ];
+                self.write_md_record(MetadataCode::TEMPLATE_VALUE, &record);
+            }
+            Metadata::LocalVariable(x) => {
+                let record = [
+                    x.distinct as u64 | LOCAL_VAR_HAS_ALIGNMENT,
+                    self.md(x.scope),
+                    self.md(x.name),
+                    self.md(x.file),
+                    x.line as u64,
+                    self.md(x.ty),
+                    x.arg as u64,
+                    x.flags as u64,
+                    x.align_in_bits as u64,
+                    self.md(x.annotations),
+                ];
+                self.write_md_record(MetadataCode::LOCAL_VAR, &record);
+            }
+            Metadata::GlobalVariable(x) => {
+                let record = [
+                    x.distinct as u64 | GLOBAL_VAR_VERSION_2,
+                    self.md(x.scope),
+                    self.md(x.name),
+                    self.md(x.linkage_name),
+                    self.md(x.file),
+                    x.line as u64,
+                    self.md(x.ty),
+                    x.local_to_unit as u64,
+                    x.definition as u64,
+                    self.md(x.static_data_member_declaration),
+                    self.md(x.template_params),
+                    x.align_in_bits as u64,
+                    self.md(x.annotations),
+                ];
+                self.write_md_record(MetadataCode::GLOBAL_VAR, &record);
+            }
+            Metadata::GlobalVariableExpression(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(Some(x.variable)),
+                    self.md(Some(x.expression)),
+                ];
+                self.write_md_record(MetadataCode::GLOBAL_VAR_EXPR, &record);
+            }
+            Metadata::Label(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.scope),
+                    self.md(x.name),
+                    self.md(x.file),
+                    x.line as u64,
+                ];
+                self.write_md_record(MetadataCode::LABEL, &record);
+            }
+            Metadata::Expression(x) => {
+                let mut record = vec![x.distinct as u64 | EXPRESSION_VERSION_3];
+                record.extend(&x.elements);
+                self.write_md_record(MetadataCode::EXPRESSION, &record);
+            }
+            Metadata::ArgList(x) => {
+                // Values can't be null so they aren't shifted.
+                let record: Vec<u64> = x
+                    .args
+                    .iter()
+                    .map(|x| self.enumerator.metadata_id(*x) as u64)
+                    .collect();
+                self.write_md_record(MetadataCode::ARG_LIST, &record);
+            }
+            Metadata::AssignId => self.write_md_record(MetadataCode::ASSIGN_ID, &[1]),
+            Metadata::Namespace(x) => {
+                let record = [
+                    x.distinct as u64 | (x.export_symbols as u64) << 1,
+                    self.md(x.scope),
+                    self.md(x.name),
+                ];
+                self.write_md_record(MetadataCode::NAMESPACE, &record);
+            }
+            Metadata::Module(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.file),
+                    self.md(x.scope),
+                    self.md(x.name),
+                    self.md(x.configuration_macros),
+                    self.md(x.include_path),
+                    self.md(x.api_notes_file),
+                    x.line as u64,
+                    x.is_decl as u64,
+                ];
+                self.write_md_record(MetadataCode::MODULE, &record);
+            }
+            Metadata::ImportedEntity(x) => {
+                let record = [
+                    x.distinct as u64,
+                    x.tag as u64,
+                    self.md(x.scope),
+                    self.md(x.entity),
+                    x.line as u64,
+                    self.md(x.name),
+                    self.md(x.file),
+                    self.md(x.elements),
+                ];
+                self.write_md_record(MetadataCode::IMPORTED_ENTITY, &record);
+            }
+            Metadata::Macro(x) => {
+                let record = [
+                    x.distinct as u64,
+                    x.macro_type as u64,
+                    x.line as u64,
+                    self.md(x.name),
+                    self.md(x.value),
+                ];
+                self.write_md_record(MetadataCode::MACRO, &record);
+            }
+            Metadata::MacroFile(x) => {
+                let record = [
+                    x.distinct as u64,
+                    x.macro_type as u64,
+                    x.line as u64,
+                    self.md(x.file),
+                    self.md(x.elements),
+                ];
+                self.write_md_record(MetadataCode::MACRO_FILE, &record);
+            }
+            Metadata::CommonBlock(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.scope),
+                    self.md(x.declaration),
+                    self.md(x.name),
+                    self.md(x.file),
+                    x.line as u64,
+                ];
+                self.write_md_record(MetadataCode::COMMON_BLOCK, &record);
+            }
+            Metadata::ObjCProperty(x) => {
+                let record = [
+                    x.distinct as u64,
+                    self.md(x.name),
+                    self.md(x.file),
+                    x.line as u64,
+                    self.md(x.getter_name),
+                    self.md(x.setter_name),
+                    x.attributes as u64,
+                    self.md(x.ty),
+                ];
+                self.write_md_record(MetadataCode::OBJC_PROPERTY, &record);
+            }
+        }
+    }
+
+    fn md(&self, id: Option<MetadataId>) -> u64 {
+        self.metadata_or_null_id(id) as u64
+    }
+
+    fn write_md_record(&mut self, code: MetadataCode, record: &[u64]) {
+        self.stream.write_unabrr_record_u64(code as u32, record);
+    }
+
+    fn write_decl_attachment(&mut self, value: ValueRef, attachments: &Attachments) {
+        // [value id, n x [kind, node]]
+        let mut record = vec![self.enumerator.value_id(value)];
+        self.push_attachments(attachments, &mut record);
+        self.stream
+            .write_unabrr_record(MetadataCode::GLOBAL_DECL_ATTACHMENT as u32, &record);
+    }
+
+    // Sorted by kind like LLVM, whatever the order they were attached in.
+    fn push_attachments(&self, attachments: &Attachments, record: &mut Vec<u32>) {
+        let mut attachments: Vec<_> = attachments.iter().collect();
+        attachments.sort_by_key(|(kind, _)| kind.0);
+        for (kind, node) in attachments {
+            record.push(kind.0);
+            record.push(self.enumerator.metadata_id(node));
+        }
+    }
+
+    // Instructions are referenced by their position in the function, counting the
+    // ones without a value.
+    pub(crate) fn write_function_attachments(&mut self, function: &Function) {
+        let has_attachments = !function.metadata.is_empty()
+            || function.instructions.iter().any(|x| !x.metadata.is_empty());
+        if !has_attachments {
+            return;
+        }
+
+        self.stream.enter_block(
+            BlockId::MetadataAttachment as u32,
+            METADATA_ATTACHMENT_ABBR_ID_WIDTH,
+        );
+
+        if !function.metadata.is_empty() {
+            // [n x [kind, node]]
+            let mut record = Vec::new();
+            self.push_attachments(&function.metadata, &mut record);
+            self.stream
+                .write_unabrr_record(MetadataCode::ATTACHMENT as u32, &record);
+        }
+
+        let instructions = function
+            .blocks()
+            .flat_map(|(_, block)| block.instructions());
+        for (i, id) in instructions.enumerate() {
+            let inst = function.instruction(*id);
+            if inst.metadata.is_empty() {
+                continue;
+            }
+
+            // [instruction, n x [kind, node]]
+            let mut record = vec![i as u32];
+            self.push_attachments(&inst.metadata, &mut record);
+            self.stream
+                .write_unabrr_record(MetadataCode::ATTACHMENT as u32, &record);
+        }
+
+        self.stream.end_block(BlockId::MetadataAttachment as u32);
+    }
+
+    // Metadata operands are numbered from 1, 0 meaning null.
+    pub(crate) fn metadata_or_null_id(&self, id: Option<MetadataId>) -> u32 {
+        id.map_or(0, |x| self.enumerator.metadata_id(x) + 1)
+    }
+
+    fn write_named_metadata(&mut self) {
+        if self.module.named_metadata.is_empty() {
+            return;
+        }
+
+        self.stream.define_abbr(&Abbr::new(
+            "name",
+            &[
+                OperandDef::Literal(MetadataCode::NAME as u32),
+                OperandDef::Array(Box::new(OperandDef::Fixed(8))),
+            ],
+        ));
+
+        let module = self.module;
+        for named in module.named_metadatas() {
+            self.stream
+                .write_record("name", &[fixed_chars(&named.name, 8)]);
+
+            let record: Vec<u32> = named
+                .operands
+                .iter()
+                .map(|x| self.enumerator.metadata_id(*x))
+                .collect();
+            self.stream
+                .write_unabrr_record(MetadataCode::NAMED_NODE as u32, &record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::{BlockId, MetadataCode},
+        bitstream::reader::read,
+        ir::{
+            builder::Builder,
+            debug_info::{DIFlag, DIGlobalVariable, DIGlobalVariableExpression, EmissionKind},
+            di_builder::DIBuilder,
+            dwarf::{DW_ATE_SIGNED, DW_LANG_C_PLUS_PLUS_14},
+            metadata::{Metadata, MetadataKind},
+            module::Module,
+        },
+    };
+
+    use super::METADATA_ABBR_ID_WIDTH;
+
+    #[test]
+    fn write_metadata() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let g = module.add_global("g", i32);
+
+        let ident = module.md_string("compiler 1.0");
+        let node = module.md_node(&[Some(ident)]);
+        module.add_named_metadata_operand("llvm.ident", node);
+
+        let one = module.const_int(i32, 1);
+        let one = module.md_value(one);
+        let g = module.global_value(g);
+        let g = module.md_value(g);
+        let name = module.md_string("name");
+        let inner = module.md_node(&[Some(name), Some(one)]);
+        let distinct = module.md_distinct_node(&[Some(inner), None, Some(g)]);
+        module.add_named_metadata_operand("test", distinct);
+        module.add_named_metadata_operand("test", inner);
+
+        // Equal nodes are merged, distinct ones aren't.
+        assert_eq!(module.md_node(&[Some(name), Some(one)]), inner);
+        assert_ne!(
+            module.md_distinct_node(&[Some(inner), None, Some(g)]),
+            distinct
+        );
+
+        let bitcode = read(&module.to_bitcode());
+        let metadata = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Metadata as u32);
+        let strings = metadata.record(MetadataCode::STRINGS as u32);
+        assert_eq!(strings.ops[0], 2);
+        let chars = strings.blob.as_deref().unwrap();
+        assert!(chars.ends_with(b"compiler 1.0name"));
+        // The strings are the metadata 0 and 1, then come the values as
+        // [type, value] and the nodes with their operands shifted by one.
+        let values = metadata.ops(MetadataCode::VALUE as u32);
+        assert_eq!(values, [[0, 1], [1, 0]]);
+        let distinct = metadata.record(MetadataCode::DISTINCT_NODE as u32);
+        assert_eq!(distinct.ops, [7, 0, 4]);
+        let nodes = metadata.ops(MetadataCode::NODE as u32);
+        assert_eq!(nodes, [vec![1], vec![2, 3]]);
+
+        let names: Vec<_> = metadata
+            .records(MetadataCode::NAME as u32)
+            .iter()
+            .map(|x| x.string(0))
+            .collect();
+        assert_eq!(names, ["llvm.ident", "test"]);
+        let named = metadata.ops(MetadataCode::NAMED_NODE as u32);
+        assert_eq!(named, [vec![5], vec![4, 6]]);
+    }
+
+    #[test]
+    fn write_attachments() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let ptr = module.types.ptr();
+        let fn_ty = module.types.function(i32, &[ptr], false);
+        let custom = module.md_kind("custom");
+        assert_eq!(module.md_kind("nonnull"), MetadataKind::NONNULL);
+
+        let hot = module.md_string("hot");
+        let hot = module.md_node(&[Some(hot)]);
+        let empty = module.md_node(&[]);
+
+        let g = module.add_global("g", i32);
+        module.global_mut(g).metadata.set(custom, hot);
+        let decl = module.add_function("decl", fn_ty);
+        module.function_mut(decl).metadata.set(custom, empty);
+
+        let f = module.add_function("f", fn_ty);
+        module
+            .function_mut(f)
+            .metadata
+            .set(MetadataKind::ANNOTATION, hot);
+        let p = module.function(f).arg(0);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        let q = builder.load(ptr, p, "q");
+        builder.set_metadata(q.instruction().unwrap(), MetadataKind::NONNULL, empty);
+        let x = builder.load(i32, q, "x");
+        let x = x.instruction().unwrap();
+        builder.set_metadata(x, custom, hot);
+        builder.set_metadata(x, MetadataKind::INVARIANT_LOAD, empty);
+        let ret = builder.module().function(f).instruction_value(x).unwrap();
+        builder.ret(ret);
+
+        // "hot" is the metadata 0, its node 1 and the empty node 2.
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let kinds = module_block.block(BlockId::MetadataKind as u32);
+        let kind = kinds.records.last().unwrap();
+        assert_eq!((kind.ops[0], kind.string(1)), (41, "custom".into()));
+
+        // [value, n x [kind, node]]
+        let metadata = module_block.block(BlockId::Metadata as u32);
+        let globals = metadata.ops(MetadataCode::GLOBAL_DECL_ATTACHMENT as u32);
+        assert_eq!(globals, [[1, 41, 2], [0, 41, 1]]);
+
+        // The function's attachments have no instruction, x's are sorted by
+        // kind.
+        let body = module_block.block(BlockId::Function as u32);
+        let attachments = body
+            .block(BlockId::MetadataAttachment as u32)
+            .ops(MetadataCode::ATTACHMENT as u32);
+        assert_eq!(
+            attachments,
+            [vec![30, 1], vec![0, 11, 2], vec![1, 6, 2, 41, 1]]
+        );
+    }
+
+    // struct S { template <typename T> static T x; };
+    // template <> int S::x<int> __attribute__((btf_decl_tag("tag"))) = 0;
+    #[test]
+    fn write_global_variable() {
+        let mut module = Module::new("test.cpp");
+        module.set_debug_info_version();
+        let i32 = module.types.i32();
+        let g = module.add_global("_ZN1S1xIiEE", i32);
+        let zero = module.const_int(i32, 0);
+        module.global_mut(g).initializer = Some(zero);
+
+        let mut di = DIBuilder::new(&mut module);
+        let file = di.create_file("test.cpp", "/src", None);
+        di.create_compile_unit(
+            DW_LANG_C_PLUS_PLUS_14,
+            file,
+            "compiler 1.0",
+            false,
+            EmissionKind::FullDebug,
+        );
+        let int = di.create_basic_type("int", 32, DW_ATE_SIGNED, 0);
+        let s = di.create_struct_type(None, "S", file, 1, 8, 8, 0, &[], "_ZTS1S");
+        let flags = DIFlag::Public as u32 | DIFlag::StaticMember as u32;
+        let declaration = di.create_member_type(s, "x", file, 1, 0, 0, 0, flags, int);
+        di.set_elements(s, &[declaration]);
+        let t = di.create_template_type_parameter("T", Some(int), false);
+        let expression = di.create_expression(&[]);
+
+        let module = di.module();
+        let template_params = module.md_node(&[Some(t)]);
+        let tag = [module.md_string("btf_decl_tag"), module.md_string("tag")];
+        let tag = module.md_node(&tag.map(Some));
+        let annotations = module.md_node(&[Some(tag)]);
+        let name = module.md_string("x<int>");
+        let linkage_name = module.md_string("_ZN1S1xIiEE");
+        let variable = module.add_metadata(Metadata::GlobalVariable(DIGlobalVariable {
+            scope: Some(s),
+            name: Some(name),
+            linkage_name: Some(linkage_name),
+            file: Some(file),
+            line: 2,
+            ty: Some(int),
+            local_to_unit: false,
+            definition: true,
+            static_data_member_declaration: Some(declaration),
+            template_params: Some(template_params),
+            align_in_bits: 0,
+            annotations: Some(annotations),
+            distinct: true,
+        }));
+        let expression = module.add_metadata(Metadata::GlobalVariableExpression(
+            DIGlobalVariableExpression {
+                variable,
+                expression,
+                distinct: false,
+            },
+        ));
+        module.global_mut(g).set_debug_info(expression);
+
+        let bitcode = read(&module.to_bitcode());
+        let metadata = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Metadata as u32);
+        let strings = metadata
+            .record(MetadataCode::STRINGS as u32)
+            .metadata_strings();
+        assert_eq!(
+            strings[8..],
+            ["x<int>", "_ZN1S1xIiEE", "T", "btf_decl_tag", "tag"]
+        );
+
+        // S is the metadata 16, the file 19, int 20, the member x 21, the
+        // template parameters 24 and the annotations 26. The operands are
+        // shifted by one.
+        // [version << 1 | distinct, scope, name, linkage name, file, line,
+        //  type, local, definition, declaration, template params, align,
+        //  annotations]
+        let variable = metadata.record(MetadataCode::GLOBAL_VAR as u32);
+        assert_eq!(variable.ops, [5, 17, 9, 10, 20, 2, 21, 0, 1, 22, 25, 0, 27]);
+        let declaration = metadata.record(MetadataCode::DERIVED_TYPE as u32);
+        let static_member = (DIFlag::Public as u32 | DIFlag::StaticMember as u32) as u64;
+        assert_eq!(declaration.ops[10], static_member);
+        let nodes = metadata.ops(MetadataCode::NODE as u32);
+        assert!(nodes.contains(&vec![24]));
+        assert!(nodes.contains(&vec![12, 13]));
+        assert!(nodes.contains(&vec![26]));
+        // [distinct, variable, expression]
+        let expression = metadata.record(MetadataCode::GLOBAL_VAR_EXPR as u32);
+        assert_eq!(expression.ops, [0, 18, 28]);
+    }
+
+    #[test]
+    fn write_metadata_index() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        for i in 0..20 {
+            let value = module.const_int(i32, i);
+            let value = module.md_value(value);
+            let name = module.md_string(&format!("n{i}"));
+            let node = module.md_node(&[Some(name), Some(value)]);
+            module.add_named_metadata_operand("test", node);
+        }
+
+        let bitcode = read(&module.to_bitcode());
+        let metadata = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Metadata as u32);
+        // [offset low, offset high] from the end of the record, the index
+        // comes right after the 40 values and nodes.
+        let offset = metadata.record(MetadataCode::INDEX_OFFSET as u32);
+        let start = offset.bit_offset + METADATA_ABBR_ID_WIDTH as usize + 64;
+        let index = metadata.record(MetadataCode::INDEX as u32);
+        assert_eq!(
+            (offset.ops[0] | offset.ops[1] << 32) as usize,
+            index.bit_offset - start
+        );
+        let records: Vec<_> = metadata
+            .records
+            .iter()
+            .filter(|x| start <= x.bit_offset && x.bit_offset < index.bit_offset)
+            .collect();
+        assert_eq!(records.len(), 40);
+
+        // [bitpos], each record as the delta from the previous one.
+        let mut position = start;
+        for (delta, record) in index.ops.iter().zip(&records) {
+            position += *delta as usize;
+            assert_eq!(position, record.bit_offset);
+        }
+        assert_eq!(index.ops.len(), records.len());
+    }
+}
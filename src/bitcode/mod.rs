@@ -1,3 +1,9 @@
+pub mod attributes;
 pub mod blocks;
 pub mod constants;
+pub mod enumerator;
+pub mod function;
+pub mod metadata;
 pub mod module;
+pub mod value_symtab;
+pub mod writer;
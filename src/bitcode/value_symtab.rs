@@ -0,0 +1,189 @@
+use crate::bitstream::{
+    operand::{FixedValue, OperandValue, VBRValue},
+    BitStream,
+};
+
+use super::constants::{vbr_widths, BlockId, ValueSymtabCode};
+
+const VALUE_SYMTAB_ABBR_ID_WIDTH: u32 = 4;
+
+/// The narrowest encoding able to represent a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// `[a-zA-Z0-9._]`
+    Char6,
+    Fixed7,
+    Fixed8,
+}
+
+impl StringEncoding {
+    pub fn of(name: &str) -> Self {
+        let mut encoding = StringEncoding::Char6;
+
+        for byte in name.bytes() {
+            if encoding == StringEncoding::Char6 && !is_char6(byte) {
+                encoding = StringEncoding::Fixed7;
+            }
+
+            if byte & 0x80 != 0 {
+                return StringEncoding::Fixed8;
+            }
+        }
+
+        encoding
+    }
+}
+
+fn is_char6(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'_'
+}
+
+/// Names of the local values (arguments and instructions) and basic blocks of a function.
+///
+/// Written as the VALUE_SYMTAB block nested in the function block.
+#[derive(Debug, Clone, Default)]
+pub struct ValueSymtab {
+    values: Vec<(u32, String)>,
+    blocks: Vec<(u32, String)>,
+}
+
+impl ValueSymtab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names the value with the given (absolute) value id.
+    pub fn add_value(&mut self, value_id: u32, name: &str) {
+        self.values.push((value_id, name.to_string()));
+    }
+
+    /// Names the basic block with the given index.
+    pub fn add_block(&mut self, block_id: u32, name: &str) {
+        self.blocks.push((block_id, name.to_string()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.blocks.is_empty()
+    }
+
+    /// Writes the VALUE_SYMTAB block, expects the block info abbrs from
+    /// [`ValueSymtabBlock`](super::blocks::ValueSymtabBlock) to be defined.
+    pub fn write(&self, stream: &mut BitStream) {
+        if self.is_empty() {
+            return;
+        }
+
+        stream.enter_block(BlockId::ValueSymtab as u32, VALUE_SYMTAB_ABBR_ID_WIDTH);
+
+        for (id, name) in &self.values {
+            match StringEncoding::of(name) {
+                StringEncoding::Char6 => {
+                    stream.write_record("entry6", &[value_id(*id), name.as_str().into()])
+                }
+                StringEncoding::Fixed7 => {
+                    stream.write_record("entry7", &[value_id(*id), fixed_chars(name, 7)])
+                }
+                StringEncoding::Fixed8 => stream.write_record(
+                    "entry8",
+                    &[
+                        code(ValueSymtabCode::Entry),
+                        value_id(*id),
+                        fixed_chars(name, 8),
+                    ],
+                ),
+            }
+        }
+
+        for (id, name) in &self.blocks {
+            match StringEncoding::of(name) {
+                StringEncoding::Char6 => {
+                    stream.write_record("bbentry6", &[value_id(*id), name.as_str().into()])
+                }
+                _ => stream.write_record(
+                    "entry8",
+                    &[
+                        code(ValueSymtabCode::BbEntry),
+                        value_id(*id),
+                        fixed_chars(name, 8),
+                    ],
+                ),
+            }
+        }
+
+        stream.end_block(BlockId::ValueSymtab as u32);
+    }
+}
+
+fn code(code: ValueSymtabCode) -> OperandValue {
+    OperandValue::Fixed(FixedValue {
+        value: code as u32,
+        width: 3,
+    })
+}
+
+fn value_id(id: u32) -> OperandValue {
+    OperandValue::Vbr(VBRValue {
+        value: id as u64,
+        width: vbr_widths::VALUE_INDEX,
+    })
+}
+
+pub(crate) fn fixed_chars(name: &str, width: u32) -> OperandValue {
+    OperandValue::Array(
+        name.bytes()
+            .map(|byte| {
+                OperandValue::Fixed(FixedValue {
+                    value: byte as u32,
+                    width,
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::blocks::ValueSymtabBlock,
+        bitstream::{BitStream, BlockInfoMap},
+    };
+
+    use super::{StringEncoding, ValueSymtab};
+
+    #[test]
+    fn string_encoding() {
+        assert_eq!(StringEncoding::of("entry.bb_1"), StringEncoding::Char6);
+        assert_eq!(StringEncoding::of(""), StringEncoding::Char6);
+        assert_eq!(StringEncoding::of("a-b"), StringEncoding::Fixed7);
+        assert_eq!(StringEncoding::of("a b$"), StringEncoding::Fixed7);
+        assert_eq!(StringEncoding::of("añ"), StringEncoding::Fixed8);
+    }
+
+    #[test]
+    fn write_symtab() {
+        let mut writer = BitStream::new(0xdec04342);
+
+        let mut map = BlockInfoMap::new();
+        ValueSymtabBlock::build_info(&mut map);
+
+        writer.enter_block(8, 3);
+        writer.write_block_info(&map);
+
+        let mut symtab = ValueSymtab::new();
+        symtab.add_value(0, "x");
+        symtab.add_value(1, "a-b");
+        symtab.add_value(2, "é");
+        symtab.add_block(0, "entry");
+        symtab.add_block(1, "if-then");
+        symtab.write(&mut writer);
+
+        writer.end_block(8);
+        writer.writer.flush();
+
+        let content = hex::encode(&writer.writer.buffer[4..]);
+        assert_eq!(
+            content,
+            "210c00001300000001100000060000000781239141c80449061032399201840c2505081902000000712000000800000006105c1530845b628a80309c7a0005d14c11460a38d2cc5ae8d0cadc0000000000000000"
+        );
+    }
+}
@@ -0,0 +1,1048 @@
+use std::collections::HashMap;
+
+use crate::{
+    bitstream::{
+        abbrv::Abbr,
+        operand::{OperandDef, OperandValue, VBRValue},
+        BitStream, BlockInfoMap,
+    },
+    ir::{
+        constants::ConstantKind,
+        global::{encode_alignment, ComdatId},
+        module::Module,
+        types::Type,
+        value::ConstantId,
+    },
+};
+
+use super::{
+    attributes::encode_signed,
+    blocks::{ConstantBlock, ValueSymtabBlock},
+    constants::{
+        vbr_widths, BlockId, ConstantsCode, IdentificationCodes, ModuleCode, OperandBundleTagCode,
+        StrtabCode, SyncScopeNameCode, TypeCode, CURRENT_EPOCH,
+    },
+    enumerator::ValueEnumerator,
+    module::{MAGIC, MODULE_ABBR_ID_WIDTH, VERSION},
+};
+
+const IDENTIFICATION_ABBR_ID_WIDTH: u32 = 5;
+const TYPE_ABBR_ID_WIDTH: u32 = 4;
+const CONSTANTS_ABBR_ID_WIDTH: u32 = 4;
+const OPERAND_BUNDLE_TAGS_ABBR_ID_WIDTH: u32 = 3;
+const SYNC_SCOPE_NAMES_ABBR_ID_WIDTH: u32 = 2;
+const STRTAB_ABBR_ID_WIDTH: u32 = 3;
+
+/// The name and version of the producer written to the identification block.
+pub const PRODUCER: &str = "LLVM19.1.7";
+
+/// Writes the module as a bitcode file.
+pub fn write_module(module: &Module) -> Vec<u8> {
+    let mut writer = ModuleWriter::new(module);
+    writer.write();
+    writer.stream.finish()
+}
+
+pub(crate) struct ModuleWriter<'a> {
+    pub(crate) module: &'a Module,
+    pub(crate) stream: BitStream,
+    pub(crate) enumerator: ValueEnumerator<'a>,
+    strtab: Vec<u8>,
+    sections: HashMap<&'a str, u64>,
+    gc_names: HashMap<&'a str, u64>,
+}
+
+impl<'a> ModuleWriter<'a> {
+    pub fn new(module: &'a Module) -> Self {
+        Self {
+            module,
+            stream: BitStream::new(MAGIC),
+            enumerator: ValueEnumerator::new(module),
+            strtab: Vec::new(),
+            sections: HashMap::new(),
+            gc_names: HashMap::new(),
+        }
+    }
+
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L5108
+    pub fn write(&mut self) {
+        self.write_identification();
+
+        self.stream
+            .enter_block(BlockId::Module as u32, MODULE_ABBR_ID_WIDTH);
+        self.stream
+            .write_unabrr_record(ModuleCode::Version as u32, &[VERSION]);
+
+        self.write_block_info();
+        self.write_type_table();
+
+        let enumerator = &self.enumerator;
+        enumerator
+            .attributes()
+            .write(&mut self.stream, |ty| enumerator.type_index(ty));
+
+        self.write_comdats();
+        self.write_module_info();
+
+        let constants = self.enumerator.module_constants().to_vec();
+        self.write_constants(&constants);
+        self.write_metadata_kinds();
+        self.write_module_metadata();
+
+        self.write_operand_bundle_tags();
+        self.write_sync_scope_names();
+
+        let module = self.module;
+        for (_, function) in module.functions() {
+            if !function.is_declaration() {
+                self.write_function(function);
+            }
+        }
+
+        self.stream.end_block(BlockId::Module as u32);
+
+        self.write_strtab();
+    }
+
+    fn write_identification(&mut self) {
+        self.stream
+            .enter_block(BlockId::Identification as u32, IDENTIFICATION_ABBR_ID_WIDTH);
+
+        self.stream.define_abbr(&Abbr::new(
+            "producer",
+            &[
+                OperandDef::Literal(IdentificationCodes::String as u32),
+                OperandDef::Array(Box::new(OperandDef::Char6)),
+            ],
+        ));
+        self.stream.write_record("producer", &[PRODUCER.into()]);
+        self.stream
+            .write_unabrr_record(IdentificationCodes::Epoch as u32, &[CURRENT_EPOCH]);
+
+        self.stream.end_block(BlockId::Identification as u32);
+    }
+
+    fn write_block_info(&mut self) {
+        let mut info = BlockInfoMap::new();
+        ConstantBlock::build_info(&mut info);
+        ValueSymtabBlock::build_info(&mut info);
+        self.stream.write_block_info(&info);
+    }
+
+    fn write_type_table(&mut self) {
+        let module = self.module;
+
+        self.stream
+            .enter_block(BlockId::Type as u32, TYPE_ABBR_ID_WIDTH);
+        self.stream.write_unabrr_record(
+            TypeCode::Numentry as u32,
+            &[self.enumerator.types().len() as u32],
+        );
+
+        for id in self.enumerator.types().to_vec() {
+            let index = |ty| self.enumerator.type_index(ty) as u64;
+
+            let (code, record) = match module.types.get(id) {
+                Type::Void => (TypeCode::Void, vec![]),
+                Type::Half => (TypeCode::Half, vec![]),
+                Type::BFloat => (TypeCode::Bfloat, vec![]),
+                Type::Float => (TypeCode::Float, vec![]),
+                Type::Double => (TypeCode::Double, vec![]),
+                Type::X86Fp80 => (TypeCode::X86Fp80, vec![]),
+                Type::Fp128 => (TypeCode::Fp128, vec![]),
+                Type::PpcFp128 => (TypeCode::PpcFp128, vec![]),
+                Type::Label => (TypeCode::Label, vec![]),
+                Type::Metadata => (TypeCode::Metadata, vec![]),
+                Type::Token => (TypeCode::Token, vec![]),
+                // [width]
+                Type::Integer(bits) => (TypeCode::Integer, vec![*bits as u64]),
+                // [address space]
+                Type::Pointer { address_space } => {
+                    (TypeCode::OpaquePointer, vec![*address_space as u64])
+                }
+                // [numelts, eltty]
+                Type::Array { element, len } => (TypeCode::Array, vec![*len, index(*element)]),
+                // [numelts, eltty, (scalable)]
+                Type::Vector {
+                    element,
+                    len,
+                    scalable,
+                } => {
+                    let mut record = vec![*len as u64, index(*element)];
+                    if *scalable {
+                        record.push(1);
+                    }
+                    (TypeCode::Vector, record)
+                }
+                Type::Struct(st) => {
+                    // [ispacked, eltty...]
+                    let mut record = vec![st.packed as u64];
+                    record.extend(st.fields.iter().map(|x| index(*x)));
+
+                    match &st.name {
+                        None => (TypeCode::StructAnon, record),
+                        Some(name) => {
+                            write_string_record(
+                                &mut self.stream,
+                                TypeCode::StructName as u32,
+                                name,
+                            );
+
+                            if st.opaque {
+                                (TypeCode::Opaque, vec![0])
+                            } else {
+                                (TypeCode::StructNamed, record)
+                            }
+                        }
+                    }
+                }
+                Type::Function(fn_ty) => {
+                    // [vararg, retty, paramty...]
+                    let mut record = vec![fn_ty.vararg as u64, index(fn_ty.ret)];
+                    record.extend(fn_ty.params.iter().map(|x| index(*x)));
+                    (TypeCode::Function, record)
+                }
+            };
+
+            self.stream.write_unabrr_record_u64(code as u32, &record);
+        }
+
+        self.stream.end_block(BlockId::Type as u32);
+    }
+
+    /// Adds the string to the string table, returning its offset and size.
+    pub(crate) fn add_to_strtab(&mut self, value: &str) -> (u64, u64) {
+        let offset = self.strtab.len() as u64;
+        self.strtab.extend_from_slice(value.as_bytes());
+        (offset, value.len() as u64)
+    }
+
+    fn write_comdats(&mut self) {
+        for (_, comdat) in self.module.comdats() {
+            // [strtab offset, strtab size, selection kind]
+            let (offset, size) = self.add_to_strtab(&comdat.name);
+            self.stream.write_unabrr_record_u64(
+                ModuleCode::Comdat as u32,
+                &[offset, size, comdat.selection_kind as u64],
+            );
+        }
+    }
+
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L1459
+    fn write_module_info(&mut self) {
+        let module = self.module;
+
+        if let Some(triple) = &module.triple {
+            write_string_record(&mut self.stream, ModuleCode::Triple as u32, triple);
+        }
+
+        if let Some(data_layout) = &module.data_layout {
+            write_string_record(&mut self.stream, ModuleCode::DataLayout as u32, data_layout);
+        }
+
+        if !module.module_asm.is_empty() {
+            write_string_record(&mut self.stream, ModuleCode::Asm as u32, &module.module_asm);
+        }
+
+        let sections = module
+            .globals()
+            .filter_map(|(_, x)| x.section.as_deref())
+            .chain(module.functions().filter_map(|(_, x)| x.section.as_deref()));
+        for section in sections {
+            if !self.sections.contains_key(section) {
+                write_string_record(&mut self.stream, ModuleCode::SectionName as u32, section);
+                self.sections
+                    .insert(section, self.sections.len() as u64 + 1);
+            }
+        }
+
+        for (_, function) in module.functions() {
+            if let Some(gc) = function.gc.as_deref() {
+                if !self.gc_names.contains_key(gc) {
+                    write_string_record(&mut self.stream, ModuleCode::GcName as u32, gc);
+                    self.gc_names.insert(gc, self.gc_names.len() as u64 + 1);
+                }
+            }
+        }
+
+        write_string_record(
+            &mut self.stream,
+            ModuleCode::SourceFilename as u32,
+            &module.source_filename,
+        );
+
+        for (_, global) in module.globals() {
+            // [strtab offset, strtab size, type, isconst, initid,
+            //  linkage, alignment, section, visibility, threadlocal,
+            //  unnamed_addr, externally_initialized, dllstorageclass,
+            //  comdat, attributes, DSO_Local]
+            let (offset, size) = self.add_to_strtab(&global.name);
+            let mut record = vec![
+                offset,
+                size,
+                self.enumerator.type_index(global.value_type) as u64,
+                // The value type is explicit.
+                (global.address_space() as u64) << 2 | 2 | global.constant as u64,
+                global
+                    .initializer
+                    .map(|x| self.enumerator.value_id(x.kind()) as u64 + 1)
+                    .unwrap_or(0),
+                global.linkage.code(),
+                encode_alignment(global.alignment),
+                self.section_id(global.section.as_deref()),
+            ];
+
+            if global.visibility as u64 != 0
+                || global.thread_local as u64 != 0
+                || global.unnamed_addr as u64 != 0
+                || global.externally_initialized
+                || global.dll_storage_class as u64 != 0
+                || global.comdat.is_some()
+                || global.dso_local
+            {
+                record.extend([
+                    global.visibility as u64,
+                    global.thread_local as u64,
+                    global.unnamed_addr as u64,
+                    global.externally_initialized as u64,
+                    global.dll_storage_class as u64,
+                    comdat_id(global.comdat),
+                    // attributes
+                    0,
+                    global.dso_local as u64,
+                ]);
+            }
+
+            self.stream
+                .write_unabrr_record_u64(ModuleCode::GlobalVar as u32, &record);
+        }
+
+        for (_, function) in module.functions() {
+            // [strtab offset, strtab size, type, callingconv, isproto,
+            //  linkage, paramattrs, alignment, section, visibility, gc,
+            //  unnamed_addr, prologuedata, dllstorageclass, comdat,
+            //  prefixdata, personalityfn, DSO_Local, addrspace]
+            let (offset, size) = self.add_to_strtab(&function.name);
+            let record = vec![
+                offset,
+                size,
+                self.enumerator.type_index(function.ty()) as u64,
+                function.calling_conv.code(),
+                function.is_declaration() as u64,
+                function.linkage.code(),
+                self.enumerator.attributes().get(&function.attributes) as u64,
+                encode_alignment(function.alignment),
+                self.section_id(function.section.as_deref()),
+                function.visibility as u64,
+                function
+                    .gc
+                    .as_deref()
+                    .map(|x| self.gc_names[x])
+                    .unwrap_or(0),
+                function.unnamed_addr as u64,
+                // prologuedata
+                0,
+                function.dll_storage_class as u64,
+                comdat_id(function.comdat),
+                // prefixdata
+                0,
+                function
+                    .personality
+                    .map(|x| self.enumerator.value_id(x.kind) as u64 + 1)
+                    .unwrap_or(0),
+                function.dso_local as u64,
+                // addrspace
+                0,
+            ];
+
+            self.stream
+                .write_unabrr_record_u64(ModuleCode::Function as u32, &record);
+        }
+
+        for (_, alias) in module.aliases() {
+            // [strtab offset, strtab size, alias type, addrspace, aliasee val#,
+            //  linkage, visibility, dllstorageclass, threadlocal, unnamed_addr,
+            //  DSO_Local, partition strtab offset, partition strtab size]
+            let (offset, size) = self.add_to_strtab(&alias.name);
+            let Type::Pointer { address_space } = module.types.get(alias.aliasee.ty) else {
+                unreachable!()
+            };
+            let mut record = vec![
+                offset,
+                size,
+                self.enumerator.type_index(alias.value_type) as u64,
+                *address_space as u64,
+                self.enumerator.value_id(alias.aliasee.kind) as u64,
+                alias.linkage.code(),
+                alias.visibility as u64,
+                alias.dll_storage_class as u64,
+                alias.thread_local as u64,
+                alias.unnamed_addr as u64,
+                alias.dso_local as u64,
+            ];
+            record.extend(self.partition(alias.partition.as_deref()));
+
+            self.stream
+                .write_unabrr_record_u64(ModuleCode::Alias as u32, &record);
+        }
+
+        for (_, ifunc) in module.ifuncs() {
+            // [strtab offset, strtab size, ifunc type, addrspace, resolver val#,
+            //  linkage, visibility, DSO_Local, partition strtab offset,
+            //  partition strtab size]
+            let (offset, size) = self.add_to_strtab(&ifunc.name);
+            let mut record = vec![
+                offset,
+                size,
+                self.enumerator.type_index(ifunc.value_type) as u64,
+                // addrspace
+                0,
+                self.enumerator.value_id(ifunc.resolver.kind) as u64,
+                ifunc.linkage.code(),
+                ifunc.visibility as u64,
+                ifunc.dso_local as u64,
+            ];
+            record.extend(self.partition(ifunc.partition.as_deref()));
+
+            self.stream
+                .write_unabrr_record_u64(ModuleCode::CodeIfunc as u32, &record);
+        }
+    }
+
+    fn partition(&mut self, partition: Option<&str>) -> [u64; 2] {
+        let (offset, size) = partition.map_or((0, 0), |x| self.add_to_strtab(x));
+        [offset, size]
+    }
+
+    fn section_id(&self, section: Option<&str>) -> u64 {
+        section.map(|x| self.sections[x]).unwrap_or(0)
+    }
+
+    /// Writes a CONSTANTS block with the given constants, which must be enumerated.
+    pub(crate) fn write_constants(&mut self, constants: &[ConstantId]) {
+        if constants.is_empty() {
+            return;
+        }
+
+        let module = self.module;
+
+        self.stream
+            .enter_block(BlockId::Constants as u32, CONSTANTS_ABBR_ID_WIDTH);
+
+        let mut last_ty = None;
+
+        for id in constants {
+            let constant = module.constants.get(*id);
+
+            if last_ty != Some(constant.ty) {
+                last_ty = Some(constant.ty);
+                self.stream.write_record(
+                    "settype",
+                    &[vbr(
+                        self.enumerator.type_index(constant.ty) as u64,
+                        vbr_widths::TYPE_INDEX,
+                    )],
+                );
+            }
+
+            let ty = module.types.get(constant.ty);
+
+            match &constant.kind {
+                ConstantKind::Null => self.stream.write_record("null", &[]),
+                ConstantKind::Undef => self.stream.write_record("undef", &[]),
+                ConstantKind::Poison => self
+                    .stream
+                    .write_unabrr_record(ConstantsCode::Poison as u32, &[]),
+                ConstantKind::Int(value) => {
+                    let Type::Integer(bits) = ty else {
+                        unreachable!()
+                    };
+
+                    if *bits <= 64 {
+                        // Sign extended to 64 bits.
+                        let shift = 64 - bits;
+                        let value = ((*value as u64) << shift) as i64 >> shift;
+                        self.stream
+                            .write_record("int", &[vbr(encode_signed(value), vbr_widths::INTEGER)]);
+                    } else {
+                        // Only the active words are written.
+                        let mut words = vec![*value as u64];
+                        if (*value >> 64) != 0 {
+                            words.push((*value >> 64) as u64);
+                        }
+                        let record: Vec<u64> =
+                            words.iter().map(|x| encode_signed(*x as i64)).collect();
+                        self.stream
+                            .write_unabrr_record_u64(ConstantsCode::WideInteger as u32, &record);
+                    }
+                }
+                ConstantKind::Float(bits) => {
+                    let record = match ty {
+                        Type::X86Fp80 => vec![
+                            // The 16 bit exponent and sign come first.
+                            ((*bits >> 64) as u64) << 48 | (*bits as u64) >> 16,
+                            *bits as u64 & 0xffff,
+                        ],
+                        Type::Fp128 | Type::PpcFp128 => {
+                            vec![*bits as u64, (*bits >> 64) as u64]
+                        }
+                        _ => vec![*bits as u64],
+                    };
+                    self.stream
+                        .write_unabrr_record_u64(ConstantsCode::Float as u32, &record);
+                }
+                ConstantKind::Aggregate(elements) => {
+                    let ids = elements
+                        .iter()
+                        .map(|x| {
+                            vbr(
+                                self.enumerator.value_id(x.kind()) as u64,
+                                vbr_widths::VALUE_INDEX,
+                            )
+                        })
+                        .collect();
+                    self.stream
+                        .write_record("aggr", &[OperandValue::Array(ids)]);
+                }
+                ConstantKind::Data(elements) => {
+                    // Strings are read back as arrays, `<n x i8>` is plain data.
+                    let is_string = match ty {
+                        Type::Array { element, .. } => {
+                            module.types.get(*element) == &Type::Integer(8)
+                        }
+                        _ => false,
+                    };
+
+                    let is_cstring = is_string
+                        && elements.last() == Some(&0)
+                        && !elements[..elements.len() - 1].contains(&0);
+
+                    if is_cstring {
+                        self.stream.write_unabrr_record_u64(
+                            ConstantsCode::Cstring as u32,
+                            &elements[..elements.len() - 1],
+                        );
+                    } else if is_string {
+                        self.stream
+                            .write_unabrr_record_u64(ConstantsCode::String as u32, elements);
+                    } else {
+                        self.stream
+                            .write_unabrr_record_u64(ConstantsCode::Data as u32, elements);
+                    }
+                }
+                // [opcode, opty, opval]
+                ConstantKind::Cast { op, value } => {
+                    let record = [
+                        *op as u64,
+                        self.enumerator.type_index(value.ty) as u64,
+                        self.enumerator.value_id(value.kind) as u64,
+                    ];
+                    self.stream
+                        .write_unabrr_record_u64(ConstantsCode::CeCast as u32, &record);
+                }
+                // [opcode, lhs, rhs, (flags)]
+                ConstantKind::BinOp {
+                    op,
+                    lhs,
+                    rhs,
+                    flags,
+                } => {
+                    let mut record = vec![
+                        *op as u64,
+                        self.enumerator.value_id(lhs.kind) as u64,
+                        self.enumerator.value_id(rhs.kind) as u64,
+                    ];
+                    if *flags != 0 {
+                        record.push(*flags);
+                    }
+                    self.stream
+                        .write_unabrr_record_u64(ConstantsCode::CeBinop as u32, &record);
+                }
+                // [pointee type, flags, (bitwidth, lower, upper), (opty, opval)...]
+                ConstantKind::GetElementPtr {
+                    source_type,
+                    ptr,
+                    indices,
+                    flags,
+                    inrange,
+                } => {
+                    let mut record = vec![self.enumerator.type_index(*source_type) as u64, *flags];
+                    let code = match inrange {
+                        Some((lower, upper)) => {
+                            // The range has the width of the pointer index type.
+                            let Type::Pointer { address_space } =
+                                module.types.get(module.types.scalar_type(ptr.ty))
+                            else {
+                                unreachable!("gep on a non pointer");
+                            };
+                            let width = module.index_width(*address_space) as u64;
+                            record.extend([width, encode_signed(*lower), encode_signed(*upper)]);
+                            ConstantsCode::CeGepWithInrange
+                        }
+                        None => ConstantsCode::CeGep,
+                    };
+                    for operand in std::iter::once(ptr).chain(indices) {
+                        record.push(self.enumerator.type_index(operand.ty) as u64);
+                        record.push(self.enumerator.value_id(operand.kind) as u64);
+                    }
+                    self.stream.write_unabrr_record_u64(code as u32, &record);
+                }
+                // [fnty, fnval, bb#]
+                ConstantKind::BlockAddress { function, block } => {
+                    let record = [
+                        self.enumerator.type_index(function.ty) as u64,
+                        self.enumerator.value_id(function.kind) as u64,
+                        block.0 as u64,
+                    ];
+                    self.stream
+                        .write_unabrr_record_u64(ConstantsCode::BlockAddress as u32, &record);
+                }
+                // [gvty, gv]
+                ConstantKind::DsoLocalEquivalent(value) | ConstantKind::NoCfi(value) => {
+                    let code = match constant.kind {
+                        ConstantKind::NoCfi(_) => ConstantsCode::CodeNoCfiValue,
+                        _ => ConstantsCode::DsoLocalEquivalent,
+                    };
+                    let record = [
+                        self.enumerator.type_index(value.ty) as u64,
+                        self.enumerator.value_id(value.kind) as u64,
+                    ];
+                    self.stream.write_unabrr_record_u64(code as u32, &record);
+                }
+                // [fnty, flags, asmstrsize, asmstr..., constraintsize, constraints...]
+                ConstantKind::InlineAsm(asm) => {
+                    let flags = asm.side_effect as u64
+                        | (asm.align_stack as u64) << 1
+                        | (asm.dialect as u64) << 2
+                        | (asm.can_throw as u64) << 3;
+                    let mut record = vec![
+                        self.enumerator.type_index(asm.function_type) as u64,
+                        flags,
+                        asm.asm.len() as u64,
+                    ];
+                    record.extend(asm.asm.bytes().map(u64::from));
+                    record.push(asm.constraints.len() as u64);
+                    record.extend(asm.constraints.bytes().map(u64::from));
+                    self.stream
+                        .write_unabrr_record_u64(ConstantsCode::InlineAsm as u32, &record);
+                }
+                // [ptr, key, disc, addrdisc]
+                ConstantKind::PtrAuth {
+                    ptr,
+                    key,
+                    discriminator,
+                    addr_discriminator,
+                } => {
+                    let record: Vec<_> = [ptr, key, discriminator, addr_discriminator]
+                        .iter()
+                        .map(|x| self.enumerator.value_id(x.kind) as u64)
+                        .collect();
+                    self.stream
+                        .write_unabrr_record_u64(ConstantsCode::PtrAuth as u32, &record);
+                }
+            }
+        }
+
+        self.stream.end_block(BlockId::Constants as u32);
+    }
+
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L3832
+    fn write_operand_bundle_tags(&mut self) {
+        self.stream.enter_block(
+            BlockId::OperandBundleTags as u32,
+            OPERAND_BUNDLE_TAGS_ABBR_ID_WIDTH,
+        );
+
+        for (_, name) in self.module.bundle_tags() {
+            write_string_record(
+                &mut self.stream,
+                OperandBundleTagCode::BundleTag as u32,
+                name,
+            );
+        }
+
+        self.stream.end_block(BlockId::OperandBundleTags as u32);
+    }
+
+    // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/Bitcode/Writer/BitcodeWriter.cpp#L3848
+    fn write_sync_scope_names(&mut self) {
+        self.stream.enter_block(
+            BlockId::SyncScopeNames as u32,
+            SYNC_SCOPE_NAMES_ABBR_ID_WIDTH,
+        );
+
+        for (_, name) in self.module.sync_scopes() {
+            write_string_record(&mut self.stream, SyncScopeNameCode::Name as u32, name);
+        }
+
+        self.stream.end_block(BlockId::SyncScopeNames as u32);
+    }
+
+    fn write_strtab(&mut self) {
+        self.stream
+            .enter_block(BlockId::Strtab as u32, STRTAB_ABBR_ID_WIDTH);
+
+        self.stream.define_abbr(&Abbr::new(
+            "blob",
+            &[
+                OperandDef::Literal(StrtabCode::Blob as u32),
+                OperandDef::Blob,
+            ],
+        ));
+        self.stream
+            .write_record("blob", &[OperandValue::Blob(self.strtab.clone())]);
+
+        self.stream.end_block(BlockId::Strtab as u32);
+    }
+}
+
+pub(crate) fn vbr(value: u64, width: u32) -> OperandValue {
+    OperandValue::Vbr(VBRValue { value, width })
+}
+
+// Comdats are numbered from 1, 0 meaning no comdat.
+fn comdat_id(comdat: Option<ComdatId>) -> u64 {
+    comdat.map_or(0, |x| x.0 as u64 + 1)
+}
+
+/// Writes a string as an unabbreviated record of chars.
+pub(crate) fn write_string_record(stream: &mut BitStream, code: u32, value: &str) {
+    let chars: Vec<u32> = value.bytes().map(u32::from).collect();
+    stream.write_unabrr_record(code, &chars);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::{
+            BinaryOpCode, BlockId, CastCodes, ComdatSelectionKindCode, ConstantsCode, FunctionCide,
+            GetElementPtrOptionalFlag, ModuleCode, StrtabCode, ValueSymtabCode,
+        },
+        bitstream::reader::read,
+        ir::{
+            global::{Linkage, Visibility},
+            instructions::{BinOpFlags, Instruction, InstructionKind},
+            module::Module,
+        },
+    };
+
+    #[test]
+    fn write_module() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32, i32], false);
+
+        let g = module.add_global("g", i32);
+        let five = module.const_int(i32, 5);
+        module.global_mut(g).initializer = Some(five);
+
+        let f = module.add_function("add", fn_ty);
+        let func = module.function_mut(f);
+        let entry = func.append_block("entry");
+        let (a, b) = (func.arg(0), func.arg(1));
+        func.set_name(a, "a");
+        func.set_name(b, "b");
+        let sum = func.push_instruction(
+            entry,
+            Instruction::new(
+                InstructionKind::BinOp {
+                    op: BinaryOpCode::Add,
+                    lhs: a,
+                    rhs: b,
+                    flags: 0,
+                },
+                Some(i32),
+            ),
+        );
+        let sum = func.instruction_value(sum).unwrap();
+        func.set_name(sum, "sum");
+        func.push_instruction(
+            entry,
+            Instruction::new(InstructionKind::Ret(Some(sum)), None),
+        );
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        // [strtab offset, strtab size, type, explicit type, initid]
+        let global = module_block.record(ModuleCode::GlobalVar as u32);
+        assert_eq!(global.ops[..5], [0, 1, 0, 2, 3]);
+        // [strtab offset, strtab size, type, callingconv, isproto]
+        let function = module_block.record(ModuleCode::Function as u32);
+        assert_eq!(function.ops[..5], [1, 3, 1, 0, 0]);
+        let constants = module_block.block(BlockId::Constants as u32);
+        assert_eq!(constants.record(ConstantsCode::Integer as u32).ops, [10]);
+
+        // The arguments are the values 3 and 4, the sum is the value 5.
+        let body = module_block.block(BlockId::Function as u32);
+        let binop = body.record(FunctionCide::INST_BINOP as u32);
+        assert_eq!(binop.ops, [2, 1, BinaryOpCode::Add as u64]);
+        assert_eq!(body.record(FunctionCide::INST_RET as u32).ops, [1]);
+        let symtab = body.block(BlockId::ValueSymtab as u32);
+        let names: Vec<_> = symtab
+            .records(ValueSymtabCode::Entry as u32)
+            .iter()
+            .map(|x| (x.ops[0], x.string(1)))
+            .collect();
+        assert_eq!(names, [(3, "a".into()), (4, "b".into()), (5, "sum".into())]);
+
+        let strtab = bitcode.block(BlockId::Strtab as u32);
+        let blob = strtab.record(StrtabCode::Blob as u32).blob.as_deref();
+        assert_eq!(blob, Some(&b"gadd"[..]));
+    }
+
+    // Relative pointers, vtable-like slices and signed pointers.
+    #[test]
+    fn write_constant_expressions() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let i64 = module.types.i64();
+        let ptr = module.types.ptr();
+        let void = module.types.void();
+        let arr_ty = module.types.array(i32, 4);
+        let fn_ty = module.types.function(void, &[], false);
+
+        let arr = module.add_global("arr", arr_ty);
+        let rel = module.add_global("rel", i32);
+        let elem = module.add_global("elem", ptr);
+        let addr = module.add_global("addr", ptr);
+        let equiv = module.add_global("equiv", ptr);
+        let nocfi = module.add_global("nocfi", ptr);
+        let signed = module.add_global("signed", ptr);
+
+        let f = module.add_function("f", fn_ty);
+        let func = module.function_mut(f);
+        let entry = func.append_block("entry");
+        let next = func.append_block("next");
+        func.push_instruction(entry, Instruction::new(InstructionKind::Br(next), None));
+        func.push_instruction(next, Instruction::new(InstructionKind::Ret(None), None));
+
+        let (arr_value, rel_value) = (module.global_value(arr), module.global_value(rel));
+        let f_value = module.function_value(f);
+        let zero = module.const_null(arr_ty);
+        module.global_mut(arr).initializer = Some(zero);
+
+        let target = module.const_cast(CastCodes::PtrToInt, f_value, i64);
+        let base = module.const_cast(CastCodes::PtrToInt, rel_value, i64);
+        let nsw = BinOpFlags {
+            nsw: true,
+            ..Default::default()
+        };
+        let offset = module.const_binop(BinaryOpCode::Sub, target, base, nsw);
+        let offset = module.const_cast(CastCodes::Trunc, offset, i32);
+        module.global_mut(rel).initializer = Some(offset);
+
+        let indices = [module.const_int(i64, 0), module.const_int(i64, 2)];
+        let flags = [GetElementPtrOptionalFlag::GEP_INBOUNDS];
+        let gep = module.const_gep(arr_ty, arr_value, &indices, &flags, Some((-8, 8)));
+        module.global_mut(elem).initializer = Some(gep);
+
+        let block = module.const_blockaddress(f, next);
+        module.global_mut(addr).initializer = Some(block);
+        let value = module.const_dso_local_equivalent(f);
+        module.global_mut(equiv).initializer = Some(value);
+        let value = module.const_no_cfi(f);
+        module.global_mut(nocfi).initializer = Some(value);
+        let signed_value = module.global_value(signed);
+        let value = module.const_ptrauth(f_value, 0, 1234, Some(signed_value));
+        module.global_mut(signed).initializer = Some(value);
+
+        // The globals are the values 0 to 6, f is 7 and the constants follow.
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let initializers: Vec<_> = module_block
+            .records(ModuleCode::GlobalVar as u32)
+            .iter()
+            .map(|x| x.ops[4])
+            .collect();
+        assert_eq!(initializers, [9, 13, 16, 17, 18, 19, 22]);
+
+        let constants = module_block.block(BlockId::Constants as u32);
+        let casts = constants.ops(ConstantsCode::CeCast as u32);
+        // [opcode, opty, opval]
+        assert_eq!(casts, [[9, 2, 7], [9, 2, 1], [0, 1, 11]]);
+        // [opcode, lhs, rhs, flags]
+        let binop = constants.record(ConstantsCode::CeBinop as u32);
+        assert_eq!(binop.ops, [BinaryOpCode::Sub as u64, 9, 10, 2]);
+        // [pointee type, flags, range width, range start, range end, n x
+        //  (type, value)], -8 and 8 as signed VBRs.
+        let gep = constants.record(ConstantsCode::CeGepWithInrange as u32);
+        assert_eq!(gep.ops, [4, 1, 64, 17, 16, 2, 0, 1, 13, 1, 14]);
+        // [function type, function, basic block]
+        let block = constants.record(ConstantsCode::BlockAddress as u32);
+        assert_eq!(block.ops, [2, 7, 1]);
+        let equivalent = constants.record(ConstantsCode::DsoLocalEquivalent as u32);
+        assert_eq!(equivalent.ops, [2, 7]);
+        let no_cfi = constants.record(ConstantsCode::CodeNoCfiValue as u32);
+        assert_eq!(no_cfi.ops, [2, 7]);
+        // [pointer, key, discriminator, address discriminator]
+        let ptrauth = constants.record(ConstantsCode::PtrAuth as u32);
+        assert_eq!(ptrauth.ops, [7, 19, 20, 6]);
+    }
+
+    // LLVM can't read empty strings and aggregates, they're zeroinitializer.
+    #[test]
+    fn write_empty_constants() {
+        let mut module = Module::new("test.c");
+        let empty = module.const_string(b"", false);
+        let empty_struct = module.const_struct(&[], false);
+        let string = module.add_global("string", empty.ty);
+        module.global_mut(string).initializer = Some(empty);
+        let st = module.add_global("st", empty_struct.ty);
+        module.global_mut(st).initializer = Some(empty_struct);
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let constants = module_block.block(BlockId::Constants as u32);
+        let (set_type, null) = (ConstantsCode::SetType as u32, ConstantsCode::Null as u32);
+        assert_eq!(constants.codes(), [set_type, null, set_type, null]);
+        let initializers: Vec<_> = module_block
+            .records(ModuleCode::GlobalVar as u32)
+            .iter()
+            .map(|x| x.ops[4])
+            .collect();
+        assert_eq!(initializers, [3, 4]);
+    }
+
+    #[test]
+    fn write_byte_vectors_as_data() {
+        let mut module = Module::new("test.c");
+        let i8 = module.types.i8();
+        let vector_ty = module.types.vector(i8, 4);
+        let vector = module.const_data(vector_ty, &[1, 2, 3, 0]);
+        let g = module.add_global("g", vector_ty);
+        module.global_mut(g).initializer = Some(vector);
+
+        let bitcode = read(&module.to_bitcode());
+        let constants = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Constants as u32);
+        assert!(constants.records(ConstantsCode::Cstring as u32).is_empty());
+        assert_eq!(constants.ops(ConstantsCode::Data as u32), [[1, 2, 3, 0]]);
+    }
+
+    // The inrange bounds have the index width of the address space.
+    #[test]
+    fn write_inrange_index_width() {
+        let mut module = Module::new("test.c");
+        module.data_layout = Some("e-p:32:32-p1:64:64:64:32-p2:64:64".to_string());
+        assert_eq!(
+            [0, 1, 2, 3].map(|x| module.index_width(x)),
+            [32, 32, 64, 32]
+        );
+
+        let i32 = module.types.i32();
+        let arr_ty = module.types.array(i32, 4);
+        let ptr = module.types.ptr_in(2);
+        let arr = module.add_global_in("arr", arr_ty, 2);
+        let zero = module.const_null(arr_ty);
+        module.global_mut(arr).initializer = Some(zero);
+        let elem = module.add_global("elem", ptr);
+        let arr_value = module.global_value(arr);
+        let indices = [module.const_int(i32, 0), module.const_int(i32, 2)];
+        let gep = module.const_gep(arr_ty, arr_value, &indices, &[], Some((0, 16)));
+        module.global_mut(elem).initializer = Some(gep);
+
+        let bitcode = read(&module.to_bitcode());
+        let constants = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Constants as u32);
+        // [pointee type, flags, range width, range start, range end, ...]
+        let gep = constants.record(ConstantsCode::CeGepWithInrange as u32);
+        assert_eq!(gep.ops[..5], [1, 0, 64, 0, 32]);
+    }
+
+    #[test]
+    fn write_aliases_and_ifuncs() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let ptr = module.types.ptr();
+        let void = module.types.void();
+        let fn_ty = module.types.function(void, &[], false);
+        let resolver_ty = module.types.function(ptr, &[], false);
+
+        let g = module.add_global("g", i32);
+        let five = module.const_int(i32, 5);
+        module.global_mut(g).initializer = Some(five);
+
+        let imp = module.add_function("impl", fn_ty);
+        let resolver = module.add_function("resolver", resolver_ty);
+        let imp_value = module.function_value(imp);
+        let func = module.function_mut(imp);
+        let entry = func.append_block("entry");
+        func.push_instruction(entry, Instruction::new(InstructionKind::Ret(None), None));
+        let func = module.function_mut(resolver);
+        let entry = func.append_block("entry");
+        func.push_instruction(
+            entry,
+            Instruction::new(InstructionKind::Ret(Some(imp_value)), None),
+        );
+
+        let g_value = module.global_value(g);
+        let a = module.add_alias("a", i32, g_value);
+        module.alias_mut(a).visibility = Visibility::Hidden;
+        let b = module.add_alias("b", fn_ty, imp_value);
+        module.alias_mut(b).linkage = Linkage::WeakAny;
+        module.alias_mut(b).partition = Some("part".to_string());
+        let dispatch = module.add_ifunc("dispatch", fn_ty, resolver);
+        module.ifunc_mut(dispatch).dso_local = true;
+
+        // g is the value 0, impl 1 and resolver 2.
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let aliases = module_block.records(ModuleCode::Alias as u32);
+        // [strtab offset, strtab size, alias type, addrspace, aliasee,
+        //  linkage, visibility, dllstorageclass, threadlocal, unnamed_addr,
+        //  DSO_Local, partition strtab offset, partition strtab size]
+        assert_eq!(aliases[0].ops, [13, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(aliases[1].ops, [14, 1, 3, 0, 1, 16, 0, 0, 0, 0, 0, 15, 4]);
+        // [strtab offset, strtab size, ifunc type, addrspace, resolver,
+        //  linkage, visibility, DSO_Local, partition strtab offset,
+        //  partition strtab size]
+        let ifunc = module_block.record(ModuleCode::CodeIfunc as u32);
+        assert_eq!(ifunc.ops, [19, 8, 3, 0, 2, 0, 0, 1, 0, 0]);
+
+        let strtab = bitcode.block(BlockId::Strtab as u32);
+        let blob = strtab.record(StrtabCode::Blob as u32).blob.as_deref();
+        assert_eq!(blob, Some(&b"gimplresolverabpartdispatch"[..]));
+    }
+
+    #[test]
+    fn write_comdats() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let void = module.types.void();
+        let fn_ty = module.types.function(void, &[], false);
+
+        let inst = module.add_comdat("inst", ComdatSelectionKindCode::ANY);
+        let data = module.add_comdat("data", ComdatSelectionKindCode::LARGEST);
+
+        let g = module.add_global("g", i32);
+        let one = module.const_int(i32, 1);
+        let global = module.global_mut(g);
+        global.initializer = Some(one);
+        global.linkage = Linkage::LinkOnceOdr;
+        global.comdat = Some(data);
+
+        let f = module.add_function("inst", fn_ty);
+        let func = module.function_mut(f);
+        func.linkage = Linkage::LinkOnceOdr;
+        func.comdat = Some(inst);
+        let entry = func.append_block("entry");
+        func.push_instruction(entry, Instruction::new(InstructionKind::Ret(None), None));
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let comdats = module_block.ops(ModuleCode::Comdat as u32);
+        // [strtab offset, strtab size, selection kind]
+        assert_eq!(comdats, [[0, 4, 1], [4, 4, 3]]);
+        // The linkage is linkonce_odr and the comdats are numbered from 1.
+        let global = module_block.record(ModuleCode::GlobalVar as u32);
+        assert_eq!((global.ops[5], global.ops[13]), (19, 2));
+        let function = module_block.record(ModuleCode::Function as u32);
+        assert_eq!((function.ops[5], function.ops[14]), (19, 1));
+
+        let strtab = bitcode.block(BlockId::Strtab as u32);
+        let blob = strtab.record(StrtabCode::Blob as u32).blob.as_deref();
+        assert_eq!(blob, Some(&b"instdataginst"[..]));
+    }
+}
@@ -1,4 +1,3 @@
-
 use bytes::BufMut;
 
 #[derive(Debug, Clone, Default)]
@@ -68,10 +67,48 @@ impl BitStreamWriter {
         }
     }
 
+    /// Writes a VBR int.
+    pub fn write_vbr(&mut self, mut value: u32, width: u32) {
+        assert!((2..=32).contains(&width), "Invalid bit size for VBR");
+
+        let value_bits = width - 1;
+        let mask = (1 << value_bits) - 1;
+        let vbr = 1 << value_bits;
+
+        while value > mask {
+            let left = value >> value_bits;
+            self.write_bits(vbr | (value & mask), width);
+            value = left;
+        }
+
+        self.write_bits(value, width);
+    }
+
     pub fn bit_offset(&self) -> usize {
         self.buffer.len() * 8 + self.dword_offset as usize
     }
 
+    /// Overwrites the `width` bits written at `bit_offset`.
+    pub fn backpatch(&mut self, bit_offset: usize, value: u64, width: u32) {
+        assert!(width <= 64, "Invalid number of bits");
+        assert!(
+            bit_offset + width as usize <= self.bit_offset(),
+            "Backpatching bits not written yet"
+        );
+
+        for i in 0..width as usize {
+            let bit = (value >> i) & 1;
+            let offset = bit_offset + i;
+            if offset < self.buffer.len() * 8 {
+                let byte = &mut self.buffer[offset / 8];
+                *byte = *byte & !(1 << (offset % 8)) | (bit as u8) << (offset % 8);
+            } else {
+                let offset = offset - self.buffer.len() * 8;
+                self.dword = self.dword & !(1 << offset) | (bit as u32) << offset;
+            }
+        }
+    }
+
     pub fn pad(&mut self, width: u32) {
         self.write_bits(0, width);
     }
@@ -139,6 +176,23 @@ mod tests {
         assert_eq!(writer.bit_offset(), 32);
     }
 
+    #[test]
+    fn backpatch() {
+        let mut writer = BitStreamWriter::new();
+        writer.write_bits(1, 3);
+        writer.write_dword(0);
+        writer.write_dword(0);
+        writer.write_bits(5, 3);
+        writer.backpatch(3, 0x1234_5678_9abc_def0, 64);
+        assert_eq!(writer.bit_offset(), 70);
+        writer.flush();
+
+        let mut bytes = [0; 16];
+        bytes[..9].copy_from_slice(&writer.buffer);
+        let value = u128::from_le_bytes(bytes);
+        assert_eq!(value, 5 << 67 | 0x1234_5678_9abc_def0 << 3 | 1);
+    }
+
     #[test]
     fn write_words_and_bytes() {
         let mut writer = BitStreamWriter::new();
@@ -9,6 +9,8 @@ pub mod abbrv;
 pub mod bitstream_writer;
 pub mod block;
 pub mod operand;
+#[cfg(test)]
+pub(crate) mod reader;
 
 const ROOT_ABBR_ID_WIDTH: u32 = 2;
 const BLOCK_ID_WIDTH: u32 = 8;
@@ -108,10 +110,17 @@ impl BitStream {
 
         self.enter_block(BLOCKINFO, ROOT_ABBR_ID_WIDTH);
 
-        for (id, abbrs) in map.iter() {
-            self.write_unabrr_record(SETBID, &[*id]);
+        // Sorted so the output doesn't depend on the hash map order.
+        let mut ids: Vec<_> = map.keys().copied().collect();
+        ids.sort_unstable();
+
+        for id in ids {
+            let abbrs = &map[&id];
+            self.write_unabrr_record(SETBID, &[id]);
             for abbr in abbrs {
-                self.define_abbr(abbr);
+                // Not registered in the blockinfo block itself, they belong to the block set by SETBID.
+                self.write_abbr_id(DEFINE_ABBREV);
+                abbr.write_definition(self);
             }
         }
         self.end_block(BLOCKINFO);
@@ -150,47 +159,41 @@ impl BitStream {
         }
     }
 
-    /// Writes a VBR int.
-    pub fn write_vbr(&mut self, mut value: u32, width: u32) {
-        assert!((2..=32).contains(&width), "Invalid bit size for VBR");
-
-        let value_bits = width - 1;
-        let mask = (1 << value_bits) - 1;
-        let vbr = 1 << value_bits;
-
-        while value > mask {
-            let left = value >> value_bits;
-            self.writer.write_bits(vbr | (value & mask), width);
-            value = left;
+    /// Writes an unabbreviated record with 64-bit operands.
+    pub fn write_unabrr_record_u64(&mut self, code: u32, values: &[u64]) {
+        self.write_abbr_id(UNABBREV_RECORD);
+        self.write_vbr(code, CODE_WIDTH);
+        self.write_vbr(values.len() as u32, NUM_OPS_WIDTH);
+        for value in values {
+            self.write_vbr_u64((value >> 32) as u32, *value as u32, OP_WIDTH);
         }
+    }
 
-        self.writer.write_bits(value, width);
+    /// Writes a VBR int.
+    pub fn write_vbr(&mut self, value: u32, width: u32) {
+        self.writer.write_vbr(value, width);
     }
 
-    pub fn write_vbr_u64(&mut self, mut hi: u32, mut lo: u32, width: u32) {
-        assert!((2..=64).contains(&width), "Invalid bit size for VBR");
+    /// Writes a 64-bit VBR int, the chunks are at most 32 bits wide.
+    pub fn write_vbr_u64(&mut self, hi: u32, lo: u32, width: u32) {
+        assert!((2..=32).contains(&width), "Invalid bit size for VBR");
 
         if hi == 0 {
             return self.write_vbr(lo, width);
         }
 
-        let value_bits = width - 1;
-        let mask = (1 << value_bits) - 1;
-        let vbr = 1 << value_bits;
-
-        while hi != 0 {
-            let left = ((hi & mask) << (32 - value_bits)) | (lo >> value_bits);
+        let mut value = ((hi as u64) << 32) | lo as u64;
 
-            if left == 0 {
-                break;
-            }
+        let value_bits = width - 1;
+        let mask: u64 = (1 << value_bits) - 1;
+        let vbr: u32 = 1 << value_bits;
 
-            self.writer.write_bits(vbr | (lo & mask), width);
-            lo = left;
-            hi >>= value_bits;
+        while value > mask {
+            self.writer.write_bits(vbr | (value & mask) as u32, width);
+            value >>= value_bits;
         }
 
-        self.writer.write_bits(lo, width);
+        self.writer.write_bits(value as u32, width);
     }
 
     /// Writes the given abbr id.
@@ -205,18 +208,31 @@ impl BitStream {
         self.writer.write_bits(id, width);
     }
 
+    /// The position of the next bit written.
+    pub fn bit_offset(&self) -> usize {
+        self.writer.bit_offset()
+    }
+
+    /// Overwrites a 64-bit placeholder written at `bit_offset`.
+    pub fn backpatch_u64(&mut self, bit_offset: usize, value: u64) {
+        self.writer.backpatch(bit_offset, value, 64);
+    }
+
     pub fn align(&mut self, align: u32) {
         self.writer.align(align);
     }
+
+    /// Flushes the pending bits and returns the written bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        assert!(self.stack.is_empty(), "unterminated blocks");
+        self.writer.flush();
+        self.writer.buffer
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::bitstream::{
-        abbrv::Abbr,
-        operand::OperandDef,
-        BlockInfoMap,
-    };
+    use crate::bitstream::{abbrv::Abbr, operand::OperandDef, BlockInfoMap};
 
     use super::BitStream;
 
@@ -256,8 +272,13 @@ mod tests {
         writer.write_vbr_u64(0xabbaabba, 0xc0dec0de, 6);
         writer.writer.flush();
         let content = hex::encode(&writer.writer.buffer[4..]);
-        assert_eq!(content, "be09f72db8de");
-        // assert_eq!(content, "be09f72db8de6bedde0a");
+        assert_eq!(content, "be09f72db8de6bedde0a");
+
+        let mut writer = BitStream::new(0xdeadbeef);
+        writer.write_vbr_u64(1, 0, 32);
+        writer.writer.flush();
+        let content = hex::encode(&writer.writer.buffer[4..]);
+        assert_eq!(content, "0000008002000000");
     }
 
     #[test]
@@ -0,0 +1,371 @@
+//! Decodes a bitstream into its blocks and records, letting tests check the
+//! records they are about instead of the whole output.
+
+use std::collections::HashMap;
+
+const ROOT_ABBR_ID_WIDTH: u32 = 2;
+
+const END_BLOCK: u64 = 0;
+const ENTER_SUBBLOCK: u64 = 1;
+const DEFINE_ABBREV: u64 = 2;
+const UNABBREV_RECORD: u64 = 3;
+
+const BLOCKINFO: u32 = 0;
+const SETBID: u32 = 1;
+
+/// The id of the block holding the top level blocks.
+pub const ROOT: u32 = u32::MAX;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Literal(u64),
+    Fixed(u32),
+    Vbr(u32),
+    Array(Box<Op>),
+    Char6,
+    Blob,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub code: u32,
+    /// The operands after the code, with arrays flattened.
+    pub ops: Vec<u64>,
+    pub blob: Option<Vec<u8>>,
+    /// The position of the abbreviation id starting the record.
+    pub bit_offset: usize,
+}
+
+impl Record {
+    /// The operands from `start` on as the characters of a string.
+    pub fn string(&self, start: usize) -> String {
+        self.ops[start..]
+            .iter()
+            .map(|x| char::from(*x as u8))
+            .collect()
+    }
+
+    /// The strings of a METADATA_STRINGS record, [count, offset] with a blob
+    /// of VBR6 lengths followed by the chars at the offset.
+    pub fn metadata_strings(&self) -> Vec<String> {
+        let blob = self.blob.as_deref().expect("no blob");
+        let mut lengths = Reader {
+            data: blob,
+            pos: 0,
+            block_info: HashMap::new(),
+        };
+        let mut offset = self.ops[1] as usize;
+        (0..self.ops[0])
+            .map(|_| {
+                let len = lengths.read_vbr(6) as usize;
+                offset += len;
+                String::from_utf8(blob[offset - len..offset].to_vec()).unwrap()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub id: u32,
+    pub records: Vec<Record>,
+    pub blocks: Vec<Block>,
+}
+
+impl Block {
+    /// The only sub-block with the id.
+    pub fn block(&self, id: u32) -> &Block {
+        let mut blocks = self.blocks(id);
+        let block = blocks.next().unwrap_or_else(|| panic!("no block {id}"));
+        assert!(blocks.next().is_none(), "several blocks {id}");
+        block
+    }
+
+    pub fn blocks(&self, id: u32) -> impl Iterator<Item = &Block> {
+        self.blocks.iter().filter(move |x| x.id == id)
+    }
+
+    /// The only record with the code.
+    pub fn record(&self, code: u32) -> &Record {
+        let records = self.records(code);
+        assert_eq!(records.len(), 1, "expected one record {code}");
+        records[0]
+    }
+
+    pub fn records(&self, code: u32) -> Vec<&Record> {
+        self.records.iter().filter(|x| x.code == code).collect()
+    }
+
+    /// The operands of the records with the code.
+    pub fn ops(&self, code: u32) -> Vec<Vec<u64>> {
+        self.records(code).iter().map(|x| x.ops.clone()).collect()
+    }
+
+    /// The codes of the records, in order.
+    pub fn codes(&self) -> Vec<u32> {
+        self.records.iter().map(|x| x.code).collect()
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    block_info: HashMap<u32, Vec<Vec<Op>>>,
+}
+
+/// Reads the blocks following the 32-bit magic number.
+pub fn read(data: &[u8]) -> Block {
+    let mut reader = Reader {
+        data,
+        pos: 32,
+        block_info: HashMap::new(),
+    };
+    let mut root = Block {
+        id: ROOT,
+        records: Vec::new(),
+        blocks: Vec::new(),
+    };
+    while reader.pos < data.len() * 8 {
+        let id = reader.read(ROOT_ABBR_ID_WIDTH);
+        assert_eq!(id, ENTER_SUBBLOCK, "expected a block at the top level");
+        root.blocks.push(reader.read_block());
+    }
+    root
+}
+
+impl Reader<'_> {
+    fn read(&mut self, width: u32) -> u64 {
+        let mut value = 0;
+        for i in 0..width as usize {
+            let bit = self.data[(self.pos + i) / 8] >> ((self.pos + i) % 8) & 1;
+            value |= (bit as u64) << i;
+        }
+        self.pos += width as usize;
+        value
+    }
+
+    fn read_vbr(&mut self, width: u32) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let chunk = self.read(width);
+            value |= (chunk & ((1 << (width - 1)) - 1)) << shift;
+            if chunk >> (width - 1) == 0 {
+                return value;
+            }
+            shift += width - 1;
+        }
+    }
+
+    fn align(&mut self) {
+        self.pos = self.pos.next_multiple_of(32);
+    }
+
+    // After the ENTER_SUBBLOCK abbreviation id.
+    fn read_block(&mut self) -> Block {
+        let id = self.read_vbr(8) as u32;
+        let abbr_id_width = self.read_vbr(4) as u32;
+        self.align();
+        let words = self.read(32) as usize;
+        let end = self.pos + words * 32;
+
+        let mut block = Block {
+            id,
+            records: Vec::new(),
+            blocks: Vec::new(),
+        };
+        let mut abbrs = self.block_info.get(&id).cloned().unwrap_or_default();
+        let mut info_id = None;
+        loop {
+            let bit_offset = self.pos;
+            match self.read(abbr_id_width) {
+                END_BLOCK => {
+                    self.align();
+                    assert_eq!(self.pos, end, "block {id} has a wrong length");
+                    return block;
+                }
+                ENTER_SUBBLOCK => block.blocks.push(self.read_block()),
+                DEFINE_ABBREV => {
+                    let abbr = self.read_abbr();
+                    if id == BLOCKINFO {
+                        let info_id = info_id.expect("SETBID before the abbreviations");
+                        self.block_info.entry(info_id).or_default().push(abbr);
+                    } else {
+                        abbrs.push(abbr);
+                    }
+                }
+                UNABBREV_RECORD => {
+                    let code = self.read_vbr(6) as u32;
+                    let count = self.read_vbr(6);
+                    let ops: Vec<_> = (0..count).map(|_| self.read_vbr(6)).collect();
+                    if id == BLOCKINFO && code == SETBID {
+                        info_id = Some(ops[0] as u32);
+                    }
+                    block.records.push(Record {
+                        code,
+                        ops,
+                        blob: None,
+                        bit_offset,
+                    });
+                }
+                abbr_id => {
+                    let abbr = abbrs[abbr_id as usize - 4].clone();
+                    let mut record = self.read_abbr_record(&abbr);
+                    record.bit_offset = bit_offset;
+                    block.records.push(record);
+                }
+            }
+        }
+    }
+
+    // An array operand holds its element, which is counted as an operand too.
+    fn read_abbr(&mut self) -> Vec<Op> {
+        let count = self.read_vbr(5);
+        let mut ops = Vec::new();
+        let mut i = 0;
+        while i < count {
+            let op = self.read_op();
+            i += 1;
+            if matches!(op, Op::Array(_)) {
+                ops.push(Op::Array(Box::new(self.read_op())));
+                i += 1;
+            } else {
+                ops.push(op);
+            }
+        }
+        ops
+    }
+
+    fn read_op(&mut self) -> Op {
+        if self.read(1) == 1 {
+            return Op::Literal(self.read_vbr(8));
+        }
+        match self.read(3) {
+            1 => Op::Fixed(self.read_vbr(5) as u32),
+            2 => Op::Vbr(self.read_vbr(5) as u32),
+            // The element is read by the caller.
+            3 => Op::Array(Box::new(Op::Blob)),
+            4 => Op::Char6,
+            5 => Op::Blob,
+            x => panic!("invalid operand encoding {x}"),
+        }
+    }
+
+    fn read_abbr_record(&mut self, ops: &[Op]) -> Record {
+        let mut values = Vec::new();
+        let mut blob = None;
+        for op in ops {
+            match op {
+                Op::Array(element) => {
+                    let len = self.read_vbr(6);
+                    for _ in 0..len {
+                        values.push(self.read_scalar(element));
+                    }
+                }
+                Op::Blob => {
+                    let len = self.read_vbr(6) as usize;
+                    self.align();
+                    blob = Some((0..len).map(|_| self.read(8) as u8).collect());
+                    self.align();
+                }
+                op => values.push(self.read_scalar(op)),
+            }
+        }
+        Record {
+            code: values.remove(0) as u32,
+            ops: values,
+            blob,
+            bit_offset: 0,
+        }
+    }
+
+    fn read_scalar(&mut self, op: &Op) -> u64 {
+        match op {
+            Op::Literal(value) => *value,
+            Op::Fixed(width) => self.read(*width),
+            Op::Vbr(width) => self.read_vbr(*width),
+            Op::Char6 => {
+                let code = self.read(6) as u8;
+                (match code {
+                    0..=25 => b'a' + code,
+                    26..=51 => b'A' + code - 26,
+                    52..=61 => b'0' + code - 52,
+                    62 => b'.',
+                    _ => b'_',
+                }) as u64
+            }
+            Op::Array(_) | Op::Blob => panic!("arrays and blobs are the last operands"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitstream::{
+        abbrv::Abbr,
+        operand::{FixedValue, OperandDef, OperandValue, VBRValue},
+        BitStream, BlockInfoMap,
+    };
+
+    use super::{read, Record};
+
+    #[test]
+    fn read_records() {
+        let mut writer = BitStream::new(0xdec04342);
+        let info = Abbr::new(
+            "name",
+            &[
+                OperandDef::Literal(16),
+                OperandDef::Array(OperandDef::Char6.into()),
+            ],
+        );
+        let local = Abbr::new(
+            "values",
+            &[
+                OperandDef::Literal(7),
+                OperandDef::Fixed(3),
+                OperandDef::Vbr(6),
+                OperandDef::Blob,
+            ],
+        );
+        let mut map = BlockInfoMap::new();
+        map.insert(9, vec![info]);
+
+        writer.enter_block(8, 3);
+        writer.write_block_info(&map);
+        writer.enter_block(9, 4);
+        writer.write_record("name", &["a_Z.9".into()]);
+        writer.end_block(9);
+        writer.define_abbr(&local);
+        writer.write_unabrr_record_u64(3, &[1 << 40, 2]);
+        let offset = writer.bit_offset();
+        writer.write_record(
+            "values",
+            &[
+                OperandValue::Literal,
+                OperandValue::Fixed(FixedValue { value: 5, width: 3 }),
+                OperandValue::Vbr(VBRValue {
+                    value: 1000,
+                    width: 6,
+                }),
+                OperandValue::Blob(b"blob".to_vec()),
+            ],
+        );
+        writer.end_block(8);
+
+        let root = read(&writer.finish());
+        let module = root.block(8);
+        assert_eq!(module.block(9).record(16).string(0), "a_Z.9");
+        assert_eq!(module.codes(), [3, 7]);
+        assert_eq!(module.record(3).ops, [1 << 40, 2]);
+        assert_eq!(
+            module.record(7),
+            &Record {
+                code: 7,
+                ops: vec![5, 1000],
+                blob: Some(b"blob".to_vec()),
+                bit_offset: offset,
+            }
+        );
+    }
+}
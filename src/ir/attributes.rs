@@ -0,0 +1,363 @@
+use crate::bitcode::constants::AttributeKindCode;
+
+use super::{memory_effects::MemoryEffects, types::TypeId};
+
+/// An inclusive-exclusive `[lower, upper)` range of integers of the given bit width.
+///
+/// As in LLVM, a range where `lower == upper` is the full set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConstantRange {
+    pub bit_width: u32,
+    pub lower: i64,
+    pub upper: i64,
+}
+
+impl ConstantRange {
+    pub fn new(bit_width: u32, lower: i64, upper: i64) -> Self {
+        assert!(
+            (1..=64).contains(&bit_width),
+            "only ranges up to 64 bits are supported"
+        );
+        Self {
+            bit_width,
+            lower,
+            upper,
+        }
+    }
+}
+
+/// Value of the `uwtable` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UwTableKind {
+    Sync = 1,
+    Async = 2,
+}
+
+// https://llvm.org/docs/LangRef.html#parameter-attributes
+// https://llvm.org/docs/LangRef.html#function-attributes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    /// Attributes without a value, like `nounwind`.
+    Enum(AttributeKindCode),
+    /// Attributes with an integer value, like `align` or `dereferenceable`.
+    Int(AttributeKindCode, u64),
+    /// Attributes with a type, like `byval(<ty>)`.
+    Type(AttributeKindCode, Option<TypeId>),
+    /// `range(<ty> <lower>, <upper>)`.
+    Range(AttributeKindCode, ConstantRange),
+    /// `initializes((<lo>, <hi>), ...)`, all ranges must have the same bit width.
+    RangeList(AttributeKindCode, Vec<ConstantRange>),
+    /// `"key"="value"`, an empty value is written as just `"key"`.
+    String(String, String),
+}
+
+/// What identifies an attribute inside a set, sets hold at most one attribute per key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AttributeKey {
+    Kind(AttributeKindCode),
+    String(String),
+}
+
+impl Attribute {
+    pub fn key(&self) -> AttributeKey {
+        match self {
+            Attribute::Enum(kind)
+            | Attribute::Int(kind, _)
+            | Attribute::Type(kind, _)
+            | Attribute::Range(kind, _)
+            | Attribute::RangeList(kind, _) => AttributeKey::Kind(*kind),
+            Attribute::String(key, _) => AttributeKey::String(key.clone()),
+        }
+    }
+
+    pub fn string(key: &str, value: &str) -> Self {
+        Attribute::String(key.to_string(), value.to_string())
+    }
+
+    /// Alignment in bytes of a parameter or return value.
+    pub fn align(bytes: u64) -> Self {
+        assert!(bytes.is_power_of_two(), "alignment must be a power of 2");
+        Attribute::Int(AttributeKindCode::ALIGNMENT, bytes)
+    }
+
+    /// Stack alignment in bytes of a function.
+    pub fn stack_align(bytes: u64) -> Self {
+        assert!(bytes.is_power_of_two(), "alignment must be a power of 2");
+        Attribute::Int(AttributeKindCode::STACK_ALIGNMENT, bytes)
+    }
+
+    pub fn dereferenceable(bytes: u64) -> Self {
+        Attribute::Int(AttributeKindCode::DEREFERENCEABLE, bytes)
+    }
+
+    pub fn dereferenceable_or_null(bytes: u64) -> Self {
+        Attribute::Int(AttributeKindCode::DEREFERENCEABLE_OR_NULL, bytes)
+    }
+
+    /// `allocsize(<elem_size_arg>[, <num_elems_arg>])`
+    pub fn alloc_size(elem_size_arg: u32, num_elems_arg: Option<u32>) -> Self {
+        // The number of elements is not present when all bits are set.
+        assert_ne!(num_elems_arg, Some(u32::MAX), "invalid allocsize argument");
+        let num_elems = num_elems_arg.unwrap_or(u32::MAX);
+        Attribute::Int(
+            AttributeKindCode::ALLOC_SIZE,
+            ((elem_size_arg as u64) << 32) | num_elems as u64,
+        )
+    }
+
+    /// `vscale_range(<min>[, <max>])`, no max means unbounded.
+    pub fn vscale_range(min: u32, max: Option<u32>) -> Self {
+        Attribute::Int(
+            AttributeKindCode::VSCALE_RANGE,
+            ((min as u64) << 32) | max.unwrap_or(0) as u64,
+        )
+    }
+
+    pub fn uwtable(kind: UwTableKind) -> Self {
+        Attribute::Int(AttributeKindCode::UW_TABLE, kind as u64)
+    }
+
+    /// `allockind(...)`, takes the raw `AllocFnKind` bits.
+    pub fn alloc_kind(kind: u64) -> Self {
+        Attribute::Int(AttributeKindCode::ALLOC_KIND, kind)
+    }
+
+    /// `nofpclass(...)`, takes the raw `FPClassTest` mask.
+    pub fn nofpclass(mask: u32) -> Self {
+        assert!(mask < (1 << 10), "invalid floating point class mask");
+        Attribute::Int(AttributeKindCode::NOFPCLASS, mask as u64)
+    }
+
+    /// `memory(...)`
+    pub fn memory(effects: MemoryEffects) -> Self {
+        effects.into()
+    }
+
+    pub fn byval(ty: TypeId) -> Self {
+        Attribute::Type(AttributeKindCode::BY_VAL, Some(ty))
+    }
+
+    pub fn sret(ty: TypeId) -> Self {
+        Attribute::Type(AttributeKindCode::STRUCT_RET, Some(ty))
+    }
+
+    pub fn byref(ty: TypeId) -> Self {
+        Attribute::Type(AttributeKindCode::BYREF, Some(ty))
+    }
+
+    pub fn inalloca(ty: TypeId) -> Self {
+        Attribute::Type(AttributeKindCode::IN_ALLOCA, Some(ty))
+    }
+
+    pub fn preallocated(ty: TypeId) -> Self {
+        Attribute::Type(AttributeKindCode::PREALLOCATED, Some(ty))
+    }
+
+    pub fn elementtype(ty: TypeId) -> Self {
+        Attribute::Type(AttributeKindCode::ELEMENTTYPE, Some(ty))
+    }
+
+    pub fn range(range: ConstantRange) -> Self {
+        Attribute::Range(AttributeKindCode::RANGE, range)
+    }
+
+    pub fn initializes(ranges: &[ConstantRange]) -> Self {
+        assert!(!ranges.is_empty(), "initializes needs at least one range");
+        assert!(
+            ranges.iter().all(|x| x.bit_width == ranges[0].bit_width),
+            "initializes ranges must have the same bit width"
+        );
+        Attribute::RangeList(AttributeKindCode::INITIALIZES, ranges.to_vec())
+    }
+}
+
+impl From<AttributeKindCode> for Attribute {
+    fn from(kind: AttributeKindCode) -> Self {
+        Attribute::Enum(kind)
+    }
+}
+
+/// A set of attributes, sorted by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AttributeSet {
+    attrs: Vec<Attribute>,
+}
+
+impl AttributeSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attribute, replacing the one with the same key if present.
+    pub fn add(&mut self, attr: impl Into<Attribute>) {
+        let attr = attr.into();
+        let key = attr.key();
+
+        match self.attrs.binary_search_by(|x| x.key().cmp(&key)) {
+            Ok(i) => self.attrs[i] = attr,
+            Err(i) => self.attrs.insert(i, attr),
+        }
+    }
+
+    pub fn with(mut self, attr: impl Into<Attribute>) -> Self {
+        self.add(attr);
+        self
+    }
+
+    pub fn remove(&mut self, key: &AttributeKey) -> Option<Attribute> {
+        let i = self.attrs.iter().position(|x| &x.key() == key)?;
+        Some(self.attrs.remove(i))
+    }
+
+    pub fn get(&self, key: &AttributeKey) -> Option<&Attribute> {
+        self.attrs.iter().find(|x| &x.key() == key)
+    }
+
+    pub fn contains(&self, kind: AttributeKindCode) -> bool {
+        self.get(&AttributeKey::Kind(kind)).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Attribute> {
+        self.attrs.iter()
+    }
+}
+
+impl<A: Into<Attribute>> FromIterator<A> for AttributeSet {
+    fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
+        let mut set = AttributeSet::new();
+        for attr in iter {
+            set.add(attr);
+        }
+        set
+    }
+}
+
+/// The attributes of a function or call site: function, return value and
+/// parameter attribute sets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AttributeList {
+    function: AttributeSet,
+    ret: AttributeSet,
+    // Trailing empty sets are never stored so equal lists compare equal.
+    params: Vec<AttributeSet>,
+}
+
+impl AttributeList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.function.is_empty() && self.ret.is_empty() && self.params.is_empty()
+    }
+
+    pub fn function(&self) -> &AttributeSet {
+        &self.function
+    }
+
+    pub fn ret(&self) -> &AttributeSet {
+        &self.ret
+    }
+
+    pub fn param(&self, index: usize) -> Option<&AttributeSet> {
+        self.params.get(index)
+    }
+
+    pub fn params(&self) -> &[AttributeSet] {
+        &self.params
+    }
+
+    pub fn add_function(&mut self, attr: impl Into<Attribute>) {
+        self.function.add(attr);
+    }
+
+    pub fn add_ret(&mut self, attr: impl Into<Attribute>) {
+        self.ret.add(attr);
+    }
+
+    pub fn add_param(&mut self, index: usize, attr: impl Into<Attribute>) {
+        if self.params.len() <= index {
+            self.params.resize_with(index + 1, Default::default);
+        }
+        self.params[index].add(attr);
+    }
+
+    pub fn set_function(&mut self, set: AttributeSet) {
+        self.function = set;
+    }
+
+    pub fn set_ret(&mut self, set: AttributeSet) {
+        self.ret = set;
+    }
+
+    pub fn set_param(&mut self, index: usize, set: AttributeSet) {
+        if self.params.len() <= index {
+            self.params.resize_with(index + 1, Default::default);
+        }
+        self.params[index] = set;
+
+        while self.params.last().is_some_and(|x| x.is_empty()) {
+            self.params.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitcode::constants::AttributeKindCode;
+
+    use super::{Attribute, AttributeKey, AttributeList, AttributeSet};
+
+    #[test]
+    fn sets_are_sorted_and_deduplicated() {
+        let a = AttributeSet::new()
+            .with(AttributeKindCode::NO_UNWIND)
+            .with(Attribute::align(8))
+            .with(Attribute::string("target-cpu", "x86-64"))
+            .with(Attribute::align(16));
+
+        let b = AttributeSet::new()
+            .with(Attribute::string("target-cpu", "x86-64"))
+            .with(Attribute::align(16))
+            .with(AttributeKindCode::NO_UNWIND);
+
+        assert_eq!(a, b);
+        assert_eq!(a.iter().count(), 3);
+        assert_eq!(
+            a.get(&AttributeKey::Kind(AttributeKindCode::ALIGNMENT)),
+            Some(&Attribute::align(16))
+        );
+    }
+
+    #[test]
+    fn packed_int_attributes() {
+        assert_eq!(
+            Attribute::alloc_size(0, None),
+            Attribute::Int(AttributeKindCode::ALLOC_SIZE, 0xffffffff)
+        );
+        assert_eq!(
+            Attribute::alloc_size(1, Some(2)),
+            Attribute::Int(AttributeKindCode::ALLOC_SIZE, (1 << 32) | 2)
+        );
+        assert_eq!(
+            Attribute::vscale_range(1, Some(16)),
+            Attribute::Int(AttributeKindCode::VSCALE_RANGE, (1 << 32) | 16)
+        );
+    }
+
+    #[test]
+    fn lists_ignore_trailing_empty_params() {
+        let mut a = AttributeList::new();
+        a.add_param(0, AttributeKindCode::NOUNDEF);
+        a.set_param(2, AttributeSet::new());
+
+        let mut b = AttributeList::new();
+        b.add_param(0, AttributeKindCode::NOUNDEF);
+
+        assert_eq!(a, b);
+    }
+}
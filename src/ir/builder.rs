@@ -0,0 +1,2418 @@
+use crate::bitcode::constants::{
+    AtomicOrderingCode, BinaryOpCode, CastCodes, GetElementPtrOptionalFlag, RMWOperation,
+    UnaryOpCodes,
+};
+
+use super::{
+    constants::{ConstantKind, InlineAsm},
+    instructions::{
+        Atomic, BinOpFlags, BundleTag, CallInst, CastFlags, DebugRecord, DebugRecordKind,
+        FloatPredicate, Instruction, InstructionKind, IntPredicate, LandingPadClause,
+        OperandBundle, SyncScope, TailCallKind,
+    },
+    metadata::{Metadata, MetadataId, MetadataKind},
+    module::Module,
+    types::{ElementCount, Type, TypeId},
+    value::{BasicBlockId, FunctionId, InstId, Value, ValueRef},
+};
+
+/// Appends instructions to a basic block, checking the types of the operands.
+///
+/// Misuse, like adding an `i32` to an `i64` or loading from something that isn't a
+/// pointer, panics.
+// https://llvm.org/docs/LangRef.html#instruction-reference
+pub struct Builder<'m> {
+    module: &'m mut Module,
+    function: FunctionId,
+    block: Option<BasicBlockId>,
+    fast_math: u64,
+    debug_loc: Option<MetadataId>,
+    debug_records: Vec<DebugRecord>,
+}
+
+impl<'m> Builder<'m> {
+    /// A builder for the function, call [`Builder::position_at_end`] before adding
+    /// instructions.
+    pub fn new(module: &'m mut Module, function: FunctionId) -> Self {
+        Self {
+            module,
+            function,
+            block: None,
+            fast_math: 0,
+            debug_loc: None,
+            debug_records: Vec::new(),
+        }
+    }
+
+    pub fn module(&mut self) -> &mut Module {
+        self.module
+    }
+
+    pub fn function(&self) -> FunctionId {
+        self.function
+    }
+
+    /// Appends a new basic block to the function, without moving the builder.
+    pub fn append_block(&mut self, name: &str) -> BasicBlockId {
+        self.module.function_mut(self.function).append_block(name)
+    }
+
+    pub fn position_at_end(&mut self, block: BasicBlockId) {
+        assert!(
+            self.debug_records.is_empty(),
+            "debug records must be followed by an instruction"
+        );
+        self.block = Some(block);
+    }
+
+    pub fn insertion_block(&self) -> Option<BasicBlockId> {
+        self.block
+    }
+
+    /// Fast math flags (see [`FastMathMap`](crate::bitcode::constants::FastMathMap))
+    /// added to the floating point instructions built from now on.
+    pub fn set_fast_math_flags(&mut self, flags: u64) {
+        self.fast_math = flags;
+    }
+
+    /// The [`DILocation`](super::debug_info::DILocation) of the instructions built
+    /// from now on.
+    pub fn set_debug_loc(&mut self, loc: Option<MetadataId>) {
+        if let Some(loc) = loc {
+            assert!(
+                matches!(self.module.metadata.get(loc), Metadata::Location(_)),
+                "debug locations must be DILocations"
+            );
+        }
+        self.debug_loc = loc;
+    }
+
+    /// Attaches the node to an instruction of the function.
+    pub fn set_metadata(&mut self, inst: InstId, kind: MetadataKind, node: MetadataId) {
+        assert!(
+            self.module.metadata.get(node).is_node(),
+            "attachments must be nodes"
+        );
+        assert_ne!(
+            kind,
+            MetadataKind::DBG,
+            "instruction locations are set with set_debug_loc"
+        );
+        self.module
+            .function_mut(self.function)
+            .instruction_mut(inst)
+            .metadata
+            .set(kind, node);
+    }
+
+    /// Attaches the `!llvm.loop` ID made by
+    /// [`MDBuilder::create_loop_id`](super::md_builder::MDBuilder::create_loop_id)
+    /// to a branch back to the loop header.
+    pub fn set_loop(&mut self, inst: InstId, loop_id: MetadataId) {
+        assert!(
+            matches!(
+                self.module.function(self.function).instruction(inst).kind,
+                InstructionKind::Br(_) | InstructionKind::CondBr { .. }
+            ),
+            "loop IDs are attached to latch branches"
+        );
+        assert!(
+            matches!(
+                self.module.metadata.get(loop_id),
+                Metadata::Node(node) if node.distinct && node.operands.first() == Some(&Some(loop_id))
+            ),
+            "loop IDs are distinct nodes referencing themselves"
+        );
+        self.set_metadata(inst, MetadataKind::LOOP, loop_id);
+    }
+
+    fn check_metadata(&self, id: MetadataId, valid: bool, what: &str) {
+        assert!(
+            valid,
+            "expected {what}, got {:?}",
+            self.module.metadata.get(id)
+        );
+    }
+
+    // Either a record placed before the next instruction, or a call to the
+    // `llvm.dbg.*` intrinsic taking the metadata.
+    fn push_debug_record(&mut self, record: DebugRecord, intrinsic: &str, args: &[MetadataId]) {
+        if !self.module.debug_intrinsics {
+            self.debug_records.push(record);
+            return;
+        }
+
+        let function = match self.module.get_function(intrinsic) {
+            Some(function) => function,
+            None => {
+                let void = self.module.types.void();
+                let metadata = self.module.types.metadata();
+                let fn_ty = self
+                    .module
+                    .types
+                    .function(void, &vec![metadata; args.len()], false);
+                self.module.add_function(intrinsic, fn_ty)
+            }
+        };
+        let args: Vec<_> = args
+            .iter()
+            .map(|x| self.module.metadata_as_value(*x))
+            .collect();
+        self.call(function, &args, "");
+    }
+
+    fn record_debug_loc(&self) -> MetadataId {
+        self.debug_loc
+            .expect("debug records need a debug location, see set_debug_loc")
+    }
+
+    // https://llvm.org/docs/LangRef.html#debug-records
+    fn dbg_variable(
+        &mut self,
+        kind: DebugRecordKind,
+        location: MetadataId,
+        variable: MetadataId,
+        expression: MetadataId,
+    ) {
+        let table = &self.module.metadata;
+        self.check_metadata(
+            location,
+            matches!(
+                table.get(location),
+                Metadata::Value(_) | Metadata::ArgList(_)
+            ),
+            "a value or an arg list",
+        );
+        self.check_metadata(
+            variable,
+            matches!(table.get(variable), Metadata::LocalVariable(_)),
+            "a DILocalVariable",
+        );
+        self.check_metadata(
+            expression,
+            matches!(table.get(expression), Metadata::Expression(_)),
+            "a DIExpression",
+        );
+        if let Metadata::Value(value) = table.get(location) {
+            self.check_local(*value);
+        }
+
+        let record = DebugRecord::Variable {
+            kind,
+            location,
+            variable,
+            expression,
+            debug_loc: self.record_debug_loc(),
+        };
+        let (intrinsic, args) = match kind {
+            DebugRecordKind::Value => ("llvm.dbg.value", vec![location, variable, expression]),
+            DebugRecordKind::Declare => ("llvm.dbg.declare", vec![location, variable, expression]),
+            DebugRecordKind::Assign {
+                assign_id,
+                address,
+                address_expression,
+            } => (
+                "llvm.dbg.assign",
+                vec![
+                    location,
+                    variable,
+                    expression,
+                    assign_id,
+                    address,
+                    address_expression,
+                ],
+            ),
+        };
+        self.push_debug_record(record, intrinsic, &args);
+    }
+
+    /// The variable has the value of `location`, a value wrapped with
+    /// [`Module::md_value`] or a `DIArgList`, from the next instruction on.
+    pub fn dbg_value(
+        &mut self,
+        location: MetadataId,
+        variable: MetadataId,
+        expression: MetadataId,
+    ) {
+        self.dbg_variable(DebugRecordKind::Value, location, variable, expression);
+    }
+
+    /// The variable lives at `address`, usually an alloca.
+    pub fn dbg_declare(&mut self, address: Value, variable: MetadataId, expression: MetadataId) {
+        self.check_local(address);
+        assert!(self.ty(address.ty).is_pointer(), "expected an address");
+        let address = self.module.md_value(address);
+        self.dbg_variable(DebugRecordKind::Declare, address, variable, expression);
+    }
+
+    /// Like [`Builder::dbg_value`], also linked to the stores with the
+    /// `assign_id` attachment writing the variable at `address`.
+    pub fn dbg_assign(
+        &mut self,
+        location: MetadataId,
+        variable: MetadataId,
+        expression: MetadataId,
+        assign_id: MetadataId,
+        address: Value,
+        address_expression: MetadataId,
+    ) {
+        self.check_metadata(
+            assign_id,
+            matches!(self.module.metadata.get(assign_id), Metadata::AssignId),
+            "a DIAssignID",
+        );
+        self.check_metadata(
+            address_expression,
+            matches!(
+                self.module.metadata.get(address_expression),
+                Metadata::Expression(_)
+            ),
+            "a DIExpression",
+        );
+        self.check_local(address);
+        let address = self.module.md_value(address);
+        let kind = DebugRecordKind::Assign {
+            assign_id,
+            address,
+            address_expression,
+        };
+        self.dbg_variable(kind, location, variable, expression);
+    }
+
+    /// The position of a source label.
+    pub fn dbg_label(&mut self, label: MetadataId) {
+        self.check_metadata(
+            label,
+            matches!(self.module.metadata.get(label), Metadata::Label(_)),
+            "a DILabel",
+        );
+        let record = DebugRecord::Label {
+            label,
+            debug_loc: self.record_debug_loc(),
+        };
+        self.push_debug_record(record, "llvm.dbg.label", &[label]);
+    }
+
+    fn ty(&self, id: TypeId) -> &Type {
+        self.module.types.get(id)
+    }
+
+    fn scalar(&self, id: TypeId) -> &Type {
+        self.ty(self.module.types.scalar_type(id))
+    }
+
+    fn push(&mut self, kind: InstructionKind, ty: Option<TypeId>, name: &str) -> InstId {
+        let block = self
+            .block
+            .expect("the builder is not positioned at a block");
+        let function = self.module.function_mut(self.function);
+
+        if let Some(last) = function.block(block).instructions().last() {
+            assert!(
+                !function.instruction(*last).kind.is_terminator(),
+                "the block is already terminated"
+            );
+        }
+
+        let mut inst = Instruction::new(kind, ty);
+        inst.debug_loc = self.debug_loc;
+        inst.debug_records = std::mem::take(&mut self.debug_records);
+        if !name.is_empty() {
+            assert!(ty.is_some(), "instructions without a value can't be named");
+            inst.name = Some(name.to_string());
+        }
+        function.push_instruction(block, inst)
+    }
+
+    fn push_value(&mut self, kind: InstructionKind, ty: TypeId, name: &str) -> Value {
+        let id = self.push(kind, Some(ty), name);
+        Value::local(ValueRef::Instruction(id), ty, self.function)
+    }
+
+    fn check_local(&self, value: Value) {
+        if value.kind.is_local() {
+            assert_eq!(
+                value.function,
+                Some(self.function),
+                "{:?} doesn't belong to the function",
+                value.kind
+            );
+        }
+    }
+
+    fn check_same_type(&self, lhs: Value, rhs: Value) {
+        self.check_local(lhs);
+        self.check_local(rhs);
+        assert_eq!(lhs.ty, rhs.ty, "operands have different types");
+    }
+
+    fn is_block(&self, block: BasicBlockId) -> bool {
+        block.index() < self.module.function(self.function).blocks.len()
+    }
+
+    pub fn ret(&mut self, value: Value) -> InstId {
+        self.check_local(value);
+        assert_eq!(
+            self.module.function(self.function).return_type(),
+            value.ty,
+            "returned value doesn't match the return type"
+        );
+        self.push(InstructionKind::Ret(Some(value)), None, "")
+    }
+
+    pub fn ret_void(&mut self) -> InstId {
+        let ret = self.module.function(self.function).return_type();
+        assert_eq!(
+            self.ty(ret),
+            &Type::Void,
+            "the function must return a value"
+        );
+        self.push(InstructionKind::Ret(None), None, "")
+    }
+
+    pub fn br(&mut self, dest: BasicBlockId) -> InstId {
+        assert!(self.is_block(dest), "invalid block");
+        self.push(InstructionKind::Br(dest), None, "")
+    }
+
+    pub fn cond_br(
+        &mut self,
+        cond: Value,
+        then_dest: BasicBlockId,
+        else_dest: BasicBlockId,
+    ) -> InstId {
+        self.check_local(cond);
+        assert_eq!(
+            self.ty(cond.ty),
+            &Type::Integer(1),
+            "condition must be an i1"
+        );
+        assert!(
+            self.is_block(then_dest) && self.is_block(else_dest),
+            "invalid block"
+        );
+
+        self.push(
+            InstructionKind::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            },
+            None,
+            "",
+        )
+    }
+
+    /// The case values must be integer constants of the type of the value.
+    pub fn switch(
+        &mut self,
+        value: Value,
+        default: BasicBlockId,
+        cases: &[(Value, BasicBlockId)],
+    ) -> InstId {
+        self.check_local(value);
+        assert!(self.ty(value.ty).is_integer(), "switch needs an integer");
+        assert!(self.is_block(default), "invalid block");
+
+        for (case, dest) in cases {
+            assert_eq!(case.ty, value.ty, "case value type mismatch");
+            assert!(
+                matches!(
+                    case.kind,
+                    ValueRef::Constant(id)
+                        if matches!(self.module.constants().get(id).kind, ConstantKind::Int(_))
+                ),
+                "case values must be integer constants"
+            );
+            assert!(self.is_block(*dest), "invalid block");
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        assert!(
+            cases.iter().all(|(x, _)| seen.insert(x.kind)),
+            "duplicate case value"
+        );
+
+        self.push(
+            InstructionKind::Switch {
+                value,
+                default,
+                cases: cases.to_vec(),
+            },
+            None,
+            "",
+        )
+    }
+
+    pub fn indirect_br(&mut self, address: Value, dests: &[BasicBlockId]) -> InstId {
+        self.check_local(address);
+        assert!(
+            self.ty(address.ty).is_pointer(),
+            "address must be a pointer"
+        );
+        assert!(dests.iter().all(|x| self.is_block(*x)), "invalid block");
+
+        self.push(
+            InstructionKind::IndirectBr {
+                address,
+                dests: dests.to_vec(),
+            },
+            None,
+            "",
+        )
+    }
+
+    pub fn unreachable(&mut self) -> InstId {
+        self.push(InstructionKind::Unreachable, None, "")
+    }
+
+    pub fn fneg(&mut self, value: Value, name: &str) -> Value {
+        self.check_local(value);
+        assert!(
+            self.scalar(value.ty).is_floating_point(),
+            "fneg needs a floating point operand"
+        );
+
+        let flags = self.fast_math;
+        self.push_value(
+            InstructionKind::UnOp {
+                op: UnaryOpCodes::Fneg,
+                value,
+                flags,
+            },
+            value.ty,
+            name,
+        )
+    }
+
+    /// An integer binary operator.
+    pub fn binop(
+        &mut self,
+        op: BinaryOpCode,
+        lhs: Value,
+        rhs: Value,
+        flags: BinOpFlags,
+        name: &str,
+    ) -> Value {
+        self.check_same_type(lhs, rhs);
+        assert!(
+            self.scalar(lhs.ty).is_integer(),
+            "{op:?} needs integer operands"
+        );
+
+        self.push_value(
+            InstructionKind::BinOp {
+                op,
+                lhs,
+                rhs,
+                flags: flags.encode(op),
+            },
+            lhs.ty,
+            name,
+        )
+    }
+
+    // Floating point operators share the opcodes of the integer ones.
+    fn float_binop(&mut self, op: BinaryOpCode, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.check_same_type(lhs, rhs);
+        assert!(
+            self.scalar(lhs.ty).is_floating_point(),
+            "floating point operator needs floating point operands"
+        );
+
+        let flags = self.fast_math;
+        self.push_value(
+            InstructionKind::BinOp {
+                op,
+                lhs,
+                rhs,
+                flags,
+            },
+            lhs.ty,
+            name,
+        )
+    }
+
+    pub fn add(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::Add, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn sub(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::Sub, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn mul(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::Mul, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn udiv(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::UDiv, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn sdiv(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::SDiv, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn urem(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::URem, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn srem(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::SRem, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn shl(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::Shl, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn lshr(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::LShr, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn ashr(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::AShr, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn and(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::And, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn or(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::Or, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn xor(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.binop(BinaryOpCode::Xor, lhs, rhs, BinOpFlags::default(), name)
+    }
+
+    pub fn fadd(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.float_binop(BinaryOpCode::Add, lhs, rhs, name)
+    }
+
+    pub fn fsub(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.float_binop(BinaryOpCode::Sub, lhs, rhs, name)
+    }
+
+    pub fn fmul(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.float_binop(BinaryOpCode::Mul, lhs, rhs, name)
+    }
+
+    pub fn fdiv(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.float_binop(BinaryOpCode::SDiv, lhs, rhs, name)
+    }
+
+    pub fn frem(&mut self, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.float_binop(BinaryOpCode::SRem, lhs, rhs, name)
+    }
+
+    pub fn cast(
+        &mut self,
+        op: CastCodes,
+        value: Value,
+        dest_ty: TypeId,
+        flags: CastFlags,
+        name: &str,
+    ) -> Value {
+        self.check_local(value);
+        self.module.check_cast(op, value.ty, dest_ty);
+
+        self.push_value(
+            InstructionKind::Cast {
+                op,
+                value,
+                flags: flags.encode(op),
+            },
+            dest_ty,
+            name,
+        )
+    }
+
+    pub fn trunc(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(CastCodes::Trunc, value, dest_ty, CastFlags::default(), name)
+    }
+
+    pub fn zext(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(CastCodes::Zext, value, dest_ty, CastFlags::default(), name)
+    }
+
+    pub fn sext(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(CastCodes::Sext, value, dest_ty, CastFlags::default(), name)
+    }
+
+    pub fn fptrunc(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::FpTrunc,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn fpext(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(CastCodes::FpExt, value, dest_ty, CastFlags::default(), name)
+    }
+
+    pub fn fptoui(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::FpToUi,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn fptosi(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::FpToSi,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn uitofp(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::UiToFp,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn sitofp(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::SiToFp,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn ptrtoint(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::PtrToInt,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn inttoptr(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::IntToPtr,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn bitcast(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::Bitcast,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    pub fn addrspacecast(&mut self, value: Value, dest_ty: TypeId, name: &str) -> Value {
+        self.cast(
+            CastCodes::AddspaceCast,
+            value,
+            dest_ty,
+            CastFlags::default(),
+            name,
+        )
+    }
+
+    // i1 or a vector of i1 with the shape of the operands.
+    fn cmp_type(&mut self, operand: TypeId) -> TypeId {
+        let i1 = self.module.types.i1();
+        self.module.types.with_scalar_type(operand, i1)
+    }
+
+    pub fn icmp(&mut self, predicate: IntPredicate, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.check_same_type(lhs, rhs);
+        let scalar = self.scalar(lhs.ty);
+        assert!(
+            scalar.is_integer() || scalar.is_pointer(),
+            "icmp needs integer or pointer operands"
+        );
+
+        let ty = self.cmp_type(lhs.ty);
+        self.push_value(
+            InstructionKind::ICmp {
+                predicate,
+                lhs,
+                rhs,
+            },
+            ty,
+            name,
+        )
+    }
+
+    pub fn fcmp(&mut self, predicate: FloatPredicate, lhs: Value, rhs: Value, name: &str) -> Value {
+        self.check_same_type(lhs, rhs);
+        assert!(
+            self.scalar(lhs.ty).is_floating_point(),
+            "fcmp needs floating point operands"
+        );
+
+        let ty = self.cmp_type(lhs.ty);
+        let flags = self.fast_math;
+        self.push_value(
+            InstructionKind::FCmp {
+                predicate,
+                lhs,
+                rhs,
+                flags,
+            },
+            ty,
+            name,
+        )
+    }
+
+    pub fn select(
+        &mut self,
+        cond: Value,
+        then_value: Value,
+        else_value: Value,
+        name: &str,
+    ) -> Value {
+        self.check_local(cond);
+        self.check_same_type(then_value, else_value);
+        assert_eq!(
+            self.scalar(cond.ty),
+            &Type::Integer(1),
+            "select condition must be i1 or a vector of i1"
+        );
+        if let Some(count) = self.module.types.element_count(cond.ty) {
+            assert_eq!(
+                self.module.types.element_count(then_value.ty),
+                Some(count),
+                "vector select condition doesn't match the values"
+            );
+        }
+
+        let flags = if self.scalar(then_value.ty).is_floating_point() {
+            self.fast_math
+        } else {
+            0
+        };
+        self.push_value(
+            InstructionKind::Select {
+                cond,
+                then_value,
+                else_value,
+                flags,
+            },
+            then_value.ty,
+            name,
+        )
+    }
+
+    fn check_vector(&self, vector: Value, what: &str) -> ElementCount {
+        self.check_local(vector);
+        self.module
+            .types
+            .element_count(vector.ty)
+            .unwrap_or_else(|| panic!("{what} needs a vector"))
+    }
+
+    // https://llvm.org/docs/LangRef.html#extractelement-instruction
+    pub fn extractelement(&mut self, vector: Value, index: Value, name: &str) -> Value {
+        self.check_vector(vector, "extractelement");
+        self.check_local(index);
+        assert!(
+            self.ty(index.ty).is_integer(),
+            "the index must be an integer"
+        );
+
+        let element = self.module.types.scalar_type(vector.ty);
+        self.push_value(
+            InstructionKind::ExtractElement { vector, index },
+            element,
+            name,
+        )
+    }
+
+    // https://llvm.org/docs/LangRef.html#insertelement-instruction
+    pub fn insertelement(
+        &mut self,
+        vector: Value,
+        element: Value,
+        index: Value,
+        name: &str,
+    ) -> Value {
+        self.check_vector(vector, "insertelement");
+        self.check_local(element);
+        self.check_local(index);
+        assert_eq!(
+            self.module.types.scalar_type(vector.ty),
+            element.ty,
+            "element type mismatch"
+        );
+        assert!(
+            self.ty(index.ty).is_integer(),
+            "the index must be an integer"
+        );
+
+        self.push_value(
+            InstructionKind::InsertElement {
+                vector,
+                element,
+                index,
+            },
+            vector.ty,
+            name,
+        )
+    }
+
+    /// Picks the lanes of the result from `lhs` and `rhs`, `mask` indexes into their
+    /// concatenation and `None` lanes are poison.
+    ///
+    /// Scalable vectors can only be splatted from lane 0 or left poison, so their
+    /// mask must be all `Some(0)` or all `None`.
+    // https://llvm.org/docs/LangRef.html#shufflevector-instruction
+    pub fn shufflevector(
+        &mut self,
+        lhs: Value,
+        rhs: Value,
+        mask: &[Option<u32>],
+        name: &str,
+    ) -> Value {
+        let count = self.check_vector(lhs, "shufflevector");
+        self.check_same_type(lhs, rhs);
+        assert!(!mask.is_empty(), "the mask can't be empty");
+
+        let i32 = self.module.types.i32();
+        let mask_count = ElementCount {
+            min: mask.len() as u32,
+            scalable: count.scalable,
+        };
+        let mask_ty = self.module.types.vector_of(i32, mask_count);
+
+        // The same constants LLVM canonicalizes masks to.
+        let mask = if count.scalable {
+            assert!(
+                mask.iter().all(|x| *x == Some(0)) || mask.iter().all(|x| x.is_none()),
+                "scalable vector masks must be all zero or all poison"
+            );
+            match mask[0] {
+                Some(_) => self.module.const_null(mask_ty),
+                None => self.module.const_poison(mask_ty),
+            }
+        } else {
+            assert!(
+                mask.iter().flatten().all(|x| *x < count.min * 2),
+                "mask index out of range"
+            );
+            if mask.iter().all(|x| x.is_none()) {
+                self.module.const_poison(mask_ty)
+            } else if mask.iter().all(|x| *x == Some(0)) {
+                self.module.const_null(mask_ty)
+            } else if mask.iter().all(|x| x.is_some()) {
+                let data: Vec<_> = mask.iter().flatten().map(|x| *x as u64).collect();
+                self.module.const_data(mask_ty, &data)
+            } else {
+                let elements: Vec<_> = mask
+                    .iter()
+                    .map(|x| match x {
+                        Some(x) => self.module.const_int(i32, *x as i128),
+                        None => self.module.const_poison(i32),
+                    })
+                    .collect();
+                self.module.const_aggregate(mask_ty, &elements)
+            }
+        };
+
+        let element = self.module.types.scalar_type(lhs.ty);
+        let ty = self.module.types.vector_of(element, mask_count);
+        self.push_value(InstructionKind::ShuffleVector { lhs, rhs, mask }, ty, name)
+    }
+
+    fn indexed_type(&self, aggregate: Value, indices: &[u32]) -> TypeId {
+        self.check_local(aggregate);
+        assert!(!indices.is_empty(), "at least one index is needed");
+        self.module
+            .types
+            .indexed_type(aggregate.ty, indices)
+            .unwrap_or_else(|| {
+                panic!(
+                    "invalid indices {indices:?} into {:?}",
+                    self.ty(aggregate.ty)
+                )
+            })
+    }
+
+    // https://llvm.org/docs/LangRef.html#extractvalue-instruction
+    pub fn extractvalue(&mut self, aggregate: Value, indices: &[u32], name: &str) -> Value {
+        let ty = self.indexed_type(aggregate, indices);
+        self.push_value(
+            InstructionKind::ExtractValue {
+                aggregate,
+                indices: indices.to_vec(),
+            },
+            ty,
+            name,
+        )
+    }
+
+    // https://llvm.org/docs/LangRef.html#insertvalue-instruction
+    pub fn insertvalue(
+        &mut self,
+        aggregate: Value,
+        value: Value,
+        indices: &[u32],
+        name: &str,
+    ) -> Value {
+        let ty = self.indexed_type(aggregate, indices);
+        self.check_local(value);
+        assert_eq!(ty, value.ty, "inserted value type mismatch");
+
+        self.push_value(
+            InstructionKind::InsertValue {
+                aggregate,
+                value,
+                indices: indices.to_vec(),
+            },
+            aggregate.ty,
+            name,
+        )
+    }
+
+    /// A phi without incoming values, add them with [`Builder::add_incoming`].
+    pub fn phi(&mut self, ty: TypeId, name: &str) -> Value {
+        assert!(self.ty(ty).is_first_class(), "invalid phi type");
+
+        let block = self
+            .block
+            .expect("the builder is not positioned at a block");
+        let function = self.module.function(self.function);
+        assert!(
+            function
+                .block(block)
+                .instructions()
+                .iter()
+                .all(|x| matches!(function.instruction(*x).kind, InstructionKind::Phi { .. })),
+            "phis must be at the start of the block"
+        );
+
+        let flags = if self.scalar(ty).is_floating_point() {
+            self.fast_math
+        } else {
+            0
+        };
+        self.push_value(
+            InstructionKind::Phi {
+                incoming: Vec::new(),
+                flags,
+            },
+            ty,
+            name,
+        )
+    }
+
+    pub fn add_incoming(&mut self, phi: Value, incoming: &[(Value, BasicBlockId)]) {
+        for (value, block) in incoming {
+            self.check_local(*value);
+            assert_eq!(value.ty, phi.ty, "incoming value type mismatch");
+            assert!(self.is_block(*block), "invalid block");
+        }
+
+        let id = phi.instruction().expect("not a phi");
+        match &mut self
+            .module
+            .function_mut(self.function)
+            .instruction_mut(id)
+            .kind
+        {
+            InstructionKind::Phi { incoming: x, .. } => x.extend_from_slice(incoming),
+            _ => panic!("not a phi"),
+        }
+    }
+
+    fn check_sized(&self, ty: TypeId) {
+        assert!(
+            self.module.types.is_sized(ty),
+            "{:?} has no size",
+            self.ty(ty)
+        );
+    }
+
+    pub fn alloca(&mut self, ty: TypeId, name: &str) -> Value {
+        let i32 = self.module.types.i32();
+        let one = self.module.const_int(i32, 1);
+        self.array_alloca(ty, one, name)
+    }
+
+    /// Allocates `size` elements of the type.
+    pub fn array_alloca(&mut self, ty: TypeId, size: Value, name: &str) -> Value {
+        self.check_sized(ty);
+        self.check_local(size);
+        assert!(
+            self.ty(size.ty).is_integer(),
+            "alloca size must be an integer"
+        );
+
+        let ptr = self.module.types.ptr();
+        self.push_value(
+            InstructionKind::Alloca {
+                allocated_type: ty,
+                size,
+                align: None,
+            },
+            ptr,
+            name,
+        )
+    }
+
+    pub fn load(&mut self, ty: TypeId, ptr: Value, name: &str) -> Value {
+        self.check_local(ptr);
+        self.check_sized(ty);
+        assert!(self.ty(ptr.ty).is_pointer(), "load needs a pointer");
+
+        self.push_value(
+            InstructionKind::Load {
+                ptr,
+                align: None,
+                volatile: false,
+                atomic: None,
+            },
+            ty,
+            name,
+        )
+    }
+
+    pub fn store(&mut self, value: Value, ptr: Value) -> InstId {
+        self.check_local(value);
+        self.check_local(ptr);
+        self.check_sized(value.ty);
+        assert!(self.ty(ptr.ty).is_pointer(), "store needs a pointer");
+
+        self.push(
+            InstructionKind::Store {
+                ptr,
+                value,
+                align: None,
+                volatile: false,
+                atomic: None,
+            },
+            None,
+            "",
+        )
+    }
+
+    /// Sets the alignment of an alloca or a memory access.
+    pub fn set_alignment(&mut self, inst: InstId, alignment: u64) {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of 2"
+        );
+
+        match &mut self
+            .module
+            .function_mut(self.function)
+            .instruction_mut(inst)
+            .kind
+        {
+            InstructionKind::Alloca { align, .. }
+            | InstructionKind::Load { align, .. }
+            | InstructionKind::Store { align, .. }
+            | InstructionKind::CmpXchg { align, .. }
+            | InstructionKind::AtomicRmw { align, .. } => *align = Some(alignment),
+            _ => panic!("only alloca and memory accesses have an alignment"),
+        }
+    }
+
+    /// Marks a memory access as volatile.
+    pub fn set_volatile(&mut self, inst: InstId, value: bool) {
+        match &mut self
+            .module
+            .function_mut(self.function)
+            .instruction_mut(inst)
+            .kind
+        {
+            InstructionKind::Load { volatile, .. }
+            | InstructionKind::Store { volatile, .. }
+            | InstructionKind::CmpXchg { volatile, .. }
+            | InstructionKind::AtomicRmw { volatile, .. } => *volatile = value,
+            _ => panic!("only memory accesses can be volatile"),
+        }
+    }
+
+    fn check_sync_scope(&self, scope: SyncScope) {
+        assert!(
+            (scope.0 as usize) < self.module.sync_scopes.len(),
+            "sync scope doesn't belong to the module"
+        );
+    }
+
+    // Atomic loads and stores need an explicit alignment, unlike cmpxchg and atomicrmw
+    // which default to the size of the type.
+    fn check_atomic_type(&self, ty: TypeId, what: &str) {
+        let valid = match self.ty(ty) {
+            Type::Integer(bits) => *bits >= 8 && bits.is_power_of_two(),
+            Type::Pointer { .. } => true,
+            ty => ty.is_floating_point(),
+        };
+        assert!(
+            valid,
+            "{what} needs an integer, floating point or pointer type"
+        );
+    }
+
+    // https://llvm.org/docs/LangRef.html#load-instruction
+    pub fn atomic_load(
+        &mut self,
+        ty: TypeId,
+        ptr: Value,
+        ordering: AtomicOrderingCode,
+        sync_scope: SyncScope,
+        align: u64,
+        name: &str,
+    ) -> Value {
+        assert!(
+            !matches!(
+                ordering,
+                AtomicOrderingCode::ORDERING_NOTATOMIC
+                    | AtomicOrderingCode::ORDERING_RELEASE
+                    | AtomicOrderingCode::ORDERING_ACQREL
+            ),
+            "invalid ordering for an atomic load"
+        );
+        self.check_atomic_type(ty, "atomic load");
+
+        let value = self.load(ty, ptr, name);
+        let inst = value.instruction().unwrap();
+        self.set_alignment(inst, align);
+        self.set_atomic(inst, ordering, sync_scope);
+        value
+    }
+
+    // https://llvm.org/docs/LangRef.html#store-instruction
+    pub fn atomic_store(
+        &mut self,
+        value: Value,
+        ptr: Value,
+        ordering: AtomicOrderingCode,
+        sync_scope: SyncScope,
+        align: u64,
+    ) -> InstId {
+        assert!(
+            !matches!(
+                ordering,
+                AtomicOrderingCode::ORDERING_NOTATOMIC
+                    | AtomicOrderingCode::ORDERING_ACQUIRE
+                    | AtomicOrderingCode::ORDERING_ACQREL
+            ),
+            "invalid ordering for an atomic store"
+        );
+        self.check_atomic_type(value.ty, "atomic store");
+
+        let inst = self.store(value, ptr);
+        self.set_alignment(inst, align);
+        self.set_atomic(inst, ordering, sync_scope);
+        inst
+    }
+
+    fn set_atomic(&mut self, inst: InstId, ordering: AtomicOrderingCode, sync_scope: SyncScope) {
+        self.check_sync_scope(sync_scope);
+
+        match &mut self
+            .module
+            .function_mut(self.function)
+            .instruction_mut(inst)
+            .kind
+        {
+            InstructionKind::Load { atomic, .. } | InstructionKind::Store { atomic, .. } => {
+                *atomic = Some(Atomic {
+                    ordering,
+                    sync_scope,
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // https://llvm.org/docs/LangRef.html#fence-instruction
+    pub fn fence(&mut self, ordering: AtomicOrderingCode, sync_scope: SyncScope) -> InstId {
+        assert!(
+            matches!(
+                ordering,
+                AtomicOrderingCode::ORDERING_ACQUIRE
+                    | AtomicOrderingCode::ORDERING_RELEASE
+                    | AtomicOrderingCode::ORDERING_ACQREL
+                    | AtomicOrderingCode::ORDERING_SEQCST
+            ),
+            "fences must be acquire, release, acq_rel or seq_cst"
+        );
+        self.check_sync_scope(sync_scope);
+
+        self.push(
+            InstructionKind::Fence {
+                ordering,
+                sync_scope,
+            },
+            None,
+            "",
+        )
+    }
+
+    /// Returns the `{ ty, i1 }` pair of the loaded value and whether it was replaced,
+    /// mark it weak with [`Builder::set_weak`].
+    // https://llvm.org/docs/LangRef.html#cmpxchg-instruction
+    #[allow(clippy::too_many_arguments)]
+    pub fn cmpxchg(
+        &mut self,
+        ptr: Value,
+        cmp: Value,
+        new: Value,
+        success_ordering: AtomicOrderingCode,
+        failure_ordering: AtomicOrderingCode,
+        sync_scope: SyncScope,
+        name: &str,
+    ) -> Value {
+        self.check_local(ptr);
+        self.check_same_type(cmp, new);
+        assert!(self.ty(ptr.ty).is_pointer(), "cmpxchg needs a pointer");
+        assert!(
+            self.ty(cmp.ty).is_integer() || self.ty(cmp.ty).is_pointer(),
+            "cmpxchg needs integer or pointer operands"
+        );
+        assert!(
+            !matches!(
+                success_ordering,
+                AtomicOrderingCode::ORDERING_NOTATOMIC | AtomicOrderingCode::ORDERING_UNORDERED
+            ),
+            "cmpxchg success ordering must be at least monotonic"
+        );
+        assert!(
+            !matches!(
+                failure_ordering,
+                AtomicOrderingCode::ORDERING_NOTATOMIC
+                    | AtomicOrderingCode::ORDERING_UNORDERED
+                    | AtomicOrderingCode::ORDERING_RELEASE
+                    | AtomicOrderingCode::ORDERING_ACQREL
+            ),
+            "invalid cmpxchg failure ordering"
+        );
+        self.check_sync_scope(sync_scope);
+
+        let i1 = self.module.types.i1();
+        let ty = self.module.types.structure(&[cmp.ty, i1], false);
+        self.push_value(
+            InstructionKind::CmpXchg {
+                ptr,
+                cmp,
+                new,
+                align: None,
+                volatile: false,
+                weak: false,
+                success_ordering,
+                failure_ordering,
+                sync_scope,
+            },
+            ty,
+            name,
+        )
+    }
+
+    /// Allows a cmpxchg to fail spuriously.
+    pub fn set_weak(&mut self, inst: InstId, value: bool) {
+        match &mut self
+            .module
+            .function_mut(self.function)
+            .instruction_mut(inst)
+            .kind
+        {
+            InstructionKind::CmpXchg { weak, .. } => *weak = value,
+            _ => panic!("only cmpxchg can be weak"),
+        }
+    }
+
+    // https://llvm.org/docs/LangRef.html#atomicrmw-instruction
+    pub fn atomicrmw(
+        &mut self,
+        op: RMWOperation,
+        ptr: Value,
+        value: Value,
+        ordering: AtomicOrderingCode,
+        sync_scope: SyncScope,
+        name: &str,
+    ) -> Value {
+        self.check_local(ptr);
+        self.check_local(value);
+        assert!(self.ty(ptr.ty).is_pointer(), "atomicrmw needs a pointer");
+        assert!(
+            !matches!(
+                ordering,
+                AtomicOrderingCode::ORDERING_NOTATOMIC | AtomicOrderingCode::ORDERING_UNORDERED
+            ),
+            "atomicrmw ordering must be at least monotonic"
+        );
+        self.check_sync_scope(sync_scope);
+
+        let ty = self.ty(value.ty);
+        let valid = match op {
+            RMWOperation::XCHG => ty.is_integer() || ty.is_floating_point() || ty.is_pointer(),
+            RMWOperation::FADD | RMWOperation::FSUB | RMWOperation::FMAX | RMWOperation::FMIN => {
+                self.scalar(value.ty).is_floating_point()
+            }
+            _ => ty.is_integer(),
+        };
+        assert!(valid, "invalid operand type for atomicrmw {op:?}");
+
+        self.push_value(
+            InstructionKind::AtomicRmw {
+                op,
+                ptr,
+                value,
+                align: None,
+                volatile: false,
+                ordering,
+                sync_scope,
+            },
+            value.ty,
+            name,
+        )
+    }
+
+    pub fn gep(
+        &mut self,
+        source_type: TypeId,
+        ptr: Value,
+        indices: &[Value],
+        flags: &[GetElementPtrOptionalFlag],
+        name: &str,
+    ) -> Value {
+        self.check_local(ptr);
+        for index in indices {
+            self.check_local(*index);
+        }
+        let ty = self.module.gep_type(source_type, ptr, indices);
+
+        let flags = flags.iter().fold(0, |acc, x| acc | 1 << *x as u64);
+        self.push_value(
+            InstructionKind::GetElementPtr {
+                source_type,
+                ptr,
+                indices: indices.to_vec(),
+                flags,
+            },
+            ty,
+            name,
+        )
+    }
+
+    pub fn inbounds_gep(
+        &mut self,
+        source_type: TypeId,
+        ptr: Value,
+        indices: &[Value],
+        name: &str,
+    ) -> Value {
+        self.gep(
+            source_type,
+            ptr,
+            indices,
+            &[GetElementPtrOptionalFlag::GEP_INBOUNDS],
+            name,
+        )
+    }
+
+    /// The address of a field of the struct pointed by `ptr`.
+    pub fn struct_gep(&mut self, struct_type: TypeId, ptr: Value, field: u32, name: &str) -> Value {
+        let i32 = self.module.types.i32();
+        let zero = self.module.const_int(i32, 0);
+        let field = self.module.const_int(i32, field as i128);
+        self.inbounds_gep(struct_type, ptr, &[zero, field], name)
+    }
+
+    /// Calls a function of the module, returning `None` for void functions.
+    pub fn call(&mut self, function: FunctionId, args: &[Value], name: &str) -> Option<Value> {
+        let call = CallInst::new(self.module, function, args);
+        self.call_with(call, name)
+    }
+
+    /// Calls through a pointer, `function_type` is the type of the callee.
+    pub fn call_indirect(
+        &mut self,
+        function_type: TypeId,
+        callee: Value,
+        args: &[Value],
+        name: &str,
+    ) -> Option<Value> {
+        self.call_with(CallInst::indirect(function_type, callee, args), name)
+    }
+
+    /// Runs inline asm, returning `None` if it produces no value.
+    pub fn call_asm(&mut self, asm: InlineAsm, args: &[Value], name: &str) -> Option<Value> {
+        let function_type = asm.function_type;
+        let callee = self.module.const_inline_asm(asm);
+        self.call_indirect(function_type, callee, args, name)
+    }
+
+    // https://llvm.org/docs/LangRef.html#operand-bundles
+    fn check_bundles(&self, bundles: &[OperandBundle]) {
+        for (i, bundle) in bundles.iter().enumerate() {
+            assert!(
+                (bundle.tag.0 as usize) < self.module.bundle_tags.len(),
+                "bundle tag doesn't belong to the module"
+            );
+            assert!(
+                bundle.tag.is_custom() || bundles[..i].iter().all(|x| x.tag != bundle.tag),
+                "multiple {} operand bundles",
+                self.module.bundle_tag_name(bundle.tag)
+            );
+            for input in &bundle.inputs {
+                self.check_local(*input);
+            }
+
+            let inputs = &bundle.inputs;
+            match bundle.tag {
+                BundleTag::FUNCLET | BundleTag::CFGUARDTARGET | BundleTag::CONVERGENCECTRL => {
+                    assert_eq!(inputs.len(), 1, "expected exactly one bundle input")
+                }
+                BundleTag::KCFI => assert!(
+                    inputs.len() == 1
+                        && self.ty(inputs[0].ty) == &Type::Integer(32)
+                        && matches!(inputs[0].kind, ValueRef::Constant(_)),
+                    "kcfi bundles take an i32 constant"
+                ),
+                BundleTag::PTRAUTH => assert!(
+                    inputs.len() == 2
+                        && self.ty(inputs[0].ty) == &Type::Integer(32)
+                        && self.ty(inputs[1].ty) == &Type::Integer(64),
+                    "ptrauth bundles take an i32 key and an i64 discriminator"
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    // Checks the callee and arguments, returns the type of the result.
+    fn check_call(&self, call: &CallInst) -> Option<TypeId> {
+        let Type::Function(fn_ty) = self.ty(call.function_type) else {
+            panic!("calls need a function type");
+        };
+
+        self.check_local(call.callee);
+        assert!(
+            self.ty(call.callee.ty).is_pointer(),
+            "callee must be a pointer"
+        );
+        if let ValueRef::Constant(id) = call.callee.kind {
+            if let ConstantKind::InlineAsm(asm) = &self.module.constants().get(id).kind {
+                assert_eq!(
+                    asm.function_type, call.function_type,
+                    "inline asm called with a different type"
+                );
+            }
+        }
+
+        if fn_ty.vararg {
+            assert!(call.args.len() >= fn_ty.params.len(), "too few arguments");
+        } else {
+            assert_eq!(
+                call.args.len(),
+                fn_ty.params.len(),
+                "wrong number of arguments"
+            );
+        }
+        for (arg, param) in call.args.iter().zip(&fn_ty.params) {
+            assert_eq!(arg.ty, *param, "argument type mismatch");
+        }
+        for arg in &call.args {
+            self.check_local(*arg);
+        }
+        self.check_bundles(&call.bundles);
+
+        let ret = fn_ty.ret;
+        (self.ty(ret) != &Type::Void).then_some(ret)
+    }
+
+    /// A call with all of its properties, fast math flags are taken from the builder.
+    pub fn call_with(&mut self, mut call: CallInst, name: &str) -> Option<Value> {
+        let ty = self.check_call(&call);
+        if ty.is_some_and(|x| self.scalar(x).is_floating_point()) {
+            call.flags |= self.fast_math;
+        }
+
+        let id = self.push(InstructionKind::Call(Box::new(call)), ty, name);
+        ty.map(|ty| Value::local(ValueRef::Instruction(id), ty, self.function))
+    }
+
+    // https://llvm.org/docs/LangRef.html#invoke-instruction
+    pub fn invoke(
+        &mut self,
+        function: FunctionId,
+        args: &[Value],
+        normal_dest: BasicBlockId,
+        unwind_dest: BasicBlockId,
+        name: &str,
+    ) -> Option<Value> {
+        let call = CallInst::new(self.module, function, args);
+        self.invoke_with(call, normal_dest, unwind_dest, name)
+    }
+
+    /// An invoke with all of its properties, calls can't be tail calls or have
+    /// fast math flags.
+    pub fn invoke_with(
+        &mut self,
+        call: CallInst,
+        normal_dest: BasicBlockId,
+        unwind_dest: BasicBlockId,
+        name: &str,
+    ) -> Option<Value> {
+        let ty = self.check_call(&call);
+        assert!(
+            call.tail == TailCallKind::None && call.flags == 0,
+            "invokes can't be tail calls or have fast math flags"
+        );
+        assert!(
+            self.is_block(normal_dest) && self.is_block(unwind_dest),
+            "invalid block"
+        );
+
+        let kind = InstructionKind::Invoke {
+            call: Box::new(call),
+            normal_dest,
+            unwind_dest,
+        };
+        let id = self.push(kind, ty, name);
+        ty.map(|ty| Value::local(ValueRef::Instruction(id), ty, self.function))
+    }
+
+    fn check_personality(&self, what: &str) {
+        assert!(
+            self.module.function(self.function).personality.is_some(),
+            "{what} needs the function to have a personality"
+        );
+    }
+
+    // Pads must be the first instruction of their block after the phis.
+    fn check_first_non_phi(&self, what: &str) {
+        let block = self
+            .block
+            .expect("the builder is not positioned at a block");
+        let function = self.module.function(self.function);
+        assert!(
+            function
+                .block(block)
+                .instructions()
+                .iter()
+                .all(|x| matches!(function.instruction(*x).kind, InstructionKind::Phi { .. })),
+            "{what} must be the first non-phi instruction of its block"
+        );
+    }
+
+    fn instruction_kind(&self, value: Value) -> Option<&InstructionKind> {
+        self.check_local(value);
+        let id = value.instruction()?;
+        Some(&self.module.function(self.function).instruction(id).kind)
+    }
+
+    // The parent of a funclet pad is another pad or `none`.
+    fn parent_pad(&mut self, parent_pad: Option<Value>) -> Value {
+        match parent_pad {
+            Some(pad) => {
+                assert!(
+                    matches!(
+                        self.instruction_kind(pad),
+                        Some(InstructionKind::CatchPad { .. } | InstructionKind::CleanupPad { .. })
+                    ),
+                    "parent pad must be a catchpad or cleanuppad"
+                );
+                pad
+            }
+            None => self.module.const_none(),
+        }
+    }
+
+    /// A landingpad, of a struct type like `{ ptr, i32 }` for the exception and
+    /// its selector.
+    // https://llvm.org/docs/LangRef.html#landingpad-instruction
+    pub fn landingpad(
+        &mut self,
+        ty: TypeId,
+        cleanup: bool,
+        clauses: &[LandingPadClause],
+        name: &str,
+    ) -> Value {
+        self.check_personality("landingpad");
+        self.check_first_non_phi("landingpad");
+        assert!(
+            cleanup || !clauses.is_empty(),
+            "landingpads without clauses must be cleanups"
+        );
+        for clause in clauses {
+            let value = clause.value();
+            assert!(
+                !value.kind.is_local(),
+                "landingpad clauses must be constants"
+            );
+            match clause {
+                LandingPadClause::Catch(_) => {
+                    assert!(
+                        self.ty(value.ty).is_pointer(),
+                        "catch clauses take a pointer"
+                    )
+                }
+                LandingPadClause::Filter(_) => assert!(
+                    matches!(self.ty(value.ty), Type::Array { .. }),
+                    "filter clauses take an array"
+                ),
+            }
+        }
+
+        let kind = InstructionKind::LandingPad {
+            cleanup,
+            clauses: clauses.to_vec(),
+        };
+        self.push_value(kind, ty, name)
+    }
+
+    // https://llvm.org/docs/LangRef.html#resume-instruction
+    pub fn resume(&mut self, value: Value) -> InstId {
+        self.check_personality("resume");
+        self.check_local(value);
+        self.push(InstructionKind::Resume(value), None, "")
+    }
+
+    /// The dispatch of a Windows style exception to the catchpads of `handlers`,
+    /// unwinding to the caller if there's no `unwind_dest`.
+    // https://llvm.org/docs/LangRef.html#catchswitch-instruction
+    pub fn catchswitch(
+        &mut self,
+        parent_pad: Option<Value>,
+        handlers: &[BasicBlockId],
+        unwind_dest: Option<BasicBlockId>,
+        name: &str,
+    ) -> Value {
+        self.check_personality("catchswitch");
+        self.check_first_non_phi("catchswitch");
+        assert!(
+            !handlers.is_empty(),
+            "catchswitch needs at least one handler"
+        );
+        assert!(
+            handlers
+                .iter()
+                .chain(&unwind_dest)
+                .all(|x| self.is_block(*x)),
+            "invalid block"
+        );
+
+        let parent_pad = self.parent_pad(parent_pad);
+        let token = self.module.types.token();
+        let kind = InstructionKind::CatchSwitch {
+            parent_pad,
+            handlers: handlers.to_vec(),
+            unwind_dest,
+        };
+        self.push_value(kind, token, name)
+    }
+
+    // https://llvm.org/docs/LangRef.html#catchpad-instruction
+    pub fn catchpad(&mut self, catch_switch: Value, args: &[Value], name: &str) -> Value {
+        self.check_first_non_phi("catchpad");
+        assert!(
+            matches!(
+                self.instruction_kind(catch_switch),
+                Some(InstructionKind::CatchSwitch { .. })
+            ),
+            "catchpad needs a catchswitch"
+        );
+        for arg in args {
+            self.check_local(*arg);
+        }
+
+        let token = self.module.types.token();
+        let kind = InstructionKind::CatchPad {
+            catch_switch,
+            args: args.to_vec(),
+        };
+        self.push_value(kind, token, name)
+    }
+
+    // https://llvm.org/docs/LangRef.html#cleanuppad-instruction
+    pub fn cleanuppad(&mut self, parent_pad: Option<Value>, args: &[Value], name: &str) -> Value {
+        self.check_personality("cleanuppad");
+        self.check_first_non_phi("cleanuppad");
+        for arg in args {
+            self.check_local(*arg);
+        }
+
+        let parent_pad = self.parent_pad(parent_pad);
+        let token = self.module.types.token();
+        let kind = InstructionKind::CleanupPad {
+            parent_pad,
+            args: args.to_vec(),
+        };
+        self.push_value(kind, token, name)
+    }
+
+    // https://llvm.org/docs/LangRef.html#catchret-instruction
+    pub fn catchret(&mut self, catch_pad: Value, dest: BasicBlockId) -> InstId {
+        assert!(
+            matches!(
+                self.instruction_kind(catch_pad),
+                Some(InstructionKind::CatchPad { .. })
+            ),
+            "catchret needs a catchpad"
+        );
+        assert!(self.is_block(dest), "invalid block");
+        self.push(InstructionKind::CatchRet { catch_pad, dest }, None, "")
+    }
+
+    /// Leaves the cleanup, unwinding to the caller if there's no `unwind_dest`.
+    // https://llvm.org/docs/LangRef.html#cleanupret-instruction
+    pub fn cleanupret(&mut self, cleanup_pad: Value, unwind_dest: Option<BasicBlockId>) -> InstId {
+        assert!(
+            matches!(
+                self.instruction_kind(cleanup_pad),
+                Some(InstructionKind::CleanupPad { .. })
+            ),
+            "cleanupret needs a cleanuppad"
+        );
+        assert!(
+            unwind_dest.iter().all(|x| self.is_block(*x)),
+            "invalid block"
+        );
+        let kind = InstructionKind::CleanupRet {
+            cleanup_pad,
+            unwind_dest,
+        };
+        self.push(kind, None, "")
+    }
+
+    pub fn va_arg(&mut self, list: Value, ty: TypeId, name: &str) -> Value {
+        self.check_local(list);
+        assert!(
+            self.ty(list.ty).is_pointer(),
+            "va_arg needs a pointer to a va_list"
+        );
+
+        self.push_value(InstructionKind::VaArg { list }, ty, name)
+    }
+
+    pub fn freeze(&mut self, value: Value, name: &str) -> Value {
+        self.check_local(value);
+        self.push_value(InstructionKind::Freeze(value), value.ty, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::{
+            AtomicOrderingCode as O, BinaryOpCode, BlockId, ConstantsCode, FunctionCide,
+            ModuleCode, RMWOperation, TypeCode,
+        },
+        bitstream::reader::{read, Block},
+        ir::{
+            constants::{AsmDialect, InlineAsm},
+            instructions::{
+                BundleTag, CallInst, FloatPredicate, IntPredicate, LandingPadClause, OperandBundle,
+                SyncScope,
+            },
+            module::Module,
+        },
+    };
+
+    use super::Builder;
+
+    fn function_block(bitcode: &Block) -> &Block {
+        let module = bitcode.block(BlockId::Module as u32);
+        module.block(BlockId::Function as u32)
+    }
+
+    #[test]
+    fn build_loop() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32], false);
+        let f = module.add_function("count", fn_ty);
+        let n = module.function(f).arg(0);
+        let zero = module.const_int(i32, 0);
+        let one = module.const_int(i32, 1);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        let body = builder.append_block("loop");
+        let exit = builder.append_block("exit");
+
+        builder.position_at_end(entry);
+        builder.br(body);
+
+        builder.position_at_end(body);
+        let i = builder.phi(i32, "i");
+        let next = builder.add(i, one, "next");
+        builder.add_incoming(i, &[(zero, entry), (next, body)]);
+        let done = builder.icmp(IntPredicate::Eq, next, n, "done");
+        builder.cond_br(done, exit, body);
+
+        builder.position_at_end(exit);
+        builder.ret(next);
+
+        // The constants are the values 2 and 3, %i 4, %next 5 and %done 6.
+        let bitcode = read(&module.to_bitcode());
+        let body = function_block(&bitcode);
+        // [ty, n x [value, bb]], the values are signed as they can be
+        // forward references.
+        let phi = body.record(FunctionCide::INST_PHI as u32);
+        assert_eq!(phi.ops, [0, 4, 0, 3, 1]);
+        let binop = body.record(FunctionCide::INST_BINOP as u32);
+        assert_eq!(binop.ops, [1, 2, BinaryOpCode::Add as u64]);
+        let cmp = body.record(FunctionCide::INST_CMP2 as u32);
+        assert_eq!(cmp.ops, [1, 5, IntPredicate::Eq as u64]);
+        // [true bb, false bb, cond]
+        let branches = body.ops(FunctionCide::INST_BR as u32);
+        assert_eq!(branches, [vec![1], vec![2, 1, 1]]);
+    }
+
+    #[test]
+    fn build_atomics() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let float = module.types.float();
+        let ptr = module.types.ptr();
+        let void = module.types.void();
+        let fn_ty = module.types.function(void, &[ptr, i32, float], false);
+        let f = module.add_function("f", fn_ty);
+        let (p, a, x) = {
+            let func = module.function(f);
+            (func.arg(0), func.arg(1), func.arg(2))
+        };
+
+        let agent = module.sync_scope("agent");
+        assert_eq!(module.sync_scope("agent"), agent);
+        assert_eq!(module.sync_scope(""), SyncScope::SYSTEM);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+
+        let l = builder.atomic_load(i32, p, O::ORDERING_ACQUIRE, SyncScope::SYSTEM, 4, "l");
+        builder.atomic_store(l, p, O::ORDERING_RELEASE, SyncScope::SINGLE_THREAD, 4);
+        builder.fence(O::ORDERING_SEQCST, agent);
+        let c = builder.cmpxchg(
+            p,
+            l,
+            a,
+            O::ORDERING_ACQREL,
+            O::ORDERING_MONOTONIC,
+            SyncScope::SYSTEM,
+            "c",
+        );
+        builder.set_weak(c.instruction().unwrap(), true);
+        builder.atomicrmw(
+            RMWOperation::UincWrap,
+            p,
+            a,
+            O::ORDERING_SEQCST,
+            SyncScope::SYSTEM,
+            "",
+        );
+        builder.atomicrmw(
+            RMWOperation::FMAX,
+            p,
+            x,
+            O::ORDERING_MONOTONIC,
+            SyncScope::SYSTEM,
+            "",
+        );
+        builder.ret_void();
+
+        // The arguments are the values 1 to 3 and the instructions start at 4.
+        let bitcode = read(&module.to_bitcode());
+        let scopes = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::SyncScopeNames as u32);
+        let names: Vec<_> = scopes.records.iter().map(|x| x.string(0)).collect();
+        assert_eq!(names, ["singlethread", "", "agent"]);
+
+        let body = function_block(&bitcode);
+        // [ptr, ty, align, vol, ordering, ssid]
+        let load = body.record(FunctionCide::INST_LOADATOMIC as u32);
+        assert_eq!(load.ops, [3, 0, 3, 0, O::ORDERING_ACQUIRE as u64, 1]);
+        // [ptr, val, align, vol, ordering, ssid]
+        let store = body.record(FunctionCide::INST_STOREATOMIC as u32);
+        assert_eq!(store.ops, [4, 1, 3, 0, O::ORDERING_RELEASE as u64, 0]);
+        let fence = body.record(FunctionCide::INST_FENCE as u32);
+        assert_eq!(fence.ops, [O::ORDERING_SEQCST as u64, 2]);
+        // [ptr, cmp, new, vol, success ordering, ssid, failure ordering,
+        //  weak, align]
+        let cmpxchg = body.record(FunctionCide::INST_CMPXCHG as u32);
+        let (success, failure) = (O::ORDERING_ACQREL as u64, O::ORDERING_MONOTONIC as u64);
+        assert_eq!(cmpxchg.ops, [4, 1, 3, 0, success, 1, failure, 1, 0]);
+        // [ptr, val, operation, vol, ordering, ssid, align]
+        let rmws = body.ops(FunctionCide::INST_ATOMICRMW as u32);
+        let (uinc_wrap, fmax) = (RMWOperation::UincWrap as u64, RMWOperation::FMAX as u64);
+        assert_eq!(
+            rmws,
+            [
+                [5, 4, uinc_wrap, 0, O::ORDERING_SEQCST as u64, 1, 0],
+                [6, 4, fmax, 0, O::ORDERING_MONOTONIC as u64, 1, 0],
+            ]
+        );
+    }
+
+    #[test]
+    fn build_operand_bundles() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let ptr = module.types.ptr();
+        let void = module.types.void();
+        let callee_ty = module.types.function(void, &[], false);
+        let fn_ty = module.types.function(void, &[ptr, i32], false);
+        let g = module.add_function("g", callee_ty);
+        let f = module.add_function("f", fn_ty);
+        let (p, a) = (module.function(f).arg(0), module.function(f).arg(1));
+        let custom = module.bundle_tag("custom");
+        let type_id = module.const_int(i32, 1234);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+
+        let mut call = CallInst::new(builder.module(), g, &[]);
+        call.bundles = vec![
+            OperandBundle {
+                tag: BundleTag::DEOPT,
+                inputs: vec![a, p],
+            },
+            OperandBundle {
+                tag: custom,
+                inputs: vec![a],
+            },
+        ];
+        builder.call_with(call, "");
+
+        let mut call = CallInst::indirect(callee_ty, p, &[]);
+        call.bundles = vec![OperandBundle {
+            tag: BundleTag::KCFI,
+            inputs: vec![type_id],
+        }];
+        builder.call_with(call, "");
+        builder.ret_void();
+
+        let bitcode = read(&module.to_bitcode());
+        let tags = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::OperandBundleTags as u32);
+        let tag = tags.records.last().unwrap();
+        assert_eq!((tags.records.len(), tag.string(0)), (11, "custom".into()));
+
+        // Each call is preceded by its bundles as [tag, n x value], with %p
+        // and %a the values 2 and 3 and the constant the value 4.
+        let body = function_block(&bitcode);
+        assert_eq!(
+            body.codes(),
+            [
+                FunctionCide::DECLAREBLOCKS as u32,
+                FunctionCide::OPERAND_BUNDLE as u32,
+                FunctionCide::OPERAND_BUNDLE as u32,
+                FunctionCide::INST_CALL as u32,
+                FunctionCide::OPERAND_BUNDLE as u32,
+                FunctionCide::INST_CALL as u32,
+                FunctionCide::INST_RET as u32,
+            ]
+        );
+        let bundles = body.ops(FunctionCide::OPERAND_BUNDLE as u32);
+        assert_eq!(bundles, [vec![0, 2, 3], vec![10, 2], vec![8, 1]]);
+        // [attrs, cc, fnty, callee]
+        let calls = body.ops(FunctionCide::INST_CALL as u32);
+        assert_eq!(calls, [[0, 1 << 15, 3, 5], [0, 1 << 15, 3, 3]]);
+    }
+
+    #[test]
+    fn build_exception_handling() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let ptr = module.types.ptr();
+        let void = module.types.void();
+        let personality_ty = module.types.function(i32, &[], true);
+        let fn_ty = module.types.function(void, &[], false);
+        let typeinfo = module.add_global("typeinfo", ptr);
+        let typeinfo = module.global_value(typeinfo);
+        let gxx = module.add_function("__gxx_personality_v0", personality_ty);
+        let cxx = module.add_function("__CxxFrameHandler3", personality_ty);
+        let g = module.add_function("g", fn_ty);
+        let itanium = module.add_function("itanium", fn_ty);
+        let windows = module.add_function("windows", fn_ty);
+        module.function_mut(itanium).personality = Some(module.function_value(gxx));
+        module.function_mut(windows).personality = Some(module.function_value(cxx));
+
+        let filter_ty = module.types.array(ptr, 1);
+        let filter = module.const_aggregate(filter_ty, &[typeinfo]);
+        let lp_ty = module.types.structure(&[ptr, i32], false);
+
+        let mut builder = Builder::new(&mut module, itanium);
+        let entry = builder.append_block("entry");
+        let cont = builder.append_block("cont");
+        let lpad = builder.append_block("lpad");
+        builder.position_at_end(entry);
+        builder.invoke(g, &[], cont, lpad, "");
+        builder.position_at_end(cont);
+        builder.ret_void();
+        builder.position_at_end(lpad);
+        let clauses = [
+            LandingPadClause::Catch(typeinfo),
+            LandingPadClause::Filter(filter),
+        ];
+        let lp = builder.landingpad(lp_ty, true, &clauses, "lp");
+        builder.resume(lp);
+
+        let null = module.const_null(ptr);
+        let flags = module.const_int(i32, 64);
+        let mut builder = Builder::new(&mut module, windows);
+        let entry = builder.append_block("entry");
+        let cont = builder.append_block("cont");
+        let dispatch = builder.append_block("dispatch");
+        let handler = builder.append_block("handler");
+        let cleanup = builder.append_block("cleanup");
+        builder.position_at_end(entry);
+        builder.invoke(g, &[], cont, dispatch, "");
+        builder.position_at_end(cont);
+        builder.ret_void();
+        builder.position_at_end(dispatch);
+        let cs = builder.catchswitch(None, &[handler], Some(cleanup), "cs");
+        builder.position_at_end(handler);
+        let cp = builder.catchpad(cs, &[null, flags, null], "cp");
+        let mut call = CallInst::new(builder.module(), g, &[]);
+        call.bundles = vec![OperandBundle {
+            tag: BundleTag::FUNCLET,
+            inputs: vec![cp],
+        }];
+        builder.call_with(call, "");
+        builder.catchret(cp, cont);
+        builder.position_at_end(cleanup);
+        let cl = builder.cleanuppad(None, &[], "cl");
+        builder.cleanupret(cl, None);
+
+        // typeinfo and the functions are the values 0 to 5, the instructions
+        // of itanium start at 7 and the ones of windows at 9.
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let personalities: Vec<_> = module_block
+            .records(ModuleCode::Function as u32)
+            .iter()
+            .map(|x| x.ops[16])
+            .collect();
+        assert_eq!(personalities, [0, 0, 0, 2, 3]);
+        let mut bodies = module_block.blocks(BlockId::Function as u32);
+
+        let itanium = bodies.next().unwrap();
+        // [attrs, cc, normal bb, unwind bb, fnty, callee]
+        let invoke = itanium.record(FunctionCide::INST_INVOKE as u32);
+        assert_eq!(invoke.ops, [0, 1 << 13, 1, 2, 4, 4]);
+        // [ty, cleanup, n, n x [catch or filter, value]]
+        let landingpad = itanium.record(FunctionCide::INST_LANDINGPAD as u32);
+        assert_eq!(landingpad.ops, [6, 1, 2, 0, 7, 1, 1]);
+        assert_eq!(itanium.record(FunctionCide::INST_RESUME as u32).ops, [1]);
+
+        // none is the value 6, %cs 9, %cp 10 and %cl 11.
+        let windows = bodies.next().unwrap();
+        // [parent pad, n, n x handler, unwind bb]
+        let catchswitch = windows.record(FunctionCide::INST_CATCHSWITCH as u32);
+        assert_eq!(catchswitch.ops, [3, 1, 3, 4]);
+        // [catchswitch, n, n x arg]
+        let catchpad = windows.record(FunctionCide::INST_CATCHPAD as u32);
+        assert_eq!(catchpad.ops, [1, 3, 3, 2, 3]);
+        let bundle = windows.record(FunctionCide::OPERAND_BUNDLE as u32);
+        assert_eq!(bundle.ops, [BundleTag::FUNCLET.0 as u64, 1]);
+        let catchret = windows.record(FunctionCide::INST_CATCHRET as u32);
+        assert_eq!(catchret.ops, [1, 1]);
+        let cleanuppad = windows.record(FunctionCide::INST_CLEANUPPAD as u32);
+        assert_eq!(cleanuppad.ops, [5, 0]);
+        // The cleanup unwinds to the caller.
+        let cleanupret = windows.record(FunctionCide::INST_CLEANUPRET as u32);
+        assert_eq!(cleanupret.ops, [1]);
+    }
+
+    #[test]
+    fn build_vectors() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let i64 = module.types.i64();
+        let float = module.types.float();
+        let ptr = module.types.ptr();
+        let v4i32 = module.types.vector(i32, 4);
+        let nxv4f32 = module.types.scalable_vector(float, 4);
+        let v2ptr = module.types.vector(ptr, 2);
+        let fn_ty = module
+            .types
+            .function(v4i32, &[v4i32, nxv4f32, v2ptr, i32], false);
+        let f = module.add_function("f", fn_ty);
+        let args: Vec<_> = module.function(f).args().collect();
+        let (v, s, ps, x) = (args[0], args[1], args[2], args[3]);
+        let zero = module.const_int(i64, 0);
+        let one = module.const_int(i64, 1);
+        let two = module.const_float(float, 2.0);
+        let poison = module.const_poison(nxv4f32);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+
+        let e = builder.extractelement(v, one, "e");
+        let i = builder.insertelement(v, x, zero, "i");
+        let sh = builder.shufflevector(v, i, &[Some(0), Some(5), None, Some(7)], "sh");
+        let ins = builder.insertelement(poison, two, zero, "ins");
+        let splat = builder.shufflevector(ins, poison, &[Some(0); 4], "splat");
+        builder.fcmp(FloatPredicate::Olt, s, splat, "lt");
+        let addrs = builder.gep(i32, ps, &[one], &[], "addrs");
+        builder.icmp(IntPredicate::Eq, addrs, ps, "same");
+        let cmp = builder.icmp(IntPredicate::Sgt, v, sh, "cmp");
+        let max = builder.select(cmp, v, sh, "max");
+        let r = builder.insertelement(max, e, one, "r");
+        builder.ret(r);
+
+        let bitcode = read(&module.to_bitcode());
+        let types = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Type as u32);
+        // [n, element type, scalable]
+        let vectors = types.ops(TypeCode::Vector as u32);
+        assert!(vectors.contains(&vec![4, 2, 1]));
+
+        // The constants are the values 5 to 14 and the instructions start at
+        // 15, the first mask is the constant 11 and the splat one 14.
+        let body = function_block(&bitcode);
+        let constants = body.block(BlockId::Constants as u32);
+        let mask = constants.record(ConstantsCode::Aggregate as u32);
+        assert_eq!(mask.ops, [7, 8, 9, 10]);
+        let extract = body.record(FunctionCide::INST_EXTRACTELT as u32);
+        assert_eq!(extract.ops, [14, 10]);
+        // [vec, elt, idx]
+        let inserts = body.ops(FunctionCide::INST_INSERTELT as u32);
+        assert_eq!(inserts, [[15, 12, 10], [6, 5, 12], [1, 10, 20]]);
+        // [vec, vec, mask]
+        let shuffles = body.ops(FunctionCide::INST_SHUFFLEVEC as u32);
+        assert_eq!(shuffles, [[16, 1, 6], [1, 7, 5]]);
+        let cmps = body.ops(FunctionCide::INST_CMP2 as u32);
+        let (olt, eq, sgt) = (
+            FloatPredicate::Olt as u64,
+            IntPredicate::Eq as u64,
+            IntPredicate::Sgt as u64,
+        );
+        assert_eq!(cmps, [[18, 1, olt], [1, 19, eq], [22, 6, sgt]]);
+        // [flags, source type, n x value]
+        let gep = body.record(FunctionCide::INST_GEP as u32);
+        assert_eq!(gep.ops, [0, 0, 18, 16]);
+        // [true value, false value, cond]
+        let select = body.record(FunctionCide::INST_VSELECT as u32);
+        assert_eq!(select.ops, [23, 7, 1]);
+    }
+
+    #[test]
+    fn build_aggregates() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let double = module.types.double();
+        let pair = module.types.array(double, 2);
+        let ret = module.types.structure(&[i32, pair], false);
+        let fn_ty = module.types.function(ret, &[ret, double], false);
+        let f = module.add_function("f", fn_ty);
+        let (a, d) = (module.function(f).arg(0), module.function(f).arg(1));
+        let undef = module.const_undef(ret);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+
+        let n = builder.extractvalue(a, &[0], "n");
+        let x = builder.extractvalue(a, &[1, 1], "x");
+        let r = builder.insertvalue(undef, n, &[0], "");
+        let r = builder.insertvalue(r, d, &[1, 0], "");
+        let r = builder.insertvalue(r, x, &[1, 1], "r");
+        builder.ret(r);
+
+        // %a is the value 1, %d 2, undef 3 and the instructions start at 4.
+        let bitcode = read(&module.to_bitcode());
+        let body = function_block(&bitcode);
+        // [aggregate, n x index]
+        let extracts = body.ops(FunctionCide::INST_EXTRACTVAL as u32);
+        assert_eq!(extracts, [vec![3, 0], vec![4, 1, 1]]);
+        // [aggregate, value, n x index]
+        let inserts = body.ops(FunctionCide::INST_INSERTVAL as u32);
+        assert_eq!(inserts, [vec![3, 2, 0], vec![1, 5, 1, 0], vec![1, 3, 1, 1]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid indices [1, 2]")]
+    fn extractvalue_out_of_range() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let pair = module.types.array(i32, 2);
+        let ty = module.types.structure(&[i32, pair], false);
+        let fn_ty = module.types.function(i32, &[ty], false);
+        let f = module.add_function("f", fn_ty);
+        let a = module.function(f).arg(0);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        builder.extractvalue(a, &[1, 2], "");
+    }
+
+    #[test]
+    fn build_inline_asm() {
+        let mut module = Module::new("test.c");
+        module.append_module_asm(".globl marker");
+        module.append_module_asm("marker:");
+        let i64 = module.types.i64();
+        let void = module.types.void();
+        let syscall_ty = module.types.function(i64, &[i64, i64], false);
+        let nop_ty = module.types.function(void, &[], false);
+        let f = module.add_function("f", syscall_ty);
+        let (n, a) = (module.function(f).arg(0), module.function(f).arg(1));
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+
+        let mut syscall = InlineAsm::new(
+            syscall_ty,
+            "syscall",
+            "={rax},{rax},{rdi},~{rcx},~{r11},~{memory}",
+        );
+        syscall.side_effect = true;
+        let r = builder.call_asm(syscall, &[n, a], "r").unwrap();
+        let mut nop = InlineAsm::new(nop_ty, "nop", "");
+        nop.dialect = AsmDialect::Intel;
+        nop.align_stack = true;
+        builder.call_asm(nop, &[], "");
+        builder.ret(r);
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let asm = module_block.record(ModuleCode::Asm as u32).string(0);
+        assert_eq!(asm, ".globl marker\nmarker:\n");
+
+        // [fnty, flags, n, n x char, m, m x char], the flags being
+        // sideeffect | alignstack << 1 | dialect << 2.
+        let string = |x: &str| x.bytes().map(u64::from).collect::<Vec<_>>();
+        let constraints = "={rax},{rax},{rdi},~{rcx},~{r11},~{memory}";
+        let syscall = [
+            vec![2, 1, 7],
+            string("syscall"),
+            vec![constraints.len() as u64],
+            string(constraints),
+        ];
+        let nop = [vec![3, 6, 3], string("nop"), vec![0]];
+        let body = function_block(&bitcode);
+        let constants = body.block(BlockId::Constants as u32);
+        let asms = constants.ops(ConstantsCode::InlineAsm as u32);
+        assert_eq!(asms, [syscall.concat(), nop.concat()]);
+        // The inline asm are the values 3 and 4 and the calls the values 5
+        // and 6.
+        let calls = body.ops(FunctionCide::INST_CALL as u32);
+        assert_eq!(
+            calls,
+            [vec![0, 1 << 15, 2, 2, 4, 3], vec![0, 1 << 15, 3, 2]]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "multiple deopt operand bundles")]
+    fn duplicate_operand_bundles() {
+        let mut module = Module::new("test.c");
+        let void = module.types.void();
+        let fn_ty = module.types.function(void, &[], false);
+        let f = module.add_function("f", fn_ty);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+
+        let bundle = OperandBundle {
+            tag: BundleTag::DEOPT,
+            inputs: vec![],
+        };
+        let mut call = CallInst::new(builder.module(), f, &[]);
+        call.bundles = vec![bundle.clone(), bundle];
+        builder.call_with(call, "");
+    }
+
+    #[test]
+    #[should_panic(expected = "operands have different types")]
+    fn binop_type_mismatch() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let i64 = module.types.i64();
+        let fn_ty = module.types.function(i32, &[i32, i64], false);
+        let f = module.add_function("f", fn_ty);
+        let (a, b) = (module.function(f).arg(0), module.function(f).arg(1));
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        builder.add(a, b, "");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Zext")]
+    fn invalid_cast() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let i8 = module.types.i8();
+        let fn_ty = module.types.function(i32, &[i32], false);
+        let f = module.add_function("f", fn_ty);
+        let a = module.function(f).arg(0);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        builder.zext(a, i8, "");
+    }
+
+    #[test]
+    #[should_panic(expected = "Argument(0) doesn't belong to the function")]
+    fn argument_of_another_function() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32], false);
+        let f = module.add_function("f", fn_ty);
+        let g = module.add_function("g", fn_ty);
+        let a = module.function(g).arg(0);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        builder.ret(a);
+    }
+
+    #[test]
+    #[should_panic(expected = "Instruction(InstId(0)) doesn't belong to the function")]
+    fn instruction_of_another_function() {
+        let mut module = Module::new("test.c");
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32], false);
+        let f = module.add_function("f", fn_ty);
+        let g = module.add_function("g", fn_ty);
+
+        let mut builder = Builder::new(&mut module, g);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        let a = builder.module().function(g).arg(0);
+        let sum = builder.add(a, a, "sum");
+        builder.ret(sum);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        let a = builder.module().function(f).arg(0);
+        builder.add(a, sum, "");
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::bitcode::constants::{BinaryOpCode, CastCodes};
+
+use super::{
+    types::TypeId,
+    value::{BasicBlockId, ConstantId, Value},
+};
+
+// https://llvm.org/docs/LangRef.html#constants
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConstantKind {
+    /// Integers up to 128 bits, zero extended.
+    Int(u128),
+    /// The raw bits of a floating point value.
+    Float(u128),
+    /// `zeroinitializer` or `null`.
+    Null,
+    Undef,
+    Poison,
+    /// Structs, arrays and vectors with arbitrary constant elements.
+    Aggregate(Vec<Value>),
+    /// Arrays and vectors of integers or floating point values, the raw bits of each element.
+    Data(Vec<u64>),
+    /// The cast is to the type of the constant.
+    Cast {
+        op: CastCodes,
+        value: Value,
+    },
+    /// `flags` are the nuw/nsw flags as encoded in bitcode.
+    BinOp {
+        op: BinaryOpCode,
+        lhs: Value,
+        rhs: Value,
+        flags: u64,
+    },
+    /// `flags` are the GEP no-wrap flags as encoded in bitcode, `inrange` the
+    /// offsets from the result the address may be used to access.
+    GetElementPtr {
+        source_type: TypeId,
+        ptr: Value,
+        indices: Vec<Value>,
+        flags: u64,
+        inrange: Option<(i64, i64)>,
+    },
+    BlockAddress {
+        function: Value,
+        block: BasicBlockId,
+    },
+    DsoLocalEquivalent(Value),
+    NoCfi(Value),
+    InlineAsm(InlineAsm),
+    /// `addr_discriminator` is a null pointer when the address isn't blended in.
+    PtrAuth {
+        ptr: Value,
+        key: Value,
+        discriminator: Value,
+        addr_discriminator: Value,
+    },
+}
+
+impl ConstantKind {
+    /// The values used by the constant.
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            ConstantKind::Int(_)
+            | ConstantKind::Float(_)
+            | ConstantKind::Null
+            | ConstantKind::Undef
+            | ConstantKind::Poison
+            | ConstantKind::Data(_)
+            | ConstantKind::InlineAsm(_) => Vec::new(),
+            ConstantKind::Aggregate(elements) => elements.clone(),
+            ConstantKind::Cast { value, .. }
+            | ConstantKind::DsoLocalEquivalent(value)
+            | ConstantKind::NoCfi(value) => vec![*value],
+            ConstantKind::BinOp { lhs, rhs, .. } => vec![*lhs, *rhs],
+            ConstantKind::GetElementPtr { ptr, indices, .. } => std::iter::once(*ptr)
+                .chain(indices.iter().copied())
+                .collect(),
+            ConstantKind::BlockAddress { function, .. } => vec![*function],
+            ConstantKind::PtrAuth {
+                ptr,
+                key,
+                discriminator,
+                addr_discriminator,
+            } => vec![*ptr, *key, *discriminator, *addr_discriminator],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AsmDialect {
+    #[default]
+    Att,
+    Intel,
+}
+
+/// An inline asm blob, called with the type `function_type`.
+// https://llvm.org/docs/LangRef.html#inline-assembler-expressions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InlineAsm {
+    pub function_type: TypeId,
+    pub asm: String,
+    pub constraints: String,
+    pub side_effect: bool,
+    pub align_stack: bool,
+    pub dialect: AsmDialect,
+    /// The asm may unwind, calls to it can be invokes.
+    pub can_throw: bool,
+}
+
+impl InlineAsm {
+    pub fn new(function_type: TypeId, asm: &str, constraints: &str) -> Self {
+        Self {
+            function_type,
+            asm: asm.to_string(),
+            constraints: constraints.to_string(),
+            side_effect: false,
+            align_stack: false,
+            dialect: AsmDialect::Att,
+            can_throw: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Constant {
+    pub ty: TypeId,
+    pub kind: ConstantKind,
+}
+
+/// The uniqued constants of a module.
+#[derive(Debug, Clone, Default)]
+pub struct Constants {
+    items: Vec<Constant>,
+    interned: HashMap<Constant, ConstantId>,
+}
+
+impl Constants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: ConstantId) -> &Constant {
+        &self.items[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ConstantId, &Constant)> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (ConstantId(i as u32), x))
+    }
+
+    pub fn intern(&mut self, constant: Constant) -> ConstantId {
+        if let Some(id) = self.interned.get(&constant) {
+            return *id;
+        }
+
+        let id = ConstantId(self.items.len() as u32);
+        self.items.push(constant.clone());
+        self.interned.insert(constant, id);
+        id
+    }
+}
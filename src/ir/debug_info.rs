@@ -0,0 +1,491 @@
+//! The debug info metadata nodes, strings are `MDString` operands and `None`
+//! operands are null. See [`DIBuilder`](super::di_builder::DIBuilder) to create them.
+// https://llvm.org/docs/LangRef.html#specialized-metadata-nodes
+
+use super::metadata::MetadataId;
+
+/// Flags of the debug info nodes, combined in the `flags` fields.
+// https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/include/llvm/IR/DebugInfoFlags.def
+#[derive(Debug, Clone, Copy)]
+pub enum DIFlag {
+    Private = 1,
+    Protected = 2,
+    Public = 3,
+    FwdDecl = 1 << 2,
+    AppleBlock = 1 << 3,
+    Virtual = 1 << 5,
+    Artificial = 1 << 6,
+    Explicit = 1 << 7,
+    Prototyped = 1 << 8,
+    ObjcClassComplete = 1 << 9,
+    ObjectPointer = 1 << 10,
+    Vector = 1 << 11,
+    StaticMember = 1 << 12,
+    LValueReference = 1 << 13,
+    RValueReference = 1 << 14,
+    ExportSymbols = 1 << 15,
+    SingleInheritance = 1 << 16,
+    MultipleInheritance = 2 << 16,
+    VirtualInheritance = 3 << 16,
+    IntroducedVirtual = 1 << 18,
+    BitField = 1 << 19,
+    NoReturn = 1 << 20,
+    TypePassByValue = 1 << 22,
+    TypePassByReference = 1 << 23,
+    EnumClass = 1 << 24,
+    Thunk = 1 << 25,
+    NonTrivial = 1 << 26,
+    BigEndian = 1 << 27,
+    LittleEndian = 1 << 28,
+    AllCallsDescribed = 1 << 29,
+}
+
+/// Flags of subprograms, combined in [`DISubprogram::sp_flags`].
+#[derive(Debug, Clone, Copy)]
+pub enum DISPFlag {
+    Virtual = 1,
+    PureVirtual = 2,
+    LocalToUnit = 1 << 2,
+    Definition = 1 << 3,
+    Optimized = 1 << 4,
+    Pure = 1 << 5,
+    Elemental = 1 << 6,
+    Recursive = 1 << 7,
+    MainSubprogram = 1 << 8,
+    Deleted = 1 << 9,
+    ObjCDirect = 1 << 11,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumKind {
+    Md5 = 1,
+    Sha1 = 2,
+    Sha256 = 3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EmissionKind {
+    NoDebug = 0,
+    #[default]
+    FullDebug = 1,
+    LineTablesOnly = 2,
+    DebugDirectivesOnly = 3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NameTableKind {
+    #[default]
+    Default = 0,
+    Gnu = 1,
+    None = 2,
+    Apple = 3,
+}
+
+// https://llvm.org/docs/LangRef.html#difile
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIFile {
+    pub filename: Option<MetadataId>,
+    pub directory: Option<MetadataId>,
+    /// The hex digest of the file.
+    pub checksum: Option<(ChecksumKind, MetadataId)>,
+    /// The embedded source, for the DWARF 5 extension.
+    pub source: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// Always distinct.
+// https://llvm.org/docs/LangRef.html#dicompileunit
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DICompileUnit {
+    /// A `DW_LANG_*` constant.
+    pub language: u32,
+    pub file: Option<MetadataId>,
+    pub producer: Option<MetadataId>,
+    pub optimized: bool,
+    /// The command line flags.
+    pub flags: Option<MetadataId>,
+    pub runtime_version: u32,
+    pub split_debug_filename: Option<MetadataId>,
+    pub emission_kind: EmissionKind,
+    pub enum_types: Option<MetadataId>,
+    pub retained_types: Option<MetadataId>,
+    pub global_variables: Option<MetadataId>,
+    pub imported_entities: Option<MetadataId>,
+    pub dwo_id: u64,
+    pub macros: Option<MetadataId>,
+    pub split_debug_inlining: bool,
+    pub debug_info_for_profiling: bool,
+    pub name_table_kind: NameTableKind,
+    pub ranges_base_address: bool,
+    pub sysroot: Option<MetadataId>,
+    pub sdk: Option<MetadataId>,
+}
+
+/// Definitions are distinct and belong to a unit, declarations are uniqued.
+// https://llvm.org/docs/LangRef.html#disubprogram
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DISubprogram {
+    pub scope: Option<MetadataId>,
+    pub name: Option<MetadataId>,
+    pub linkage_name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    /// A subroutine type.
+    pub ty: Option<MetadataId>,
+    pub scope_line: u32,
+    pub containing_type: Option<MetadataId>,
+    /// [`DISPFlag`]s.
+    pub sp_flags: u32,
+    pub virtual_index: u32,
+    /// [`DIFlag`]s.
+    pub flags: u32,
+    pub unit: Option<MetadataId>,
+    pub template_params: Option<MetadataId>,
+    pub declaration: Option<MetadataId>,
+    pub retained_nodes: Option<MetadataId>,
+    pub this_adjustment: i32,
+    pub thrown_types: Option<MetadataId>,
+    pub annotations: Option<MetadataId>,
+    pub target_func_name: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+// https://llvm.org/docs/LangRef.html#dilexicalblock
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DILexicalBlock {
+    pub scope: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub column: u32,
+    pub distinct: bool,
+}
+
+/// A part of a scope in another file or with a discriminator.
+// https://llvm.org/docs/LangRef.html#dilexicalblockfile
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DILexicalBlockFile {
+    pub scope: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub discriminator: u32,
+    pub distinct: bool,
+}
+
+/// `inlined_at` is the location of the call when the scope was inlined.
+// https://llvm.org/docs/LangRef.html#dilocation
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DILocation {
+    pub line: u32,
+    pub column: u32,
+    pub scope: MetadataId,
+    pub inlined_at: Option<MetadataId>,
+    pub implicit_code: bool,
+    pub distinct: bool,
+}
+
+// https://llvm.org/docs/LangRef.html#dibasictype
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIBasicType {
+    pub tag: u32,
+    pub name: Option<MetadataId>,
+    pub size_in_bits: u64,
+    pub align_in_bits: u32,
+    /// A `DW_ATE_*` constant.
+    pub encoding: u32,
+    pub flags: u32,
+    pub distinct: bool,
+}
+
+/// Pointers, qualifiers, typedefs and members, `base_type` is the type they
+/// derive from.
+// https://llvm.org/docs/LangRef.html#diderivedtype
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIDerivedType {
+    pub tag: u32,
+    pub name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub scope: Option<MetadataId>,
+    pub base_type: Option<MetadataId>,
+    pub size_in_bits: u64,
+    pub align_in_bits: u32,
+    pub offset_in_bits: u64,
+    pub flags: u32,
+    /// Tag specific, like the class of a pointer to member.
+    pub extra_data: Option<MetadataId>,
+    pub dwarf_address_space: Option<u32>,
+    pub annotations: Option<MetadataId>,
+    /// The packed pointer authentication options of `__ptrauth` qualifiers.
+    pub ptr_auth_data: u32,
+    pub distinct: bool,
+}
+
+/// Structs, unions, enums and arrays. `identifier` is the unique name used to
+/// merge the types of different modules.
+// https://llvm.org/docs/LangRef.html#dicompositetype
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DICompositeType {
+    pub tag: u32,
+    pub name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub scope: Option<MetadataId>,
+    pub base_type: Option<MetadataId>,
+    pub size_in_bits: u64,
+    pub align_in_bits: u32,
+    pub offset_in_bits: u64,
+    pub flags: u32,
+    /// The members, enumerators or subranges.
+    pub elements: Option<MetadataId>,
+    pub runtime_lang: u32,
+    pub vtable_holder: Option<MetadataId>,
+    pub template_params: Option<MetadataId>,
+    pub identifier: Option<MetadataId>,
+    pub discriminator: Option<MetadataId>,
+    pub data_location: Option<MetadataId>,
+    pub associated: Option<MetadataId>,
+    pub allocated: Option<MetadataId>,
+    pub rank: Option<MetadataId>,
+    pub annotations: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// `types` is a node with the return type, null for `void`, then the parameter
+/// types.
+// https://llvm.org/docs/LangRef.html#disubroutinetype
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DISubroutineType {
+    pub flags: u32,
+    /// A `DW_CC_*` constant, 0 for the default.
+    pub cc: u32,
+    pub types: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// The bounds are `i64` values, variables or expressions.
+// https://llvm.org/docs/LangRef.html#disubrange
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DISubrange {
+    pub count: Option<MetadataId>,
+    pub lower_bound: Option<MetadataId>,
+    pub upper_bound: Option<MetadataId>,
+    pub stride: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// Like [`DISubrange`], the bounds are variables or expressions.
+// https://llvm.org/docs/LangRef.html#digenericsubrange
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIGenericSubrange {
+    pub count: Option<MetadataId>,
+    pub lower_bound: Option<MetadataId>,
+    pub upper_bound: Option<MetadataId>,
+    pub stride: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// `value` is truncated to `bit_width` bits.
+// https://llvm.org/docs/LangRef.html#dienumerator
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIEnumerator {
+    pub name: Option<MetadataId>,
+    pub value: i128,
+    pub bit_width: u32,
+    pub unsigned: bool,
+    pub distinct: bool,
+}
+
+/// A Fortran string.
+// https://llvm.org/docs/LangRef.html#distringtype
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIStringType {
+    pub tag: u32,
+    pub name: Option<MetadataId>,
+    pub string_length: Option<MetadataId>,
+    pub string_length_exp: Option<MetadataId>,
+    pub string_location_exp: Option<MetadataId>,
+    pub size_in_bits: u64,
+    pub align_in_bits: u32,
+    pub encoding: u32,
+    pub distinct: bool,
+}
+
+// https://llvm.org/docs/LangRef.html#ditemplatetypeparameter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DITemplateTypeParameter {
+    pub name: Option<MetadataId>,
+    pub ty: Option<MetadataId>,
+    pub is_default: bool,
+    pub distinct: bool,
+}
+
+/// `value` is a constant, or a node for template template parameters and packs.
+// https://llvm.org/docs/LangRef.html#ditemplatevalueparameter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DITemplateValueParameter {
+    pub tag: u32,
+    pub name: Option<MetadataId>,
+    pub ty: Option<MetadataId>,
+    pub is_default: bool,
+    pub value: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// A local variable or a parameter, `arg` is the 1-based position of parameters
+/// and 0 otherwise.
+// https://llvm.org/docs/LangRef.html#dilocalvariable
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DILocalVariable {
+    pub scope: Option<MetadataId>,
+    pub name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub ty: Option<MetadataId>,
+    pub arg: u32,
+    pub flags: u32,
+    pub align_in_bits: u32,
+    pub annotations: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+// https://llvm.org/docs/LangRef.html#diglobalvariable
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIGlobalVariable {
+    pub scope: Option<MetadataId>,
+    pub name: Option<MetadataId>,
+    pub linkage_name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub ty: Option<MetadataId>,
+    pub local_to_unit: bool,
+    pub definition: bool,
+    pub static_data_member_declaration: Option<MetadataId>,
+    pub template_params: Option<MetadataId>,
+    pub align_in_bits: u32,
+    pub annotations: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// A global variable with the expression computing its value from the address
+/// of the global it is attached to.
+// https://llvm.org/docs/LangRef.html#diglobalvariableexpression
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIGlobalVariableExpression {
+    pub variable: MetadataId,
+    pub expression: MetadataId,
+    pub distinct: bool,
+}
+
+// https://llvm.org/docs/LangRef.html#dilabel
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DILabel {
+    pub scope: Option<MetadataId>,
+    pub name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub distinct: bool,
+}
+
+/// The `DW_OP_*` operations and their operands.
+// https://llvm.org/docs/LangRef.html#diexpression
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIExpression {
+    pub elements: Vec<u64>,
+    pub distinct: bool,
+}
+
+/// The values of a variable location using several of them, through
+/// `DW_OP_LLVM_arg`. Only usable as a debug record location.
+// https://llvm.org/docs/LangRef.html#diarglist
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIArgList {
+    /// Values wrapped as metadata.
+    pub args: Vec<MetadataId>,
+}
+
+/// `export_symbols` is set for inline namespaces.
+// https://llvm.org/docs/LangRef.html#dinamespace
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DINamespace {
+    pub scope: Option<MetadataId>,
+    pub name: Option<MetadataId>,
+    pub export_symbols: bool,
+    pub distinct: bool,
+}
+
+/// A source language module, like a Clang or Fortran module.
+// https://llvm.org/docs/LangRef.html#dimodule
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIModule {
+    pub file: Option<MetadataId>,
+    pub scope: Option<MetadataId>,
+    pub name: Option<MetadataId>,
+    pub configuration_macros: Option<MetadataId>,
+    pub include_path: Option<MetadataId>,
+    pub api_notes_file: Option<MetadataId>,
+    pub line: u32,
+    pub is_decl: bool,
+    pub distinct: bool,
+}
+
+/// A `using` or `use` making `entity` visible in `scope`, `elements` are the
+/// renamed parts of an imported module.
+// https://llvm.org/docs/LangRef.html#diimportedentity
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIImportedEntity {
+    pub tag: u32,
+    pub scope: Option<MetadataId>,
+    pub entity: Option<MetadataId>,
+    pub line: u32,
+    pub name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub elements: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// A `#define` or `#undef`, `macro_type` is a `DW_MACINFO_*` constant.
+// https://llvm.org/docs/LangRef.html#dimacro
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIMacro {
+    pub macro_type: u32,
+    pub line: u32,
+    pub name: Option<MetadataId>,
+    pub value: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// The macros of an included file, `line` is the line of the include.
+// https://llvm.org/docs/LangRef.html#dimacrofile
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIMacroFile {
+    pub macro_type: u32,
+    pub line: u32,
+    pub file: Option<MetadataId>,
+    pub elements: Option<MetadataId>,
+    pub distinct: bool,
+}
+
+/// A Fortran `COMMON` block, the scope of the variables it contains.
+// https://llvm.org/docs/LangRef.html#dicommonblock
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DICommonBlock {
+    pub scope: Option<MetadataId>,
+    pub declaration: Option<MetadataId>,
+    pub name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub distinct: bool,
+}
+
+/// `attributes` are the `DW_APPLE_PROPERTY_*` flags.
+// https://llvm.org/docs/LangRef.html#diobjcproperty
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DIObjCProperty {
+    pub name: Option<MetadataId>,
+    pub file: Option<MetadataId>,
+    pub line: u32,
+    pub getter_name: Option<MetadataId>,
+    pub setter_name: Option<MetadataId>,
+    pub attributes: u32,
+    pub ty: Option<MetadataId>,
+    pub distinct: bool,
+}
@@ -0,0 +1,1798 @@
+use super::{
+    debug_info::{
+        ChecksumKind, DIArgList, DIBasicType, DICommonBlock, DICompileUnit, DICompositeType,
+        DIDerivedType, DIEnumerator, DIExpression, DIFile, DIFlag, DIGenericSubrange,
+        DIGlobalVariable, DIGlobalVariableExpression, DIImportedEntity, DILabel, DILexicalBlock,
+        DILexicalBlockFile, DILocalVariable, DILocation, DIMacro, DIMacroFile, DIModule,
+        DINamespace, DIObjCProperty, DISPFlag, DIStringType, DISubprogram, DISubrange,
+        DISubroutineType, DITemplateTypeParameter, DITemplateValueParameter, EmissionKind,
+        NameTableKind,
+    },
+    dwarf::{
+        DW_MACINFO_DEFINE, DW_MACINFO_START_FILE, DW_MACINFO_UNDEF, DW_TAG_ARRAY_TYPE,
+        DW_TAG_BASE_TYPE, DW_TAG_ENUMERATION_TYPE, DW_TAG_IMPORTED_DECLARATION,
+        DW_TAG_IMPORTED_MODULE, DW_TAG_MEMBER, DW_TAG_POINTER_TYPE, DW_TAG_STRING_TYPE,
+        DW_TAG_STRUCTURE_TYPE, DW_TAG_TEMPLATE_VALUE_PARAMETER, DW_TAG_TYPEDEF, DW_TAG_UNION_TYPE,
+    },
+    metadata::{Metadata, MetadataId},
+    module::Module,
+    value::Value,
+};
+
+const DBG_CU: &str = "llvm.dbg.cu";
+
+/// Creates debug info metadata, like LLVM's `DIBuilder`.
+///
+/// Strings are interned as `MDString`s, empty ones become null operands. Create
+/// the compile unit first, subprogram definitions belong to the last unit of the
+/// module.
+// https://llvm.org/docs/SourceLevelDebugging.html
+pub struct DIBuilder<'m> {
+    module: &'m mut Module,
+}
+
+impl<'m> DIBuilder<'m> {
+    pub fn new(module: &'m mut Module) -> Self {
+        Self { module }
+    }
+
+    pub fn module(&mut self) -> &mut Module {
+        self.module
+    }
+
+    fn string(&mut self, value: &str) -> Option<MetadataId> {
+        if value.is_empty() {
+            None
+        } else {
+            Some(self.module.md_string(value))
+        }
+    }
+
+    fn array(&mut self, elements: &[MetadataId]) -> MetadataId {
+        let operands: Vec<_> = elements.iter().copied().map(Some).collect();
+        self.module.md_node(&operands)
+    }
+
+    fn int(&mut self, value: i64) -> MetadataId {
+        let i64 = self.module.types.i64();
+        let value = self.module.const_int(i64, value as i128);
+        self.module.md_value(value)
+    }
+
+    fn check_file(&self, file: MetadataId) {
+        assert!(
+            matches!(self.module.metadata.get(file), Metadata::File(_)),
+            "expected a DIFile"
+        );
+    }
+
+    /// The unit subprogram definitions belong to.
+    pub fn unit(&self) -> Option<MetadataId> {
+        self.module
+            .named_metadata(DBG_CU)
+            .and_then(|x| x.operands.last().copied())
+    }
+
+    /// `checksum` is the kind and hex digest of the file contents.
+    pub fn create_file(
+        &mut self,
+        filename: &str,
+        directory: &str,
+        checksum: Option<(ChecksumKind, &str)>,
+    ) -> MetadataId {
+        let filename = self.string(filename);
+        let directory = self.string(directory);
+        let checksum = checksum.map(|(kind, value)| (kind, self.module.md_string(value)));
+        self.module.add_metadata(Metadata::File(DIFile {
+            filename,
+            directory,
+            checksum,
+            source: None,
+            distinct: false,
+        }))
+    }
+
+    /// A compile unit, `language` is a `DW_LANG_*` constant. It is added to
+    /// `!llvm.dbg.cu`.
+    pub fn create_compile_unit(
+        &mut self,
+        language: u32,
+        file: MetadataId,
+        producer: &str,
+        optimized: bool,
+        emission_kind: EmissionKind,
+    ) -> MetadataId {
+        self.check_file(file);
+
+        let producer = self.string(producer);
+        let unit = self
+            .module
+            .add_metadata(Metadata::CompileUnit(DICompileUnit {
+                language,
+                file: Some(file),
+                producer,
+                optimized,
+                flags: None,
+                runtime_version: 0,
+                split_debug_filename: None,
+                emission_kind,
+                enum_types: None,
+                retained_types: None,
+                global_variables: None,
+                imported_entities: None,
+                dwo_id: 0,
+                macros: None,
+                split_debug_inlining: true,
+                debug_info_for_profiling: false,
+                name_table_kind: NameTableKind::Default,
+                ranges_base_address: false,
+                sysroot: None,
+                sdk: None,
+            }));
+        self.module.add_named_metadata_operand(DBG_CU, unit);
+        unit
+    }
+
+    /// A subprogram, distinct and part of the unit when `sp_flags` has
+    /// [`DISPFlag::Definition`]. Attach definitions to their function with
+    /// [`Function::set_subprogram`](super::function::Function::set_subprogram).
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_function(
+        &mut self,
+        scope: MetadataId,
+        name: &str,
+        linkage_name: &str,
+        file: MetadataId,
+        line: u32,
+        ty: Option<MetadataId>,
+        scope_line: u32,
+        flags: u32,
+        sp_flags: u32,
+    ) -> MetadataId {
+        self.check_file(file);
+
+        let definition = sp_flags & DISPFlag::Definition as u32 != 0;
+        let unit = if definition {
+            Some(self.unit().expect("create a compile unit first"))
+        } else {
+            None
+        };
+
+        let name = self.string(name);
+        let linkage_name = self.string(linkage_name);
+        self.module.add_metadata(Metadata::Subprogram(DISubprogram {
+            scope: Some(scope),
+            name,
+            linkage_name,
+            file: Some(file),
+            line,
+            ty,
+            scope_line,
+            containing_type: None,
+            sp_flags,
+            virtual_index: 0,
+            flags,
+            unit,
+            template_params: None,
+            declaration: None,
+            retained_nodes: None,
+            this_adjustment: 0,
+            thrown_types: None,
+            annotations: None,
+            target_func_name: None,
+            distinct: definition,
+        }))
+    }
+
+    /// A nested scope, always distinct.
+    pub fn create_lexical_block(
+        &mut self,
+        scope: MetadataId,
+        file: MetadataId,
+        line: u32,
+        column: u32,
+    ) -> MetadataId {
+        self.check_file(file);
+        self.module
+            .add_metadata(Metadata::LexicalBlock(DILexicalBlock {
+                scope: Some(scope),
+                file: Some(file),
+                line,
+                column,
+                distinct: true,
+            }))
+    }
+
+    /// The part of `scope` from another file, or with a discriminator telling
+    /// apart the code of a line.
+    pub fn create_lexical_block_file(
+        &mut self,
+        scope: MetadataId,
+        file: MetadataId,
+        discriminator: u32,
+    ) -> MetadataId {
+        self.check_file(file);
+        self.module
+            .add_metadata(Metadata::LexicalBlockFile(DILexicalBlockFile {
+                scope: Some(scope),
+                file: Some(file),
+                discriminator,
+                distinct: false,
+            }))
+    }
+
+    /// A source location in `scope`, `inlined_at` is the location of the call the
+    /// scope was inlined into.
+    pub fn create_location(
+        &mut self,
+        line: u32,
+        column: u32,
+        scope: MetadataId,
+        inlined_at: Option<MetadataId>,
+    ) -> MetadataId {
+        assert!(
+            matches!(
+                self.module.metadata.get(scope),
+                Metadata::Subprogram(_) | Metadata::LexicalBlock(_) | Metadata::LexicalBlockFile(_)
+            ),
+            "locations are in a subprogram or lexical block"
+        );
+        if let Some(inlined_at) = inlined_at {
+            assert!(
+                matches!(self.module.metadata.get(inlined_at), Metadata::Location(_)),
+                "inlined_at must be a DILocation"
+            );
+        }
+
+        self.module.add_metadata(Metadata::Location(DILocation {
+            line,
+            column,
+            scope,
+            inlined_at,
+            implicit_code: false,
+            distinct: false,
+        }))
+    }
+
+    /// A scalar type, `encoding` is a `DW_ATE_*` constant.
+    pub fn create_basic_type(
+        &mut self,
+        name: &str,
+        size_in_bits: u64,
+        encoding: u32,
+        flags: u32,
+    ) -> MetadataId {
+        let name = self.string(name);
+        self.module.add_metadata(Metadata::BasicType(DIBasicType {
+            tag: DW_TAG_BASE_TYPE,
+            name,
+            size_in_bits,
+            align_in_bits: 0,
+            encoding,
+            flags,
+            distinct: false,
+        }))
+    }
+
+    fn derived_type(&mut self, tag: u32, base_type: Option<MetadataId>) -> DIDerivedType {
+        DIDerivedType {
+            tag,
+            name: None,
+            file: None,
+            line: 0,
+            scope: None,
+            base_type,
+            size_in_bits: 0,
+            align_in_bits: 0,
+            offset_in_bits: 0,
+            flags: 0,
+            extra_data: None,
+            dwarf_address_space: None,
+            annotations: None,
+            ptr_auth_data: 0,
+            distinct: false,
+        }
+    }
+
+    /// A pointer to `pointee`, `None` for `void*`.
+    pub fn create_pointer_type(
+        &mut self,
+        pointee: Option<MetadataId>,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        name: &str,
+    ) -> MetadataId {
+        let name = self.string(name);
+        let ty = DIDerivedType {
+            name,
+            size_in_bits,
+            align_in_bits,
+            ..self.derived_type(DW_TAG_POINTER_TYPE, pointee)
+        };
+        self.module.add_metadata(Metadata::DerivedType(ty))
+    }
+
+    /// `tag` is a qualifier like `DW_TAG_CONST_TYPE` or `DW_TAG_VOLATILE_TYPE`,
+    /// or a reference tag.
+    pub fn create_qualified_type(&mut self, tag: u32, base_type: MetadataId) -> MetadataId {
+        let ty = self.derived_type(tag, Some(base_type));
+        self.module.add_metadata(Metadata::DerivedType(ty))
+    }
+
+    pub fn create_typedef(
+        &mut self,
+        base_type: MetadataId,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        scope: Option<MetadataId>,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        let ty = DIDerivedType {
+            name,
+            file: Some(file),
+            line,
+            scope,
+            ..self.derived_type(DW_TAG_TYPEDEF, Some(base_type))
+        };
+        self.module.add_metadata(Metadata::DerivedType(ty))
+    }
+
+    /// A field of the struct or union `scope`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_member_type(
+        &mut self,
+        scope: MetadataId,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        offset_in_bits: u64,
+        flags: u32,
+        ty: MetadataId,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        let ty = DIDerivedType {
+            name,
+            file: Some(file),
+            line,
+            scope: Some(scope),
+            size_in_bits,
+            align_in_bits,
+            offset_in_bits,
+            flags,
+            ..self.derived_type(DW_TAG_MEMBER, Some(ty))
+        };
+        self.module.add_metadata(Metadata::DerivedType(ty))
+    }
+
+    fn composite_type(&mut self, tag: u32, base_type: Option<MetadataId>) -> DICompositeType {
+        DICompositeType {
+            tag,
+            name: None,
+            file: None,
+            line: 0,
+            scope: None,
+            base_type,
+            size_in_bits: 0,
+            align_in_bits: 0,
+            offset_in_bits: 0,
+            flags: 0,
+            elements: None,
+            runtime_lang: 0,
+            vtable_holder: None,
+            template_params: None,
+            identifier: None,
+            discriminator: None,
+            data_location: None,
+            associated: None,
+            allocated: None,
+            rank: None,
+            annotations: None,
+            distinct: false,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn record_type(
+        &mut self,
+        tag: u32,
+        scope: Option<MetadataId>,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        flags: u32,
+        elements: &[MetadataId],
+        identifier: &str,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        let elements = self.array(elements);
+        let identifier = self.string(identifier);
+        let ty = DICompositeType {
+            name,
+            file: Some(file),
+            line,
+            scope,
+            size_in_bits,
+            align_in_bits,
+            flags,
+            elements: Some(elements),
+            identifier,
+            distinct: true,
+            ..self.composite_type(tag, None)
+        };
+        self.module.add_metadata(Metadata::CompositeType(ty))
+    }
+
+    /// A struct, distinct so its members can be added later with
+    /// [`set_elements`](Self::set_elements) once they point back to it.
+    /// `identifier` is the unique name merging the type across modules, like a
+    /// mangled name, or empty.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_struct_type(
+        &mut self,
+        scope: Option<MetadataId>,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        flags: u32,
+        elements: &[MetadataId],
+        identifier: &str,
+    ) -> MetadataId {
+        self.record_type(
+            DW_TAG_STRUCTURE_TYPE,
+            scope,
+            name,
+            file,
+            line,
+            size_in_bits,
+            align_in_bits,
+            flags,
+            elements,
+            identifier,
+        )
+    }
+
+    /// Like [`create_struct_type`](Self::create_struct_type).
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_union_type(
+        &mut self,
+        scope: Option<MetadataId>,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        flags: u32,
+        elements: &[MetadataId],
+        identifier: &str,
+    ) -> MetadataId {
+        self.record_type(
+            DW_TAG_UNION_TYPE,
+            scope,
+            name,
+            file,
+            line,
+            size_in_bits,
+            align_in_bits,
+            flags,
+            elements,
+            identifier,
+        )
+    }
+
+    /// An enum with the `enumerators`, `underlying_type` is the integer type of
+    /// the values.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_enumeration_type(
+        &mut self,
+        scope: Option<MetadataId>,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        enumerators: &[MetadataId],
+        underlying_type: Option<MetadataId>,
+        identifier: &str,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        let elements = self.array(enumerators);
+        let identifier = self.string(identifier);
+        let ty = DICompositeType {
+            name,
+            file: Some(file),
+            line,
+            scope,
+            size_in_bits,
+            align_in_bits,
+            elements: Some(elements),
+            identifier,
+            distinct: true,
+            ..self.composite_type(DW_TAG_ENUMERATION_TYPE, underlying_type)
+        };
+        self.module.add_metadata(Metadata::CompositeType(ty))
+    }
+
+    /// An array of `element_type`, one subrange per dimension.
+    pub fn create_array_type(
+        &mut self,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        element_type: MetadataId,
+        subscripts: &[MetadataId],
+    ) -> MetadataId {
+        let elements = self.array(subscripts);
+        let ty = DICompositeType {
+            size_in_bits,
+            align_in_bits,
+            elements: Some(elements),
+            ..self.composite_type(DW_TAG_ARRAY_TYPE, Some(element_type))
+        };
+        self.module.add_metadata(Metadata::CompositeType(ty))
+    }
+
+    /// A SIMD vector, an array with [`DIFlag::Vector`].
+    pub fn create_vector_type(
+        &mut self,
+        size_in_bits: u64,
+        align_in_bits: u32,
+        element_type: MetadataId,
+        subscripts: &[MetadataId],
+    ) -> MetadataId {
+        let elements = self.array(subscripts);
+        let ty = DICompositeType {
+            size_in_bits,
+            align_in_bits,
+            flags: DIFlag::Vector as u32,
+            elements: Some(elements),
+            ..self.composite_type(DW_TAG_ARRAY_TYPE, Some(element_type))
+        };
+        self.module.add_metadata(Metadata::CompositeType(ty))
+    }
+
+    fn update_composite(&mut self, composite: MetadataId, f: impl FnOnce(&mut DICompositeType)) {
+        let Metadata::CompositeType(ty) = self.module.metadata.get(composite) else {
+            panic!("expected a DICompositeType");
+        };
+        let mut ty = ty.clone();
+        f(&mut ty);
+        self.module
+            .metadata
+            .replace(composite, Metadata::CompositeType(ty));
+    }
+
+    /// Replaces the members or enumerators of a distinct composite type, to make
+    /// cycles through their scope.
+    pub fn set_elements(&mut self, composite: MetadataId, elements: &[MetadataId]) {
+        let elements = self.array(elements);
+        self.update_composite(composite, |ty| ty.elements = Some(elements));
+    }
+
+    /// Sets the template parameters of a distinct composite type.
+    pub fn set_template_params(&mut self, composite: MetadataId, params: &[MetadataId]) {
+        let params = self.array(params);
+        self.update_composite(composite, |ty| ty.template_params = Some(params));
+    }
+
+    /// A function type, `types` starts with the return type, `None` for `void`,
+    /// followed by the parameter types.
+    pub fn create_subroutine_type(
+        &mut self,
+        types: &[Option<MetadataId>],
+        flags: u32,
+    ) -> MetadataId {
+        let types = self.module.md_node(types);
+        self.module
+            .add_metadata(Metadata::SubroutineType(DISubroutineType {
+                flags,
+                cc: 0,
+                types: Some(types),
+                distinct: false,
+            }))
+    }
+
+    /// The `count` elements of an array dimension starting at `lower_bound`.
+    pub fn create_subrange(&mut self, lower_bound: i64, count: i64) -> MetadataId {
+        let count = self.int(count);
+        let lower_bound = self.int(lower_bound);
+        self.module.add_metadata(Metadata::Subrange(DISubrange {
+            count: Some(count),
+            lower_bound: Some(lower_bound),
+            upper_bound: None,
+            stride: None,
+            distinct: false,
+        }))
+    }
+
+    /// A dimension with bounds computed at runtime, the operands are variables or
+    /// expressions.
+    pub fn create_generic_subrange(
+        &mut self,
+        count: Option<MetadataId>,
+        lower_bound: Option<MetadataId>,
+        upper_bound: Option<MetadataId>,
+        stride: Option<MetadataId>,
+    ) -> MetadataId {
+        self.module
+            .add_metadata(Metadata::GenericSubrange(DIGenericSubrange {
+                count,
+                lower_bound,
+                upper_bound,
+                stride,
+                distinct: false,
+            }))
+    }
+
+    /// A 64-bit enumerator, `unsigned` tells how to read `value`.
+    pub fn create_enumerator(&mut self, name: &str, value: i64, unsigned: bool) -> MetadataId {
+        let name = self.string(name);
+        self.module.add_metadata(Metadata::Enumerator(DIEnumerator {
+            name,
+            value: value as i128,
+            bit_width: 64,
+            unsigned,
+            distinct: false,
+        }))
+    }
+
+    /// A Fortran `CHARACTER` type of a constant size.
+    pub fn create_string_type(&mut self, name: &str, size_in_bits: u64) -> MetadataId {
+        let name = self.string(name);
+        self.module.add_metadata(Metadata::StringType(DIStringType {
+            tag: DW_TAG_STRING_TYPE,
+            name,
+            string_length: None,
+            string_length_exp: None,
+            string_location_exp: None,
+            size_in_bits,
+            align_in_bits: 0,
+            encoding: 0,
+            distinct: false,
+        }))
+    }
+
+    pub fn create_template_type_parameter(
+        &mut self,
+        name: &str,
+        ty: Option<MetadataId>,
+        is_default: bool,
+    ) -> MetadataId {
+        let name = self.string(name);
+        self.module
+            .add_metadata(Metadata::TemplateTypeParameter(DITemplateTypeParameter {
+                name,
+                ty,
+                is_default,
+                distinct: false,
+            }))
+    }
+
+    /// `value` is usually a constant wrapped with
+    /// [`Module::md_value`](super::module::Module::md_value).
+    pub fn create_template_value_parameter(
+        &mut self,
+        name: &str,
+        ty: Option<MetadataId>,
+        is_default: bool,
+        value: Option<MetadataId>,
+    ) -> MetadataId {
+        let name = self.string(name);
+        self.module
+            .add_metadata(Metadata::TemplateValueParameter(DITemplateValueParameter {
+                tag: DW_TAG_TEMPLATE_VALUE_PARAMETER,
+                name,
+                ty,
+                is_default,
+                value,
+                distinct: false,
+            }))
+    }
+
+    /// The `DW_OP_*` operations of a location, empty when the location is the
+    /// value itself.
+    pub fn create_expression(&mut self, elements: &[u64]) -> MetadataId {
+        self.module.add_metadata(Metadata::Expression(DIExpression {
+            elements: elements.to_vec(),
+            distinct: false,
+        }))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn local_variable(
+        &mut self,
+        scope: MetadataId,
+        name: &str,
+        arg: u32,
+        file: MetadataId,
+        line: u32,
+        ty: MetadataId,
+        flags: u32,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        self.module
+            .add_metadata(Metadata::LocalVariable(DILocalVariable {
+                scope: Some(scope),
+                name,
+                file: Some(file),
+                line,
+                ty: Some(ty),
+                arg,
+                flags,
+                align_in_bits: 0,
+                annotations: None,
+                distinct: false,
+            }))
+    }
+
+    /// A local variable of `scope`, a subprogram or lexical block.
+    pub fn create_auto_variable(
+        &mut self,
+        scope: MetadataId,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        ty: MetadataId,
+        flags: u32,
+    ) -> MetadataId {
+        self.local_variable(scope, name, 0, file, line, ty, flags)
+    }
+
+    /// The parameter at the 1-based position `arg`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_parameter_variable(
+        &mut self,
+        scope: MetadataId,
+        name: &str,
+        arg: u32,
+        file: MetadataId,
+        line: u32,
+        ty: MetadataId,
+        flags: u32,
+    ) -> MetadataId {
+        assert!(arg > 0, "parameters are numbered from 1");
+        self.local_variable(scope, name, arg, file, line, ty, flags)
+    }
+
+    pub fn create_label(
+        &mut self,
+        scope: MetadataId,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        self.module.add_metadata(Metadata::Label(DILabel {
+            scope: Some(scope),
+            name,
+            file: Some(file),
+            line,
+            distinct: false,
+        }))
+    }
+
+    /// A location made of several values, referenced as `DW_OP_LLVM_arg N` by the
+    /// expression.
+    pub fn create_arg_list(&mut self, values: &[Value]) -> MetadataId {
+        let args = values.iter().map(|x| self.module.md_value(*x)).collect();
+        self.module
+            .add_metadata(Metadata::ArgList(DIArgList { args }))
+    }
+
+    /// A new id linking `dbg_assign` records to stores.
+    pub fn create_assign_id(&mut self) -> MetadataId {
+        self.module.add_metadata(Metadata::AssignId)
+    }
+
+    /// A global variable definition, added to the globals of the unit. Attach the
+    /// result to the global with
+    /// [`GlobalVariable::set_debug_info`](super::global::GlobalVariable::set_debug_info).
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_global_variable_expression(
+        &mut self,
+        scope: MetadataId,
+        name: &str,
+        linkage_name: &str,
+        file: MetadataId,
+        line: u32,
+        ty: MetadataId,
+        local_to_unit: bool,
+        expression: MetadataId,
+    ) -> MetadataId {
+        self.check_file(file);
+        let unit = self.unit().expect("create a compile unit first");
+
+        let name = self.string(name);
+        let linkage_name = self.string(linkage_name);
+        let variable = self
+            .module
+            .add_metadata(Metadata::GlobalVariable(DIGlobalVariable {
+                scope: Some(scope),
+                name,
+                linkage_name,
+                file: Some(file),
+                line,
+                ty: Some(ty),
+                local_to_unit,
+                definition: true,
+                static_data_member_declaration: None,
+                template_params: None,
+                align_in_bits: 0,
+                annotations: None,
+                distinct: true,
+            }));
+        let global = self.module.add_metadata(Metadata::GlobalVariableExpression(
+            DIGlobalVariableExpression {
+                variable,
+                expression,
+                distinct: false,
+            },
+        ));
+
+        self.append_to_unit(unit, |cu| &mut cu.global_variables, &[global]);
+        global
+    }
+
+    /// Replaces `unit` with a copy where `elements` are appended to the list
+    /// selected by `field`.
+    fn append_to_unit(
+        &mut self,
+        unit: MetadataId,
+        field: fn(&mut DICompileUnit) -> &mut Option<MetadataId>,
+        elements: &[MetadataId],
+    ) {
+        let Metadata::CompileUnit(cu) = self.module.metadata.get(unit) else {
+            unreachable!("llvm.dbg.cu operands are compile units");
+        };
+        let mut cu = cu.clone();
+        let list = field(&mut cu);
+        *list = Some(self.extend_list(*list, elements));
+        self.module
+            .metadata
+            .replace(unit, Metadata::CompileUnit(cu));
+    }
+
+    /// The operands of a list of the unit or a subprogram, empty when unset.
+    fn list(&self, list: Option<MetadataId>) -> Vec<Option<MetadataId>> {
+        match list {
+            Some(node) => match self.module.metadata.get(node) {
+                Metadata::Node(node) => node.operands.clone(),
+                _ => unreachable!("the unit and subprogram lists are nodes"),
+            },
+            None => Vec::new(),
+        }
+    }
+
+    fn extend_list(&mut self, list: Option<MetadataId>, elements: &[MetadataId]) -> MetadataId {
+        let mut operands = self.list(list);
+        operands.extend(elements.iter().copied().map(Some));
+        self.module.md_node(&operands)
+    }
+
+    /// The subprogram a local scope is in, `None` for the other scopes.
+    fn subprogram_of(&self, scope: MetadataId) -> Option<MetadataId> {
+        match self.module.metadata.get(scope) {
+            Metadata::Subprogram(_) => Some(scope),
+            Metadata::LexicalBlock(DILexicalBlock { scope, .. })
+            | Metadata::LexicalBlockFile(DILexicalBlockFile { scope, .. }) => {
+                self.subprogram_of(scope.expect("lexical blocks have a scope"))
+            }
+            _ => None,
+        }
+    }
+
+    /// `export_symbols` makes an inline namespace.
+    pub fn create_namespace(
+        &mut self,
+        scope: Option<MetadataId>,
+        name: &str,
+        export_symbols: bool,
+    ) -> MetadataId {
+        let name = self.string(name);
+        self.module.add_metadata(Metadata::Namespace(DINamespace {
+            scope,
+            name,
+            export_symbols,
+            distinct: false,
+        }))
+    }
+
+    /// A language module, `is_decl` is set when it's only declared by this unit.
+    pub fn create_module(
+        &mut self,
+        scope: Option<MetadataId>,
+        name: &str,
+        file: Option<MetadataId>,
+        line: u32,
+        is_decl: bool,
+    ) -> MetadataId {
+        if let Some(file) = file {
+            self.check_file(file);
+        }
+        let name = self.string(name);
+        self.module.add_metadata(Metadata::Module(DIModule {
+            file,
+            scope,
+            name,
+            configuration_macros: None,
+            include_path: None,
+            api_notes_file: None,
+            line,
+            is_decl,
+            distinct: false,
+        }))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn imported_entity(
+        &mut self,
+        tag: u32,
+        scope: MetadataId,
+        entity: MetadataId,
+        file: MetadataId,
+        line: u32,
+        name: &str,
+        elements: &[MetadataId],
+    ) -> MetadataId {
+        self.check_file(file);
+        let unit = self.unit().expect("create a compile unit first");
+
+        let name = self.string(name);
+        let elements = if elements.is_empty() {
+            None
+        } else {
+            Some(self.array(elements))
+        };
+        let entity = self
+            .module
+            .add_metadata(Metadata::ImportedEntity(DIImportedEntity {
+                tag,
+                scope: Some(scope),
+                entity: Some(entity),
+                line,
+                name,
+                file: Some(file),
+                elements,
+                distinct: false,
+            }));
+
+        // Imports in a function are retained by its subprogram. Equal imports
+        // are the same node, which is listed once.
+        match self.subprogram_of(scope) {
+            Some(subprogram) => {
+                let Metadata::Subprogram(sp) = self.module.metadata.get(subprogram) else {
+                    unreachable!("subprogram_of returns subprograms");
+                };
+                let mut sp = sp.clone();
+                if !self.list(sp.retained_nodes).contains(&Some(entity)) {
+                    sp.retained_nodes = Some(self.extend_list(sp.retained_nodes, &[entity]));
+                    self.module
+                        .metadata
+                        .replace(subprogram, Metadata::Subprogram(sp));
+                }
+            }
+            None => {
+                let Metadata::CompileUnit(cu) = self.module.metadata.get(unit) else {
+                    unreachable!("llvm.dbg.cu operands are compile units");
+                };
+                if !self.list(cu.imported_entities).contains(&Some(entity)) {
+                    self.append_to_unit(unit, |cu| &mut cu.imported_entities, &[entity]);
+                }
+            }
+        }
+        entity
+    }
+
+    /// Imports all the names of a namespace or module into `scope`, like
+    /// `use m::*`. `elements` are the renamed declarations. The import is added
+    /// to the unit, or to the retained nodes of the subprogram of a local scope.
+    pub fn create_imported_module(
+        &mut self,
+        scope: MetadataId,
+        module: MetadataId,
+        file: MetadataId,
+        line: u32,
+        elements: &[MetadataId],
+    ) -> MetadataId {
+        self.imported_entity(
+            DW_TAG_IMPORTED_MODULE,
+            scope,
+            module,
+            file,
+            line,
+            "",
+            elements,
+        )
+    }
+
+    /// Imports a single declaration into `scope`, `name` is set when it's
+    /// renamed, like `use m::f as g`. The import is added like
+    /// [`DIBuilder::create_imported_module`].
+    pub fn create_imported_declaration(
+        &mut self,
+        scope: MetadataId,
+        declaration: MetadataId,
+        file: MetadataId,
+        line: u32,
+        name: &str,
+    ) -> MetadataId {
+        self.imported_entity(
+            DW_TAG_IMPORTED_DECLARATION,
+            scope,
+            declaration,
+            file,
+            line,
+            name,
+            &[],
+        )
+    }
+
+    /// A `DW_MACINFO_define` or `DW_MACINFO_undef` of `name`, `value` is empty
+    /// for `#undef`. Add it to the unit with [`Self::add_macros`] or to a
+    /// [`Self::create_macro_file`].
+    pub fn create_macro(
+        &mut self,
+        macro_type: u32,
+        line: u32,
+        name: &str,
+        value: &str,
+    ) -> MetadataId {
+        assert!(
+            matches!(macro_type, DW_MACINFO_DEFINE | DW_MACINFO_UNDEF),
+            "expected a define or undef"
+        );
+        assert!(!name.is_empty(), "macros must have a name");
+        let name = self.string(name);
+        let value = self.string(value);
+        self.module.add_metadata(Metadata::Macro(DIMacro {
+            macro_type,
+            line,
+            name,
+            value,
+            distinct: false,
+        }))
+    }
+
+    /// The macros of a `file` included at `line`.
+    pub fn create_macro_file(
+        &mut self,
+        line: u32,
+        file: MetadataId,
+        elements: &[MetadataId],
+    ) -> MetadataId {
+        self.check_file(file);
+        let elements = if elements.is_empty() {
+            None
+        } else {
+            Some(self.array(elements))
+        };
+        self.module.add_metadata(Metadata::MacroFile(DIMacroFile {
+            macro_type: DW_MACINFO_START_FILE,
+            line,
+            file: Some(file),
+            elements,
+            distinct: false,
+        }))
+    }
+
+    /// Appends macros and macro files to the macros of the unit.
+    pub fn add_macros(&mut self, macros: &[MetadataId]) {
+        let unit = self.unit().expect("create a compile unit first");
+        for &x in macros {
+            assert!(
+                matches!(
+                    self.module.metadata.get(x),
+                    Metadata::Macro(_) | Metadata::MacroFile(_)
+                ),
+                "expected a DIMacro or DIMacroFile"
+            );
+        }
+        self.append_to_unit(unit, |cu| &mut cu.macros, macros);
+    }
+
+    /// A Fortran `COMMON` block, use it as the scope of its global variables.
+    /// `declaration` is the global variable of the whole block, if any.
+    pub fn create_common_block(
+        &mut self,
+        scope: MetadataId,
+        declaration: Option<MetadataId>,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        self.module
+            .add_metadata(Metadata::CommonBlock(DICommonBlock {
+                scope: Some(scope),
+                declaration,
+                name,
+                file: Some(file),
+                line,
+                distinct: false,
+            }))
+    }
+
+    /// An Objective-C property, list it in the elements of its class.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_objc_property(
+        &mut self,
+        name: &str,
+        file: MetadataId,
+        line: u32,
+        getter_name: &str,
+        setter_name: &str,
+        attributes: u32,
+        ty: Option<MetadataId>,
+    ) -> MetadataId {
+        self.check_file(file);
+        let name = self.string(name);
+        let getter_name = self.string(getter_name);
+        let setter_name = self.string(setter_name);
+        self.module
+            .add_metadata(Metadata::ObjCProperty(DIObjCProperty {
+                name,
+                file: Some(file),
+                line,
+                getter_name,
+                setter_name,
+                attributes,
+                ty,
+                distinct: false,
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::{BlockId, FunctionCide, MetadataCode, StrtabCode},
+        bitstream::reader::read,
+        ir::{
+            builder::Builder,
+            debug_info::{ChecksumKind, DIFile, DIFlag, DISPFlag, EmissionKind},
+            dwarf::{
+                DW_ATE_SIGNED, DW_ATE_UNSIGNED, DW_LANG_C11, DW_LANG_C_PLUS_PLUS_14, DW_LANG_RUST,
+                DW_MACINFO_DEFINE, DW_MACINFO_UNDEF, DW_OP_LLVM_ARG, DW_OP_PLUS, DW_OP_STACK_VALUE,
+                DW_TAG_CONST_TYPE,
+            },
+            metadata::{Metadata, MetadataId, MetadataKind},
+            module::Module,
+        },
+    };
+
+    use super::DIBuilder;
+
+    #[test]
+    fn build_debug_locations() {
+        let mut module = Module::new("test.c");
+        module.set_dwarf_version(5);
+        module.set_debug_info_version();
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32], false);
+        let f = module.add_function("f", fn_ty);
+        let arg = module.function(f).arg(0);
+
+        let mut di = DIBuilder::new(&mut module);
+        let file = di.create_file(
+            "test.c",
+            "/src",
+            Some((ChecksumKind::Md5, "0123456789abcdef0123456789abcdef")),
+        );
+        // With its source embedded.
+        let filename = di.module().md_string("test.h");
+        let directory = di.module().md_string("/src");
+        let source = di.module().md_string("int x;");
+        let header = di.module().add_metadata(Metadata::File(DIFile {
+            filename: Some(filename),
+            directory: Some(directory),
+            checksum: None,
+            source: Some(source),
+            distinct: false,
+        }));
+        di.create_compile_unit(
+            DW_LANG_C11,
+            file,
+            "compiler 1.0",
+            false,
+            EmissionKind::FullDebug,
+        );
+        let sp = di.create_function(
+            file,
+            "f",
+            "",
+            file,
+            3,
+            None,
+            4,
+            DIFlag::Prototyped as u32,
+            DISPFlag::Definition as u32,
+        );
+        let block = di.create_lexical_block(sp, file, 5, 3);
+        let in_header = di.create_lexical_block_file(block, header, 0);
+        let call = di.create_location(6, 10, block, None);
+        let inlined = di.create_location(20, 5, in_header, Some(call));
+        let ret = di.create_location(8, 3, sp, None);
+        module.function_mut(f).set_subprogram(sp);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        builder.set_debug_loc(Some(call));
+        let x = builder.add(arg, arg, "x");
+        let y = builder.mul(x, x, "y");
+        builder.set_debug_loc(Some(inlined));
+        let z = builder.sub(y, arg, "z");
+        builder.set_debug_loc(Some(ret));
+        builder.ret(z);
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let metadata = module_block.block(BlockId::Metadata as u32);
+        let strings = metadata
+            .record(MetadataCode::STRINGS as u32)
+            .metadata_strings();
+        assert_eq!(
+            strings,
+            [
+                "Dwarf Version",
+                "Debug Info Version",
+                "test.c",
+                "/src",
+                "0123456789abcdef0123456789abcdef",
+                "compiler 1.0",
+                "f",
+                "test.h",
+                "int x;",
+            ]
+        );
+
+        // The values are the metadata 9 to 12, then come the unit 13, the
+        // subprogram 14, the block 15, the files 18 and 19, the block file 20
+        // and the call location 21. The operands are shifted by one, except
+        // for the scope of locations.
+        // [distinct, filename, directory, checksum kind, checksum, source]
+        let files = metadata.ops(MetadataCode::FILE as u32);
+        let md5 = ChecksumKind::Md5 as u64;
+        assert_eq!(files, [vec![0, 3, 4, md5, 5], vec![0, 8, 4, 0, 0, 9]]);
+        // [distinct, language, file, producer, optimized, flags, runtime
+        //  version, split debug filename, emission kind]
+        let unit = metadata.record(MetadataCode::COMPILE_UNIT as u32);
+        let full_debug = EmissionKind::FullDebug as u64;
+        assert_eq!(unit.ops[..9], [1, 29, 19, 6, 0, 0, 0, 0, full_debug]);
+        // [distinct | unit | sp flags, scope, name, linkage name, file, line,
+        //  type, scope line, containing type, sp flags, virtual index, flags,
+        //  unit]
+        let subprogram = metadata.record(MetadataCode::SUBPROGRAM as u32);
+        let definition = DISPFlag::Definition as u64;
+        let prototyped = DIFlag::Prototyped as u64;
+        assert_eq!(
+            subprogram.ops[..13],
+            [7, 19, 7, 0, 19, 3, 0, 4, 0, definition, 0, prototyped, 14]
+        );
+        // [distinct, scope, file, line, column]
+        let block = metadata.record(MetadataCode::LEXICAL_BLOCK as u32);
+        assert_eq!(block.ops, [1, 15, 19, 5, 3]);
+        // [distinct, scope, file, discriminator]
+        let block_file = metadata.record(MetadataCode::LEXICAL_BLOCK_FILE as u32);
+        assert_eq!(block_file.ops, [0, 16, 20, 0]);
+        // [distinct, line, column, scope, inlined at, implicit code]
+        let call = metadata.record(MetadataCode::LOCATION as u32);
+        assert_eq!(call.ops, [0, 6, 10, 15, 0, 0]);
+
+        // [line, column, scope, inlined at, implicit code], %y repeats the
+        // location of %x.
+        let body = module_block.block(BlockId::Function as u32);
+        let locations = body.ops(FunctionCide::DEBUG_LOC as u32);
+        assert_eq!(
+            locations,
+            [[6, 10, 16, 0, 0], [20, 5, 21, 22, 0], [8, 3, 15, 0, 0]]
+        );
+        let again = body.records(FunctionCide::DEBUG_LOC_AGAIN as u32);
+        assert_eq!(again.len(), 1);
+        let attachment = body
+            .block(BlockId::MetadataAttachment as u32)
+            .record(MetadataCode::ATTACHMENT as u32);
+        assert_eq!(attachment.ops, [MetadataKind::DBG.0 as u64, 14]);
+    }
+
+    #[test]
+    fn build_debug_types() {
+        let mut module = Module::new("test.cpp");
+        module.set_debug_info_version();
+        let ptr = module.types.ptr();
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[ptr, i32], false);
+        let f = module.add_function("f", fn_ty);
+        let one = module.const_int(i32, 1);
+        let one = module.md_value(one);
+
+        let mut di = DIBuilder::new(&mut module);
+        let file = di.create_file("test.cpp", "/src", None);
+        di.create_compile_unit(
+            DW_LANG_C_PLUS_PLUS_14,
+            file,
+            "compiler 1.0",
+            false,
+            EmissionKind::FullDebug,
+        );
+        let int = di.create_basic_type("int", 32, DW_ATE_SIGNED, 0);
+        let uint = di.create_basic_type("unsigned int", 32, DW_ATE_UNSIGNED, 0);
+        let const_int = di.create_qualified_type(DW_TAG_CONST_TYPE, int);
+
+        // template <int N> struct S { const int a; int b[4]; S *next; };
+        let by_value = DIFlag::TypePassByValue as u32;
+        let s = di.create_struct_type(
+            None,
+            "S<1>",
+            file,
+            1,
+            256,
+            64,
+            by_value,
+            &[],
+            "_ZTS1SILi1EE",
+        );
+        let public = DIFlag::Public as u32;
+        let a = di.create_member_type(s, "a", file, 2, 32, 0, 0, public, const_int);
+        let dim = di.create_subrange(0, 4);
+        let array = di.create_array_type(128, 32, int, &[dim]);
+        let b = di.create_member_type(s, "b", file, 3, 128, 0, 32, public, array);
+        let s_ptr = di.create_pointer_type(Some(s), 64, 0, "");
+        let protected = DIFlag::Protected as u32;
+        let next = di.create_member_type(s, "next", file, 4, 64, 0, 192, protected, s_ptr);
+        di.set_elements(s, &[a, b, next]);
+        let n = di.create_template_value_parameter("N", Some(int), false, Some(one));
+        let t = di.create_template_type_parameter("T", Some(int), true);
+        di.set_template_params(s, &[n, t]);
+
+        // enum E : unsigned { Min = 0, Max = 0xffffffff }, with a signed one too.
+        let min = di.create_enumerator("Min", 0, true);
+        let max = di.create_enumerator("Max", u32::MAX as i64, true);
+        let e = di.create_enumeration_type(
+            None,
+            "E",
+            file,
+            6,
+            32,
+            32,
+            &[min, max],
+            Some(uint),
+            "_ZTS1E",
+        );
+        let neg = di.create_enumerator("Neg", -1, false);
+        let signed = di.create_enumeration_type(None, "F", file, 7, 32, 32, &[neg], Some(int), "");
+        let e_t = di.create_typedef(e, "E_t", file, 8, None);
+        let chars = di.create_string_type("character(10)", 80);
+
+        let ty = di.create_subroutine_type(
+            &[Some(int), Some(s_ptr), Some(e_t), Some(signed), Some(chars)],
+            0,
+        );
+        let sp = di.create_function(
+            file,
+            "f",
+            "_Z1fP1S1E",
+            file,
+            10,
+            Some(ty),
+            10,
+            DIFlag::Prototyped as u32,
+            DISPFlag::Definition as u32,
+        );
+        module.function_mut(f).set_subprogram(sp);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        let arg = builder.module().function(f).arg(1);
+        builder.ret(arg);
+
+        let bitcode = read(&module.to_bitcode());
+        let metadata = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Metadata as u32);
+        let strings = metadata
+            .record(MetadataCode::STRINGS as u32)
+            .metadata_strings();
+        assert_eq!(
+            strings[6..14],
+            ["int", "S<1>", "a", "b", "next", "N", "T", "_ZTS1SILi1EE"]
+        );
+
+        // S is the metadata 29, the elements of S 43 and its template
+        // parameters 46. The operands are shifted by one.
+        // [distinct, tag, name, file, line, scope, base type, size, align,
+        //  offset, flags, elements, runtime lang, vtable holder, template
+        //  params, identifier]
+        let composites = metadata.records(MetadataCode::COMPOSITE_TYPE as u32);
+        let by_value = DIFlag::TypePassByValue as u64;
+        assert_eq!(
+            composites[0].ops[..16],
+            [3, 0x13, 8, 35, 1, 0, 0, 256, 64, 0, by_value, 44, 0, 0, 47, 14]
+        );
+        // The array of int 35 with the subrange 38 in the node 39.
+        assert_eq!(
+            composites[3].ops[..12],
+            [2, 0x01, 0, 0, 0, 0, 36, 128, 32, 0, 0, 40]
+        );
+        // [version << 1 | distinct, count, lower bound, upper bound, stride]
+        let subrange = metadata.record(MetadataCode::SUBRANGE as u32);
+        assert_eq!(subrange.ops, [4, 26, 27, 0, 0]);
+
+        // [distinct, tag, name, file, line, scope, base type, size, align,
+        //  offset, flags, extra data, address space, annotations]
+        let derived = metadata.ops(MetadataCode::DERIVED_TYPE as u32);
+        let (public, protected) = (DIFlag::Public as u64, DIFlag::Protected as u64);
+        assert_eq!(
+            derived[..5],
+            [
+                [0, 0x26, 0, 0, 0, 0, 36, 0, 0, 0, 0, 0, 0, 0],
+                [0, 0x0d, 9, 35, 2, 30, 37, 32, 0, 0, public, 0, 0, 0],
+                [0, 0x0d, 10, 35, 3, 30, 41, 128, 0, 32, public, 0, 0, 0],
+                [0, 0x0d, 11, 35, 4, 30, 48, 64, 0, 192, protected, 0, 0, 0],
+                [0, 0x0f, 0, 0, 0, 0, 30, 64, 0, 0, 0, 0, 0, 0],
+            ]
+        );
+
+        // [distinct, tag, name, type, is default, value] and
+        // [distinct, name, type, is default]
+        let value = metadata.record(MetadataCode::TEMPLATE_VALUE as u32);
+        assert_eq!(value.ops, [0, 0x30, 12, 36, 0, 28]);
+        let ty = metadata.record(MetadataCode::TEMPLATE_TYPE as u32);
+        assert_eq!(ty.ops, [0, 13, 36, 1]);
+
+        // [big int << 2 | unsigned << 1 | distinct, width, name, value], the
+        // values are signed VBRs.
+        let enumerators = metadata.ops(MetadataCode::ENUMERATOR as u32);
+        assert_eq!(
+            enumerators,
+            [
+                [6, 64, 18, 0],
+                [6, 64, 19, (u32::MAX as u64) << 1],
+                [4, 64, 22, 3]
+            ]
+        );
+    }
+
+    // mod m { pub fn f() {} }  use m::f as g;  use std::*;
+    #[test]
+    fn build_debug_scopes() {
+        let mut module = Module::new("test.rs");
+        module.set_debug_info_version();
+        let void = module.types.void();
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(void, &[], false);
+        let f = module.add_function("_ZN1m1f", fn_ty);
+        let c = module.add_global("c", i32);
+        let zero = module.const_int(i32, 0);
+        module.global_mut(c).initializer = Some(zero);
+
+        let mut di = DIBuilder::new(&mut module);
+        let file = di.create_file("test.rs", "/src", None);
+        let unit = di.create_compile_unit(DW_LANG_RUST, file, "", false, EmissionKind::FullDebug);
+        let m = di.create_namespace(None, "m", false);
+        let ty = di.create_subroutine_type(&[None], 0);
+        let sp = di.create_function(
+            m,
+            "f",
+            "_ZN1m1f",
+            file,
+            1,
+            Some(ty),
+            1,
+            0,
+            DISPFlag::Definition as u32,
+        );
+        di.create_imported_declaration(unit, sp, file, 1, "g");
+        let std = di.create_module(None, "std", None, 0, true);
+        di.create_imported_module(unit, std, file, 2, &[]);
+
+        // #define N 1 in the unit, #undef N in an included header.
+        let define = di.create_macro(DW_MACINFO_DEFINE, 3, "N", "1");
+        let header = di.create_file("test.h", "/src", None);
+        let undef = di.create_macro(DW_MACINFO_UNDEF, 1, "N", "");
+        let include = di.create_macro_file(4, header, &[undef]);
+        di.add_macros(&[define, include]);
+
+        // COMMON /blk/ c
+        let int = di.create_basic_type("integer", 32, DW_ATE_SIGNED, 0);
+        let block = di.create_common_block(sp, None, "blk", file, 5);
+        let empty = di.create_expression(&[]);
+        let c_var =
+            di.create_global_variable_expression(block, "c", "", file, 5, int, false, empty);
+
+        // @interface I @property int p; @end
+        let p = di.create_objc_property("p", file, 6, "p", "setP:", 0, Some(int));
+        let i = di.create_struct_type(None, "I", file, 6, 0, 0, 0, &[p], "");
+        let i_ptr = di.create_pointer_type(Some(i), 64, 0, "");
+        di.module().add_named_metadata_operand("types", i_ptr);
+
+        module.function_mut(f).set_subprogram(sp);
+        module.global_mut(c).set_debug_info(c_var);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        builder.position_at_end(entry);
+        builder.ret_void();
+
+        let bitcode = read(&module.to_bitcode());
+        let metadata = bitcode
+            .block(BlockId::Module as u32)
+            .block(BlockId::Metadata as u32);
+        let strings = metadata
+            .record(MetadataCode::STRINGS as u32)
+            .metadata_strings();
+        assert_eq!(
+            strings[3..],
+            [
+                "m", "f", "_ZN1m1f", "blk", "c", "integer", "g", "std", "N", "1", "test.h", "I",
+                "p", "setP:"
+            ]
+        );
+
+        // f is the metadata 19, c 20, the unit 21, the file 24, m 25, blk 28,
+        // integer 29, std 34, test.h 38, the macros of test.h 40 and the
+        // members of I 44. The operands are shifted by one.
+        // [distinct | export symbols << 1, scope, name]
+        let namespace = metadata.record(MetadataCode::NAMESPACE as u32);
+        assert_eq!(namespace.ops, [0, 0, 4]);
+        // [distinct, tag, scope, entity, line, name, file, elements]
+        let imported = metadata.ops(MetadataCode::IMPORTED_ENTITY as u32);
+        assert_eq!(
+            imported,
+            [
+                [0, 0x08, 22, 20, 1, 10, 25, 0],
+                [0, 0x3a, 22, 35, 2, 0, 25, 0]
+            ]
+        );
+        // [distinct, file, scope, name, configuration macros, include path,
+        //  api notes, line, is declaration]
+        let std = metadata.record(MetadataCode::MODULE as u32);
+        assert_eq!(std.ops, [0, 0, 0, 11, 0, 0, 0, 0, 1]);
+        // The imported entities 36 and the macros 42 of the unit.
+        let unit = metadata.record(MetadataCode::COMPILE_UNIT as u32);
+        assert_eq!((unit.ops[13], unit.ops[15]), (37, 43));
+
+        // [distinct, type, line, name, value] and
+        // [distinct, type, line, file, elements]
+        let macros = metadata.ops(MetadataCode::MACRO as u32);
+        assert_eq!(macros, [[0, 1, 3, 12, 13], [0, 2, 1, 12, 0]]);
+        let file = metadata.record(MetadataCode::MACRO_FILE as u32);
+        assert_eq!(file.ops, [0, 3, 4, 39, 41]);
+
+        // [distinct, scope, declaration, name, file, line], c is in blk.
+        let block = metadata.record(MetadataCode::COMMON_BLOCK as u32);
+        assert_eq!(block.ops, [0, 20, 0, 7, 25, 5]);
+        let variable = metadata.record(MetadataCode::GLOBAL_VAR as u32);
+        assert_eq!(variable.ops[1], 29);
+
+        // [distinct, name, file, line, getter, setter, attributes, type]
+        let property = metadata.record(MetadataCode::OBJC_PROPERTY as u32);
+        assert_eq!(property.ops, [0, 16, 25, 6, 16, 17, 0, 30]);
+        let composite = metadata.record(MetadataCode::COMPOSITE_TYPE as u32);
+        assert_eq!(composite.ops[11], 45);
+        assert!(metadata.ops(MetadataCode::NODE as u32).contains(&vec![44]));
+    }
+
+    // fn f() { { use std::*; } }  use m::f as g;  use m::f as g;
+    #[test]
+    fn imported_entities_lists() {
+        let mut module = Module::new("test.rs");
+        let mut di = DIBuilder::new(&mut module);
+        let file = di.create_file("test.rs", "/src", None);
+        let unit = di.create_compile_unit(DW_LANG_RUST, file, "", false, EmissionKind::FullDebug);
+        let m = di.create_namespace(None, "m", false);
+        let sp = di.create_function(m, "f", "", file, 1, None, 1, 0, DISPFlag::Definition as u32);
+        let block = di.create_lexical_block(sp, file, 1, 10);
+        let std = di.create_module(None, "std", None, 0, true);
+        let local = di.create_imported_module(block, std, file, 1, &[]);
+        let g = di.create_imported_declaration(unit, sp, file, 2, "g");
+        assert_eq!(di.create_imported_declaration(unit, sp, file, 2, "g"), g);
+
+        let module = di.module();
+        let list = |node: Option<MetadataId>| match node.map(|x| module.metadata.get(x)) {
+            Some(Metadata::Node(node)) => node.operands.clone(),
+            _ => panic!("expected a list"),
+        };
+        let Metadata::CompileUnit(cu) = module.metadata.get(unit) else {
+            unreachable!();
+        };
+        assert_eq!(list(cu.imported_entities), [Some(g)]);
+        let Metadata::Subprogram(sp) = module.metadata.get(sp) else {
+            unreachable!();
+        };
+        assert_eq!(list(sp.retained_nodes), [Some(local)]);
+    }
+
+    // int g = 5;
+    // int f(int a) { int x = a; int y = a + 1; /* a + y */ out: return y; }
+    fn debug_variables(debug_intrinsics: bool) -> Module {
+        let mut module = Module::new("test.c");
+        module.set_debug_info_version();
+        module.debug_intrinsics = debug_intrinsics;
+        let i32 = module.types.i32();
+        let fn_ty = module.types.function(i32, &[i32], false);
+        let f = module.add_function("f", fn_ty);
+        let a = module.function(f).arg(0);
+        let g = module.add_global("g", i32);
+        let five = module.const_int(i32, 5);
+        module.global_mut(g).initializer = Some(five);
+        let zero = module.const_int(i32, 0);
+
+        let mut di = DIBuilder::new(&mut module);
+        let file = di.create_file("test.c", "/src", None);
+        let unit = di.create_compile_unit(DW_LANG_C11, file, "", false, EmissionKind::FullDebug);
+        let int = di.create_basic_type("int", 32, DW_ATE_SIGNED, 0);
+        let empty = di.create_expression(&[]);
+        let g_var = di.create_global_variable_expression(unit, "g", "", file, 1, int, false, empty);
+        let ty = di.create_subroutine_type(&[Some(int), Some(int)], 0);
+        let sp = di.create_function(
+            file,
+            "f",
+            "",
+            file,
+            2,
+            Some(ty),
+            2,
+            DIFlag::Prototyped as u32,
+            DISPFlag::Definition as u32,
+        );
+        let a_var = di.create_parameter_variable(sp, "a", 1, file, 2, int, 0);
+        let x_var = di.create_auto_variable(sp, "x", file, 3, int, 0);
+        let y_var = di.create_auto_variable(sp, "y", file, 4, int, 0);
+        let sum_var = di.create_auto_variable(sp, "sum", file, 5, int, 0);
+        let label = di.create_label(sp, "out", file, 6);
+        let sum = di.create_expression(&[
+            DW_OP_LLVM_ARG,
+            0,
+            DW_OP_LLVM_ARG,
+            1,
+            DW_OP_PLUS,
+            DW_OP_STACK_VALUE,
+        ]);
+        let assign_id = di.create_assign_id();
+        let locs: Vec<_> = (2..7).map(|x| di.create_location(x, 3, sp, None)).collect();
+        module.function_mut(f).set_subprogram(sp);
+        module.global_mut(g).set_debug_info(g_var);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        let out = builder.append_block("out");
+        builder.position_at_end(entry);
+        builder.set_debug_loc(Some(locs[0]));
+        let a_md = builder.module().md_value(a);
+        builder.dbg_value(a_md, a_var, empty);
+        let x = builder.alloca(i32, "x");
+        builder.set_debug_loc(Some(locs[1]));
+        if debug_intrinsics {
+            builder.dbg_declare(x, x_var, empty);
+            builder.store(a, x);
+        } else {
+            let store = builder.store(a, x);
+            builder.set_metadata(store, MetadataKind::DI_ASSIGN_ID, assign_id);
+            builder.dbg_assign(a_md, x_var, empty, assign_id, x, empty);
+        }
+        builder.set_debug_loc(Some(locs[2]));
+        let zero_md = builder.module().md_value(zero);
+        builder.dbg_value(zero_md, y_var, empty);
+        let one = builder.module().const_int(i32, 1);
+        let y = builder.add(a, one, "y");
+        let y_md = builder.module().md_value(y);
+        builder.dbg_value(y_md, y_var, empty);
+        builder.set_debug_loc(Some(locs[3]));
+        let args = DIBuilder::new(builder.module()).create_arg_list(&[a, y]);
+        builder.dbg_value(args, sum_var, sum);
+        builder.br(out);
+
+        builder.position_at_end(out);
+        builder.set_debug_loc(Some(locs[4]));
+        builder.dbg_label(label);
+        builder.ret(y);
+        module
+    }
+
+    #[test]
+    fn write_debug_records() {
+        let module = debug_variables(false);
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let body = module_block.block(BlockId::Function as u32);
+        let (alloca, store, add) = (19, 44, 2);
+        let (br, ret, loc) = (11, 10, 35);
+        let (value, assign, simple, label) = (61, 63, 64, 65);
+        assert_eq!(
+            body.codes(),
+            [
+                1, alloca, loc, simple, store, loc, add, loc, assign, simple, br, loc, simple,
+                value, ret, loc, label
+            ]
+        );
+
+        // The location 26, 28, 30, 32 and 35 of a, x, y, sum and out 27, 29,
+        // 31, 33 and 36, the empty expression 21 and the sum one 34. Values
+        // defined before are relative, %a as metadata is 37, %x 38 and the
+        // argument list 40.
+        // [loc, variable, expression, value]
+        let simple = body.ops(FunctionCide::DEBUG_RECORD_VALUE_SIMPLE as u32);
+        assert_eq!(simple, [[26, 27, 21, 3], [30, 31, 21, 5], [30, 31, 21, 1]]);
+        // [loc, variable, expression, location]
+        let value = body.record(FunctionCide::DEBUG_RECORD_VALUE as u32);
+        assert_eq!(value.ops, [32, 33, 34, 40]);
+        // [loc, variable, expression, location, assign id, address expression,
+        //  address]
+        let assign = body.record(FunctionCide::DEBUG_RECORD_ASSIGN as u32);
+        assert_eq!(assign.ops, [28, 29, 21, 37, 17, 21, 38]);
+        // [loc, label]
+        let label = body.record(FunctionCide::DEBUG_RECORD_LABEL as u32);
+        assert_eq!(label.ops, [35, 36]);
+
+        let locals = body.block(BlockId::Metadata as u32);
+        assert_eq!(locals.ops(MetadataCode::ARG_LIST as u32), [[37, 39]]);
+        // f has the subprogram 16 and the store is linked to the assignment by
+        // the DIAssignID 17.
+        let attachments = body
+            .block(BlockId::MetadataAttachment as u32)
+            .ops(MetadataCode::ATTACHMENT as u32);
+        let (dbg, assign_id) = (MetadataKind::DBG.0, MetadataKind::DI_ASSIGN_ID.0);
+        assert_eq!(
+            attachments,
+            [vec![dbg as u64, 16], vec![1, assign_id as u64, 17]]
+        );
+    }
+
+    #[test]
+    fn write_debug_intrinsics() {
+        let module = debug_variables(true);
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let strtab = bitcode
+            .block(BlockId::Strtab as u32)
+            .record(StrtabCode::Blob as u32);
+        assert_eq!(
+            strtab.blob.as_deref(),
+            Some(&b"gfllvm.dbg.valuellvm.dbg.declarellvm.dbg.label"[..])
+        );
+        let body = module_block.block(BlockId::Function as u32);
+        assert!(body.codes().iter().all(|x| !(61..=65).contains(x)));
+
+        // [attrs, cc, type, callee, args...] with relative operands, the
+        // metadata arguments wrap around.
+        let calls = body.ops(FunctionCide::INST_CALL as u32);
+        let explicit_type = 1 << 15;
+        let callees: Vec<_> = calls.iter().map(|x| x[3]).collect();
+        assert!(calls.iter().all(|x| x[..2] == [0, explicit_type]));
+        assert_eq!(callees, [9, 9, 10, 11, 11, 9]);
+        // llvm.dbg.value(metadata %a, ...), the variable and the expression
+        // are module metadata.
+        assert_eq!(
+            calls[0][4..],
+            [
+                u32::MAX as u64 - 19,
+                u32::MAX as u64 - 13,
+                u32::MAX as u64 - 8
+            ]
+        );
+        // llvm.dbg.label(metadata !out)
+        assert_eq!(calls[5][4..], [u32::MAX as u64 - 16]);
+
+        let locals = body.block(BlockId::Metadata as u32);
+        assert_eq!(locals.ops(MetadataCode::ARG_LIST as u32), [[31, 33]]);
+        // Only f has an attachment, the store has no DIAssignID.
+        let attachments = body
+            .block(BlockId::MetadataAttachment as u32)
+            .ops(MetadataCode::ATTACHMENT as u32);
+        assert_eq!(attachments, [[MetadataKind::DBG.0 as u64, 16]]);
+    }
+}
@@ -0,0 +1,85 @@
+//! DWARF constants used by the debug info metadata.
+// https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/include/llvm/BinaryFormat/Dwarf.def
+
+// Source languages, the `language` of a compile unit.
+pub const DW_LANG_C89: u32 = 0x0001;
+pub const DW_LANG_C: u32 = 0x0002;
+pub const DW_LANG_C_PLUS_PLUS: u32 = 0x0004;
+pub const DW_LANG_FORTRAN90: u32 = 0x0008;
+pub const DW_LANG_C99: u32 = 0x000c;
+pub const DW_LANG_OBJC: u32 = 0x0010;
+pub const DW_LANG_C_PLUS_PLUS_11: u32 = 0x001a;
+pub const DW_LANG_RUST: u32 = 0x001c;
+pub const DW_LANG_C11: u32 = 0x001d;
+pub const DW_LANG_SWIFT: u32 = 0x001e;
+pub const DW_LANG_C_PLUS_PLUS_14: u32 = 0x0021;
+pub const DW_LANG_ZIG: u32 = 0x0027;
+pub const DW_LANG_C17: u32 = 0x002c;
+pub const DW_LANG_MIPS_ASSEMBLER: u32 = 0x8001;
+
+// Tags of the type, scope and import nodes.
+pub const DW_TAG_ARRAY_TYPE: u32 = 0x0001;
+pub const DW_TAG_CLASS_TYPE: u32 = 0x0002;
+pub const DW_TAG_ENUMERATION_TYPE: u32 = 0x0004;
+pub const DW_TAG_IMPORTED_DECLARATION: u32 = 0x0008;
+pub const DW_TAG_MEMBER: u32 = 0x000d;
+pub const DW_TAG_POINTER_TYPE: u32 = 0x000f;
+pub const DW_TAG_REFERENCE_TYPE: u32 = 0x0010;
+pub const DW_TAG_STRING_TYPE: u32 = 0x0012;
+pub const DW_TAG_STRUCTURE_TYPE: u32 = 0x0013;
+pub const DW_TAG_SUBROUTINE_TYPE: u32 = 0x0015;
+pub const DW_TAG_TYPEDEF: u32 = 0x0016;
+pub const DW_TAG_UNION_TYPE: u32 = 0x0017;
+pub const DW_TAG_VARIANT: u32 = 0x0019;
+pub const DW_TAG_INHERITANCE: u32 = 0x001c;
+pub const DW_TAG_MODULE: u32 = 0x001e;
+pub const DW_TAG_PTR_TO_MEMBER_TYPE: u32 = 0x001f;
+pub const DW_TAG_SUBRANGE_TYPE: u32 = 0x0021;
+pub const DW_TAG_BASE_TYPE: u32 = 0x0024;
+pub const DW_TAG_CONST_TYPE: u32 = 0x0026;
+pub const DW_TAG_ENUMERATOR: u32 = 0x0028;
+pub const DW_TAG_TEMPLATE_TYPE_PARAMETER: u32 = 0x002f;
+pub const DW_TAG_TEMPLATE_VALUE_PARAMETER: u32 = 0x0030;
+pub const DW_TAG_VARIANT_PART: u32 = 0x0033;
+pub const DW_TAG_VOLATILE_TYPE: u32 = 0x0035;
+pub const DW_TAG_RESTRICT_TYPE: u32 = 0x0037;
+pub const DW_TAG_NAMESPACE: u32 = 0x0039;
+pub const DW_TAG_IMPORTED_MODULE: u32 = 0x003a;
+pub const DW_TAG_RVALUE_REFERENCE_TYPE: u32 = 0x0042;
+pub const DW_TAG_GENERIC_SUBRANGE: u32 = 0x0045;
+pub const DW_TAG_ATOMIC_TYPE: u32 = 0x0047;
+pub const DW_TAG_IMMUTABLE_TYPE: u32 = 0x004b;
+pub const DW_TAG_GNU_TEMPLATE_TEMPLATE_PARAM: u32 = 0x4106;
+pub const DW_TAG_GNU_TEMPLATE_PARAMETER_PACK: u32 = 0x4107;
+
+// Encodings of the basic types.
+pub const DW_ATE_ADDRESS: u32 = 0x01;
+pub const DW_ATE_BOOLEAN: u32 = 0x02;
+pub const DW_ATE_COMPLEX_FLOAT: u32 = 0x03;
+pub const DW_ATE_FLOAT: u32 = 0x04;
+pub const DW_ATE_SIGNED: u32 = 0x05;
+pub const DW_ATE_SIGNED_CHAR: u32 = 0x06;
+pub const DW_ATE_UNSIGNED: u32 = 0x07;
+pub const DW_ATE_UNSIGNED_CHAR: u32 = 0x08;
+pub const DW_ATE_UTF: u32 = 0x10;
+
+// Kinds of macro nodes.
+pub const DW_MACINFO_DEFINE: u32 = 0x01;
+pub const DW_MACINFO_UNDEF: u32 = 0x02;
+pub const DW_MACINFO_START_FILE: u32 = 0x03;
+pub const DW_MACINFO_END_FILE: u32 = 0x04;
+
+// Operations of `DIExpression`s, the `DW_OP_LLVM_*` ones are LLVM extensions.
+pub const DW_OP_DEREF: u64 = 0x06;
+pub const DW_OP_CONSTU: u64 = 0x10;
+pub const DW_OP_CONSTS: u64 = 0x11;
+pub const DW_OP_MINUS: u64 = 0x1c;
+pub const DW_OP_PLUS: u64 = 0x22;
+pub const DW_OP_PLUS_UCONST: u64 = 0x23;
+pub const DW_OP_STACK_VALUE: u64 = 0x9f;
+pub const DW_OP_LLVM_FRAGMENT: u64 = 0x1000;
+pub const DW_OP_LLVM_CONVERT: u64 = 0x1001;
+pub const DW_OP_LLVM_TAG_OFFSET: u64 = 0x1002;
+pub const DW_OP_LLVM_ENTRY_VALUE: u64 = 0x1003;
+pub const DW_OP_LLVM_IMPLICIT_POINTER: u64 = 0x1004;
+pub const DW_OP_LLVM_ARG: u64 = 0x1005;
@@ -0,0 +1,263 @@
+use super::{
+    attributes::AttributeList,
+    global::{ComdatId, DllStorageClass, Linkage, UnnamedAddr, Visibility},
+    instructions::Instruction,
+    metadata::{Attachments, MetadataId, MetadataKind},
+    types::TypeId,
+    value::{BasicBlockId, FunctionId, InstId, Value, ValueRef},
+};
+
+// https://llvm.org/docs/LangRef.html#calling-conventions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CallingConv {
+    #[default]
+    C,
+    Fast,
+    Cold,
+    Ghc,
+    PreserveMost,
+    PreserveAll,
+    Swift,
+    Tail,
+    SwiftTail,
+    X86StdCall,
+    X86FastCall,
+    X86ThisCall,
+    X86VectorCall,
+    X86_64SysV,
+    Win64,
+    /// Any other calling convention by its LLVM id.
+    Other(u32),
+}
+
+impl CallingConv {
+    pub fn code(self) -> u64 {
+        match self {
+            CallingConv::C => 0,
+            CallingConv::Fast => 8,
+            CallingConv::Cold => 9,
+            CallingConv::Ghc => 10,
+            CallingConv::PreserveMost => 14,
+            CallingConv::PreserveAll => 15,
+            CallingConv::Swift => 16,
+            CallingConv::Tail => 18,
+            CallingConv::SwiftTail => 20,
+            CallingConv::X86StdCall => 64,
+            CallingConv::X86FastCall => 65,
+            CallingConv::X86ThisCall => 70,
+            CallingConv::X86_64SysV => 78,
+            CallingConv::Win64 => 79,
+            CallingConv::X86VectorCall => 80,
+            CallingConv::Other(x) => x as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BasicBlock {
+    pub name: Option<String>,
+    pub(crate) instructions: Vec<InstId>,
+}
+
+impl BasicBlock {
+    pub fn instructions(&self) -> &[InstId] {
+        &self.instructions
+    }
+}
+
+// https://llvm.org/docs/LangRef.html#functions
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub linkage: Linkage,
+    pub visibility: Visibility,
+    pub dll_storage_class: DllStorageClass,
+    pub unnamed_addr: UnnamedAddr,
+    pub dso_local: bool,
+    pub calling_conv: CallingConv,
+    pub alignment: Option<u64>,
+    pub section: Option<String>,
+    pub gc: Option<String>,
+    pub comdat: Option<ComdatId>,
+    /// The personality routine used by the exception handling instructions.
+    pub personality: Option<Value>,
+    pub attributes: AttributeList,
+    pub metadata: Attachments,
+    pub(crate) ty: TypeId,
+    pub(crate) ptr_ty: TypeId,
+    pub(crate) ret: TypeId,
+    pub(crate) params: Vec<TypeId>,
+    pub(crate) vararg: bool,
+    pub(crate) arg_names: Vec<Option<String>>,
+    pub(crate) blocks: Vec<BasicBlock>,
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) id: FunctionId,
+}
+
+impl Function {
+    pub(crate) fn new(
+        id: FunctionId,
+        name: &str,
+        ty: TypeId,
+        ptr_ty: TypeId,
+        ret: TypeId,
+        params: Vec<TypeId>,
+        vararg: bool,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            linkage: Linkage::External,
+            visibility: Visibility::Default,
+            dll_storage_class: DllStorageClass::Default,
+            unnamed_addr: UnnamedAddr::None,
+            dso_local: false,
+            calling_conv: CallingConv::C,
+            alignment: None,
+            section: None,
+            gc: None,
+            comdat: None,
+            personality: None,
+            attributes: AttributeList::new(),
+            metadata: Attachments::default(),
+            ty,
+            ptr_ty,
+            ret,
+            arg_names: vec![None; params.len()],
+            params,
+            vararg,
+            blocks: Vec::new(),
+            instructions: Vec::new(),
+            id,
+        }
+    }
+
+    /// The function type.
+    pub fn ty(&self) -> TypeId {
+        self.ty
+    }
+
+    pub fn return_type(&self) -> TypeId {
+        self.ret
+    }
+
+    pub fn param_types(&self) -> &[TypeId] {
+        &self.params
+    }
+
+    pub fn is_vararg(&self) -> bool {
+        self.vararg
+    }
+
+    /// Functions without a body are declarations.
+    pub fn is_declaration(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn arg(&self, index: usize) -> Value {
+        Value::local(
+            ValueRef::Argument(index as u32),
+            self.params[index],
+            self.id,
+        )
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = Value> + '_ {
+        (0..self.params.len()).map(|i| self.arg(i))
+    }
+
+    pub fn arg_name(&self, index: usize) -> Option<&str> {
+        self.arg_names[index].as_deref()
+    }
+
+    /// Names an argument or an instruction.
+    pub fn set_name(&mut self, value: Value, name: &str) {
+        assert_eq!(
+            value.function,
+            Some(self.id),
+            "only arguments and instructions of the function can be named"
+        );
+        let name = (!name.is_empty()).then(|| name.to_string());
+
+        match value.kind {
+            ValueRef::Argument(i) => self.arg_names[i as usize] = name,
+            ValueRef::Instruction(id) => {
+                let inst = &mut self.instructions[id.index()];
+                assert!(
+                    inst.ty.is_some(),
+                    "instructions without a value can't be named"
+                );
+                inst.name = name;
+            }
+            _ => panic!("only local values can be named"),
+        }
+    }
+
+    /// Attaches the [`DISubprogram`](super::debug_info::DISubprogram) describing the
+    /// function.
+    pub fn set_subprogram(&mut self, subprogram: MetadataId) {
+        self.metadata.set(MetadataKind::DBG, subprogram);
+    }
+
+    pub fn subprogram(&self) -> Option<MetadataId> {
+        self.metadata.get(MetadataKind::DBG)
+    }
+
+    /// Appends a new basic block, an empty name leaves it unnamed.
+    pub fn append_block(&mut self, name: &str) -> BasicBlockId {
+        let id = BasicBlockId(self.blocks.len() as u32);
+        self.blocks.push(BasicBlock {
+            name: (!name.is_empty()).then(|| name.to_string()),
+            instructions: Vec::new(),
+        });
+        id
+    }
+
+    pub fn block(&self, id: BasicBlockId) -> &BasicBlock {
+        &self.blocks[id.index()]
+    }
+
+    pub fn block_mut(&mut self, id: BasicBlockId) -> &mut BasicBlock {
+        &mut self.blocks[id.index()]
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = (BasicBlockId, &BasicBlock)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (BasicBlockId(i as u32), x))
+    }
+
+    pub fn instruction(&self, id: InstId) -> &Instruction {
+        &self.instructions[id.index()]
+    }
+
+    pub fn instruction_mut(&mut self, id: InstId) -> &mut Instruction {
+        &mut self.instructions[id.index()]
+    }
+
+    /// The value produced by the instruction, if any.
+    pub fn instruction_value(&self, id: InstId) -> Option<Value> {
+        self.instruction(id)
+            .ty
+            .map(|ty| Value::local(ValueRef::Instruction(id), ty, self.id))
+    }
+
+    /// Inserts an instruction in the block at the given position.
+    pub fn insert_instruction(
+        &mut self,
+        block: BasicBlockId,
+        index: usize,
+        inst: Instruction,
+    ) -> InstId {
+        let id = InstId(self.instructions.len() as u32);
+        self.instructions.push(inst);
+        self.blocks[block.index()].instructions.insert(index, id);
+        id
+    }
+
+    /// Appends an instruction to the end of the block.
+    pub fn push_instruction(&mut self, block: BasicBlockId, inst: Instruction) -> InstId {
+        let index = self.blocks[block.index()].instructions.len();
+        self.insert_instruction(block, index, inst)
+    }
+}
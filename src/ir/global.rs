@@ -0,0 +1,190 @@
+use crate::bitcode::constants::ComdatSelectionKindCode;
+
+use super::{
+    metadata::{Attachments, MetadataId, MetadataKind},
+    types::TypeId,
+    value::Value,
+};
+
+// https://llvm.org/docs/LangRef.html#linkage-types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Linkage {
+    #[default]
+    External,
+    AvailableExternally,
+    LinkOnceAny,
+    LinkOnceOdr,
+    WeakAny,
+    WeakOdr,
+    Appending,
+    Internal,
+    Private,
+    ExternalWeak,
+    Common,
+}
+
+impl Linkage {
+    /// The value used in bitcode records.
+    pub fn code(self) -> u64 {
+        match self {
+            Linkage::External => 0,
+            Linkage::AvailableExternally => 12,
+            Linkage::LinkOnceAny => 18,
+            Linkage::LinkOnceOdr => 19,
+            Linkage::WeakAny => 16,
+            Linkage::WeakOdr => 17,
+            Linkage::Appending => 2,
+            Linkage::Internal => 3,
+            Linkage::Private => 9,
+            Linkage::ExternalWeak => 7,
+            Linkage::Common => 8,
+        }
+    }
+
+    pub fn is_local(self) -> bool {
+        matches!(self, Linkage::Internal | Linkage::Private)
+    }
+}
+
+// https://llvm.org/docs/LangRef.html#visibility-styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Visibility {
+    #[default]
+    Default = 0,
+    Hidden = 1,
+    Protected = 2,
+}
+
+// https://llvm.org/docs/LangRef.html#dll-storage-classes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DllStorageClass {
+    #[default]
+    Default = 0,
+    Import = 1,
+    Export = 2,
+}
+
+// https://llvm.org/docs/LangRef.html#thread-local-storage-models
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ThreadLocalMode {
+    #[default]
+    NotThreadLocal = 0,
+    GeneralDynamic = 1,
+    LocalDynamic = 2,
+    InitialExec = 3,
+    LocalExec = 4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UnnamedAddr {
+    #[default]
+    None = 0,
+    /// `unnamed_addr`
+    Global = 1,
+    /// `local_unnamed_addr`
+    Local = 2,
+}
+
+/// Encodes an alignment in bytes as log2 + 1, 0 meaning no alignment.
+pub(crate) fn encode_alignment(align: Option<u64>) -> u64 {
+    match align {
+        Some(align) => {
+            assert!(align.is_power_of_two(), "alignment must be a power of 2");
+            align.trailing_zeros() as u64 + 1
+        }
+        None => 0,
+    }
+}
+
+/// Handle to a comdat of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComdatId(pub(crate) u32);
+
+impl ComdatId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A group of globals the linker keeps or discards together, `selection_kind`
+/// decides which copy is kept when several object files define it.
+// https://llvm.org/docs/LangRef.html#comdats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comdat {
+    pub name: String,
+    pub selection_kind: ComdatSelectionKindCode,
+}
+
+// https://llvm.org/docs/LangRef.html#global-variables
+#[derive(Debug, Clone)]
+pub struct GlobalVariable {
+    pub name: String,
+    pub value_type: TypeId,
+    pub constant: bool,
+    /// Declarations have no initializer.
+    pub initializer: Option<Value>,
+    pub linkage: Linkage,
+    pub alignment: Option<u64>,
+    pub section: Option<String>,
+    pub visibility: Visibility,
+    pub thread_local: ThreadLocalMode,
+    pub unnamed_addr: UnnamedAddr,
+    pub externally_initialized: bool,
+    pub dll_storage_class: DllStorageClass,
+    pub dso_local: bool,
+    pub comdat: Option<ComdatId>,
+    pub metadata: Attachments,
+    pub(crate) address_space: u32,
+    // The pointer type of the global itself.
+    pub(crate) ptr_ty: TypeId,
+}
+
+impl GlobalVariable {
+    pub fn address_space(&self) -> u32 {
+        self.address_space
+    }
+
+    pub fn is_declaration(&self) -> bool {
+        self.initializer.is_none()
+    }
+
+    /// Attaches the
+    /// [`DIGlobalVariableExpression`](super::debug_info::DIGlobalVariableExpression)
+    /// describing the global.
+    pub fn set_debug_info(&mut self, expression: MetadataId) {
+        self.metadata.set(MetadataKind::DBG, expression);
+    }
+}
+
+// https://llvm.org/docs/LangRef.html#aliases
+#[derive(Debug, Clone)]
+pub struct GlobalAlias {
+    pub name: String,
+    pub value_type: TypeId,
+    /// A global value or a constant expression of one.
+    pub aliasee: Value,
+    pub linkage: Linkage,
+    pub visibility: Visibility,
+    pub dll_storage_class: DllStorageClass,
+    pub thread_local: ThreadLocalMode,
+    pub unnamed_addr: UnnamedAddr,
+    pub dso_local: bool,
+    pub partition: Option<String>,
+    pub(crate) ptr_ty: TypeId,
+}
+
+/// A function resolved at load time by calling `resolver`, which returns the
+/// address of the implementation.
+// https://llvm.org/docs/LangRef.html#ifuncs
+#[derive(Debug, Clone)]
+pub struct GlobalIFunc {
+    pub name: String,
+    /// The function type of the ifunc.
+    pub value_type: TypeId,
+    pub resolver: Value,
+    pub linkage: Linkage,
+    pub visibility: Visibility,
+    pub dso_local: bool,
+    pub partition: Option<String>,
+    pub(crate) ptr_ty: TypeId,
+}
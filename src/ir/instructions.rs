@@ -0,0 +1,630 @@
+use crate::bitcode::constants::{
+    AtomicOrderingCode, BinaryOpCode, CastCodes, OverFlowingBinOpOptionalFlag,
+    PossiblyDisjointInstOptionalFlag, PossiblyExactOperatorOptionalFlag,
+    PossiblyNonNegInstOptionalFlag, RMWOperation, TruncInstOptionalFlag, UnaryOpCodes,
+};
+
+use super::{
+    attributes::AttributeList,
+    function::CallingConv,
+    metadata::{Attachments, MetadataId},
+    module::Module,
+    types::TypeId,
+    value::{BasicBlockId, FunctionId, Value},
+};
+
+// https://llvm.org/docs/LangRef.html#icmp-instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntPredicate {
+    Eq = 32,
+    Ne = 33,
+    Ugt = 34,
+    Uge = 35,
+    Ult = 36,
+    Ule = 37,
+    Sgt = 38,
+    Sge = 39,
+    Slt = 40,
+    Sle = 41,
+}
+
+// https://llvm.org/docs/LangRef.html#fcmp-instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloatPredicate {
+    False = 0,
+    Oeq = 1,
+    Ogt = 2,
+    Oge = 3,
+    Olt = 4,
+    Ole = 5,
+    One = 6,
+    Ord = 7,
+    Uno = 8,
+    Ueq = 9,
+    Ugt = 10,
+    Uge = 11,
+    Ult = 12,
+    Ule = 13,
+    Une = 14,
+    True = 15,
+}
+
+/// Poison generating flags of integer binary operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BinOpFlags {
+    /// add, sub, mul and shl.
+    pub nuw: bool,
+    /// add, sub, mul and shl.
+    pub nsw: bool,
+    /// udiv, sdiv, lshr and ashr.
+    pub exact: bool,
+    /// or.
+    pub disjoint: bool,
+}
+
+impl BinOpFlags {
+    pub(crate) fn encode(self, op: BinaryOpCode) -> u64 {
+        let mut flags = 0;
+
+        if self.nuw || self.nsw {
+            assert!(
+                matches!(
+                    op,
+                    BinaryOpCode::Add | BinaryOpCode::Sub | BinaryOpCode::Mul | BinaryOpCode::Shl
+                ),
+                "nuw and nsw are only valid on add, sub, mul and shl"
+            );
+            flags |= (self.nuw as u64) << OverFlowingBinOpOptionalFlag::NO_UNSIGNED_WRAP as u64;
+            flags |= (self.nsw as u64) << OverFlowingBinOpOptionalFlag::NO_SIGNED_WRAP as u64;
+        }
+
+        if self.exact {
+            assert!(
+                matches!(
+                    op,
+                    BinaryOpCode::UDiv
+                        | BinaryOpCode::SDiv
+                        | BinaryOpCode::LShr
+                        | BinaryOpCode::AShr
+                ),
+                "exact is only valid on udiv, sdiv, lshr and ashr"
+            );
+            flags |= 1 << PossiblyExactOperatorOptionalFlag::EXACT as u64;
+        }
+
+        if self.disjoint {
+            assert!(op == BinaryOpCode::Or, "disjoint is only valid on or");
+            flags |= 1 << PossiblyDisjointInstOptionalFlag::DISJOINT as u64;
+        }
+
+        flags
+    }
+}
+
+/// Poison generating flags of casts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CastFlags {
+    /// zext.
+    pub nneg: bool,
+    /// trunc.
+    pub nuw: bool,
+    /// trunc.
+    pub nsw: bool,
+}
+
+impl CastFlags {
+    pub(crate) fn encode(self, op: CastCodes) -> u64 {
+        let mut flags = 0;
+
+        if self.nneg {
+            assert!(op == CastCodes::Zext, "nneg is only valid on zext");
+            flags |= 1 << PossiblyNonNegInstOptionalFlag::NON_NEG as u64;
+        }
+
+        if self.nuw || self.nsw {
+            assert!(
+                op == CastCodes::Trunc,
+                "nuw and nsw are only valid on trunc"
+            );
+            flags |= (self.nuw as u64) << TruncInstOptionalFlag::NO_UNSIGNED_WRAP as u64;
+            flags |= (self.nsw as u64) << TruncInstOptionalFlag::NO_SIGNED_WRAP as u64;
+        }
+
+        flags
+    }
+}
+
+/// The set of threads an atomic operation synchronizes with, custom scopes are
+/// registered with [`Module::sync_scope`](super::module::Module::sync_scope).
+// https://llvm.org/docs/LangRef.html#syncscope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SyncScope(pub(crate) u32);
+
+impl SyncScope {
+    /// Only synchronizes with the same thread, `syncscope("singlethread")`.
+    pub const SINGLE_THREAD: SyncScope = SyncScope(0);
+    /// Synchronizes with every thread, the default.
+    pub const SYSTEM: SyncScope = SyncScope(1);
+}
+
+impl Default for SyncScope {
+    fn default() -> Self {
+        SyncScope::SYSTEM
+    }
+}
+
+/// The ordering and scope of an atomic load or store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Atomic {
+    pub ordering: AtomicOrderingCode,
+    pub sync_scope: SyncScope,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TailCallKind {
+    #[default]
+    None,
+    Tail,
+    MustTail,
+    NoTail,
+}
+
+/// The tag of an operand bundle, custom tags are registered with
+/// [`Module::bundle_tag`](super::module::Module::bundle_tag).
+// https://llvm.org/docs/LangRef.html#operand-bundles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BundleTag(pub(crate) u32);
+
+impl BundleTag {
+    pub const DEOPT: BundleTag = BundleTag(0);
+    pub const FUNCLET: BundleTag = BundleTag(1);
+    pub const GC_TRANSITION: BundleTag = BundleTag(2);
+    pub const CFGUARDTARGET: BundleTag = BundleTag(3);
+    pub const PREALLOCATED: BundleTag = BundleTag(4);
+    pub const GC_LIVE: BundleTag = BundleTag(5);
+    pub const CLANG_ARC_ATTACHEDCALL: BundleTag = BundleTag(6);
+    pub const PTRAUTH: BundleTag = BundleTag(7);
+    pub const KCFI: BundleTag = BundleTag(8);
+    pub const CONVERGENCECTRL: BundleTag = BundleTag(9);
+
+    /// The tags every LLVM context knows, in the order of their ids.
+    pub(crate) const FIXED: [&'static str; 10] = [
+        "deopt",
+        "funclet",
+        "gc-transition",
+        "cfguardtarget",
+        "preallocated",
+        "gc-live",
+        "clang.arc.attachedcall",
+        "ptrauth",
+        "kcfi",
+        "convergencectrl",
+    ];
+
+    /// Tags other than the fixed ones.
+    pub fn is_custom(self) -> bool {
+        self.0 as usize >= Self::FIXED.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperandBundle {
+    pub tag: BundleTag,
+    pub inputs: Vec<Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CallInst {
+    pub function_type: TypeId,
+    pub callee: Value,
+    pub args: Vec<Value>,
+    pub attributes: AttributeList,
+    pub calling_conv: CallingConv,
+    pub tail: TailCallKind,
+    /// Fast math flags.
+    pub flags: u64,
+    pub bundles: Vec<OperandBundle>,
+}
+
+impl CallInst {
+    /// A call to a function of the module, with its calling convention.
+    pub fn new(module: &Module, function: FunctionId, args: &[Value]) -> Self {
+        let callee = module.function(function);
+        let mut call = Self::indirect(callee.ty(), module.function_value(function), args);
+        call.calling_conv = callee.calling_conv;
+        call
+    }
+
+    /// A call through a pointer, `function_type` is the type of the callee.
+    pub fn indirect(function_type: TypeId, callee: Value, args: &[Value]) -> Self {
+        Self {
+            function_type,
+            callee,
+            args: args.to_vec(),
+            attributes: AttributeList::new(),
+            calling_conv: CallingConv::C,
+            tail: TailCallKind::None,
+            flags: 0,
+            bundles: Vec::new(),
+        }
+    }
+}
+
+/// A clause of a landingpad, matched against the exception being thrown.
+// https://llvm.org/docs/LangRef.html#landingpad-instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandingPadClause {
+    /// Catches exceptions of the type described by the typeinfo.
+    Catch(Value),
+    /// A constant array of the typeinfos the exception may match, the
+    /// landingpad is entered if it matches none of them.
+    Filter(Value),
+}
+
+impl LandingPadClause {
+    pub fn value(self) -> Value {
+        match self {
+            LandingPadClause::Catch(x) | LandingPadClause::Filter(x) => x,
+        }
+    }
+}
+
+/// The operation of an instruction.
+///
+/// `flags` fields hold the optimization flags as encoded in bitcode records:
+/// nuw/nsw, exact, disjoint, nneg, fast math flags or GEP no-wrap flags.
+#[derive(Debug, Clone)]
+pub enum InstructionKind {
+    Ret(Option<Value>),
+    Br(BasicBlockId),
+    CondBr {
+        cond: Value,
+        then_dest: BasicBlockId,
+        else_dest: BasicBlockId,
+    },
+    Switch {
+        value: Value,
+        default: BasicBlockId,
+        cases: Vec<(Value, BasicBlockId)>,
+    },
+    IndirectBr {
+        address: Value,
+        dests: Vec<BasicBlockId>,
+    },
+    Unreachable,
+    UnOp {
+        op: UnaryOpCodes,
+        value: Value,
+        flags: u64,
+    },
+    BinOp {
+        op: BinaryOpCode,
+        lhs: Value,
+        rhs: Value,
+        flags: u64,
+    },
+    /// The destination type is the type of the instruction.
+    Cast {
+        op: CastCodes,
+        value: Value,
+        flags: u64,
+    },
+    ICmp {
+        predicate: IntPredicate,
+        lhs: Value,
+        rhs: Value,
+    },
+    FCmp {
+        predicate: FloatPredicate,
+        lhs: Value,
+        rhs: Value,
+        flags: u64,
+    },
+    Select {
+        cond: Value,
+        then_value: Value,
+        else_value: Value,
+        flags: u64,
+    },
+    /// The type of the instruction is the element type of the vector.
+    ExtractElement {
+        vector: Value,
+        index: Value,
+    },
+    InsertElement {
+        vector: Value,
+        element: Value,
+        index: Value,
+    },
+    /// `mask` is a constant vector of `i32` indices into the concatenation of `lhs`
+    /// and `rhs`, with poison elements for poison lanes.
+    ShuffleVector {
+        lhs: Value,
+        rhs: Value,
+        mask: Value,
+    },
+    /// The type of the instruction is the type of the extracted member.
+    ExtractValue {
+        aggregate: Value,
+        indices: Vec<u32>,
+    },
+    InsertValue {
+        aggregate: Value,
+        value: Value,
+        indices: Vec<u32>,
+    },
+    Phi {
+        incoming: Vec<(Value, BasicBlockId)>,
+        flags: u64,
+    },
+    Alloca {
+        allocated_type: TypeId,
+        size: Value,
+        align: Option<u64>,
+    },
+    /// The loaded type is the type of the instruction.
+    Load {
+        ptr: Value,
+        align: Option<u64>,
+        volatile: bool,
+        atomic: Option<Atomic>,
+    },
+    Store {
+        ptr: Value,
+        value: Value,
+        align: Option<u64>,
+        volatile: bool,
+        atomic: Option<Atomic>,
+    },
+    Fence {
+        ordering: AtomicOrderingCode,
+        sync_scope: SyncScope,
+    },
+    /// The type of the instruction is `{ ty, i1 }`.
+    CmpXchg {
+        ptr: Value,
+        cmp: Value,
+        new: Value,
+        align: Option<u64>,
+        volatile: bool,
+        weak: bool,
+        success_ordering: AtomicOrderingCode,
+        failure_ordering: AtomicOrderingCode,
+        sync_scope: SyncScope,
+    },
+    AtomicRmw {
+        op: RMWOperation,
+        ptr: Value,
+        value: Value,
+        align: Option<u64>,
+        volatile: bool,
+        ordering: AtomicOrderingCode,
+        sync_scope: SyncScope,
+    },
+    GetElementPtr {
+        source_type: TypeId,
+        ptr: Value,
+        indices: Vec<Value>,
+        flags: u64,
+    },
+    Call(Box<CallInst>),
+    /// The type of the instruction is the return type of the callee.
+    Invoke {
+        call: Box<CallInst>,
+        normal_dest: BasicBlockId,
+        unwind_dest: BasicBlockId,
+    },
+    Resume(Value),
+    LandingPad {
+        cleanup: bool,
+        clauses: Vec<LandingPadClause>,
+    },
+    /// `parent_pad` is the `none` token for top level pads.
+    CatchSwitch {
+        parent_pad: Value,
+        handlers: Vec<BasicBlockId>,
+        unwind_dest: Option<BasicBlockId>,
+    },
+    CatchPad {
+        catch_switch: Value,
+        args: Vec<Value>,
+    },
+    CleanupPad {
+        parent_pad: Value,
+        args: Vec<Value>,
+    },
+    CatchRet {
+        catch_pad: Value,
+        dest: BasicBlockId,
+    },
+    CleanupRet {
+        cleanup_pad: Value,
+        unwind_dest: Option<BasicBlockId>,
+    },
+    /// The argument type is the type of the instruction.
+    VaArg {
+        list: Value,
+    },
+    Freeze(Value),
+}
+
+impl InstructionKind {
+    /// The values used by the instruction, in operand order.
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            InstructionKind::Ret(value) => value.iter().copied().collect(),
+            InstructionKind::Br(_)
+            | InstructionKind::Unreachable
+            | InstructionKind::Fence { .. } => Vec::new(),
+            InstructionKind::CondBr { cond, .. } => vec![*cond],
+            InstructionKind::Switch { value, cases, .. } => std::iter::once(*value)
+                .chain(cases.iter().map(|(x, _)| *x))
+                .collect(),
+            InstructionKind::IndirectBr { address, .. } => vec![*address],
+            InstructionKind::UnOp { value, .. }
+            | InstructionKind::Cast { value, .. }
+            | InstructionKind::Freeze(value)
+            | InstructionKind::Resume(value) => vec![*value],
+            InstructionKind::BinOp { lhs, rhs, .. }
+            | InstructionKind::ICmp { lhs, rhs, .. }
+            | InstructionKind::FCmp { lhs, rhs, .. } => vec![*lhs, *rhs],
+            InstructionKind::Select {
+                cond,
+                then_value,
+                else_value,
+                ..
+            } => vec![*cond, *then_value, *else_value],
+            InstructionKind::ExtractElement { vector, index } => vec![*vector, *index],
+            InstructionKind::InsertElement {
+                vector,
+                element,
+                index,
+            } => vec![*vector, *element, *index],
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => vec![*lhs, *rhs, *mask],
+            InstructionKind::ExtractValue { aggregate, .. } => vec![*aggregate],
+            InstructionKind::InsertValue {
+                aggregate, value, ..
+            } => vec![*aggregate, *value],
+            InstructionKind::Phi { incoming, .. } => incoming.iter().map(|(x, _)| *x).collect(),
+            InstructionKind::Alloca { size, .. } => vec![*size],
+            InstructionKind::Load { ptr, .. } => vec![*ptr],
+            InstructionKind::Store { ptr, value, .. }
+            | InstructionKind::AtomicRmw { ptr, value, .. } => vec![*ptr, *value],
+            InstructionKind::CmpXchg { ptr, cmp, new, .. } => vec![*ptr, *cmp, *new],
+            InstructionKind::GetElementPtr { ptr, indices, .. } => std::iter::once(*ptr)
+                .chain(indices.iter().copied())
+                .collect(),
+            InstructionKind::Call(call) | InstructionKind::Invoke { call, .. } => {
+                std::iter::once(call.callee)
+                    .chain(call.args.iter().copied())
+                    .chain(call.bundles.iter().flat_map(|x| x.inputs.iter().copied()))
+                    .collect()
+            }
+            InstructionKind::LandingPad { clauses, .. } => {
+                clauses.iter().map(|x| x.value()).collect()
+            }
+            InstructionKind::CatchSwitch { parent_pad, .. } => vec![*parent_pad],
+            InstructionKind::CatchPad {
+                catch_switch: pad,
+                args,
+            }
+            | InstructionKind::CleanupPad {
+                parent_pad: pad,
+                args,
+            } => std::iter::once(*pad).chain(args.iter().copied()).collect(),
+            InstructionKind::CatchRet { catch_pad: pad, .. }
+            | InstructionKind::CleanupRet {
+                cleanup_pad: pad, ..
+            } => vec![*pad],
+            InstructionKind::VaArg { list } => vec![*list],
+        }
+    }
+
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            InstructionKind::Ret(_)
+                | InstructionKind::Br(_)
+                | InstructionKind::CondBr { .. }
+                | InstructionKind::Switch { .. }
+                | InstructionKind::IndirectBr { .. }
+                | InstructionKind::Unreachable
+                | InstructionKind::Invoke { .. }
+                | InstructionKind::Resume(_)
+                | InstructionKind::CatchSwitch { .. }
+                | InstructionKind::CatchRet { .. }
+                | InstructionKind::CleanupRet { .. }
+        )
+    }
+}
+
+/// What a variable record tells about the variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugRecordKind {
+    /// The variable has the value of the location.
+    Value,
+    /// The location is the address of the variable, for its whole scope.
+    Declare,
+    /// Like `Value`, linked to the stores with the same
+    /// [`MetadataKind::DI_ASSIGN_ID`](super::metadata::MetadataKind::DI_ASSIGN_ID)
+    /// writing the variable at `address`.
+    Assign {
+        assign_id: MetadataId,
+        address: MetadataId,
+        address_expression: MetadataId,
+    },
+}
+
+/// Debug info placed before an instruction, replacing the calls to the
+/// `llvm.dbg.*` intrinsics. `debug_loc` is a
+/// [`DILocation`](super::debug_info::DILocation).
+// https://llvm.org/docs/LangRef.html#debug-records
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugRecord {
+    Variable {
+        kind: DebugRecordKind,
+        /// A value wrapped as metadata or a `DIArgList`.
+        location: MetadataId,
+        variable: MetadataId,
+        expression: MetadataId,
+        debug_loc: MetadataId,
+    },
+    Label {
+        label: MetadataId,
+        debug_loc: MetadataId,
+    },
+}
+
+impl DebugRecord {
+    /// The metadata used by the record.
+    pub fn metadata(&self) -> Vec<MetadataId> {
+        match self {
+            DebugRecord::Variable {
+                kind,
+                location,
+                variable,
+                expression,
+                debug_loc,
+            } => {
+                let mut metadata = vec![*debug_loc, *variable, *expression, *location];
+                if let DebugRecordKind::Assign {
+                    assign_id,
+                    address,
+                    address_expression,
+                } = kind
+                {
+                    metadata.extend([*assign_id, *address_expression, *address]);
+                }
+                metadata
+            }
+            DebugRecord::Label { label, debug_loc } => vec![*debug_loc, *label],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub kind: InstructionKind,
+    /// The result type, `None` if the instruction produces no value.
+    pub ty: Option<TypeId>,
+    pub name: Option<String>,
+    pub metadata: Attachments,
+    /// A [`DILocation`](super::debug_info::DILocation), written apart from the
+    /// other attachments.
+    pub debug_loc: Option<MetadataId>,
+    /// The debug records placed before the instruction.
+    pub debug_records: Vec<DebugRecord>,
+}
+
+impl Instruction {
+    pub fn new(kind: InstructionKind, ty: Option<TypeId>) -> Self {
+        Self {
+            kind,
+            ty,
+            name: None,
+            metadata: Attachments::default(),
+            debug_loc: None,
+            debug_records: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use super::{
+    metadata::{MDNode, Metadata, MetadataId},
+    module::Module,
+    types::TypeId,
+};
+
+/// A transformation hint of a loop, the operands of its `!llvm.loop` ID.
+// https://llvm.org/docs/TransformMetadata.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LoopHint {
+    /// `llvm.loop.unroll.count`, unroll by that factor.
+    UnrollCount(u32),
+    /// `llvm.loop.vectorize.enable`
+    VectorizeEnable(bool),
+    /// `llvm.loop.vectorize.width`, the number of lanes, 1 disables it.
+    VectorizeWidth(u32),
+    /// `llvm.loop.mustprogress`, the loop terminates or has side effects.
+    MustProgress,
+    /// `llvm.loop.distribute.enable`
+    DistributeEnable(bool),
+    /// `llvm.loop.parallel_accesses`, the accesses of these groups don't depend
+    /// on each other across iterations.
+    ParallelAccesses(Vec<MetadataId>),
+}
+
+/// Creates the nodes of the optimization hints, like LLVM's `MDBuilder`. Attach
+/// them with [`Builder::set_metadata`](super::builder::Builder::set_metadata) or
+/// the `metadata` of functions.
+// https://llvm.org/docs/LangRef.html#metadata
+pub struct MDBuilder<'m> {
+    module: &'m mut Module,
+}
+
+impl<'m> MDBuilder<'m> {
+    pub fn new(module: &'m mut Module) -> Self {
+        Self { module }
+    }
+
+    pub fn module(&mut self) -> &mut Module {
+        self.module
+    }
+
+    fn int(&mut self, ty: TypeId, value: i128) -> MetadataId {
+        let value = self.module.const_int(ty, value);
+        self.module.md_value(value)
+    }
+
+    fn i64(&mut self, value: u64) -> MetadataId {
+        let i64 = self.module.types.i64();
+        self.int(i64, value as i128)
+    }
+
+    fn check_node(&self, id: MetadataId, what: &str) {
+        assert!(
+            matches!(self.module.metadata.get(id), Metadata::Node(_)),
+            "expected {what}"
+        );
+    }
+
+    // `distinct !{!self, operands...}`, so it's never merged with another module's.
+    fn self_referencing_node(&mut self, operands: &[Option<MetadataId>]) -> MetadataId {
+        let mut operands = [&[None], operands].concat();
+        let id = self.module.md_distinct_node(&operands);
+        operands[0] = Some(id);
+        self.module.metadata.replace(
+            id,
+            Metadata::Node(MDNode {
+                operands,
+                distinct: true,
+            }),
+        );
+        id
+    }
+
+    /// `!{}`, the node of `!nonnull`, `!invariant.load`, `!unpredictable` and
+    /// the other hints without operands.
+    pub fn create_empty(&mut self) -> MetadataId {
+        self.module.md_node(&[])
+    }
+
+    /// `!{i32 1}` for `!nontemporal`.
+    pub fn create_nontemporal(&mut self) -> MetadataId {
+        let i32 = self.module.types.i32();
+        let one = self.int(i32, 1);
+        self.module.md_node(&[Some(one)])
+    }
+
+    /// `!{i64 bytes}` for `!align`, `!dereferenceable` and
+    /// `!dereferenceable_or_null`.
+    pub fn create_bytes(&mut self, bytes: u64) -> MetadataId {
+        let bytes = self.i64(bytes);
+        self.module.md_node(&[Some(bytes)])
+    }
+
+    /// `!range` of the loaded or returned integers, the values in `[lower,
+    /// upper)` of type `ty`. It wraps when `upper` is lower.
+    // https://llvm.org/docs/LangRef.html#range-metadata
+    pub fn create_range(&mut self, ty: TypeId, lower: i128, upper: i128) -> MetadataId {
+        assert!(
+            self.module.types.get(ty).is_integer(),
+            "ranges are of integers"
+        );
+        // Compared once truncated, 0 and 256 are the same i8.
+        let lower = self.module.const_int(ty, lower);
+        let upper = self.module.const_int(ty, upper);
+        assert_ne!(lower, upper, "empty and full ranges are invalid");
+        let lower = self.module.md_value(lower);
+        let upper = self.module.md_value(upper);
+        self.module.md_node(&[Some(lower), Some(upper)])
+    }
+
+    /// `!prof` of a conditional branch, switch or select, a weight per
+    /// successor.
+    // https://llvm.org/docs/BranchWeightMetadata.html
+    pub fn create_branch_weights(&mut self, weights: &[u32]) -> MetadataId {
+        assert!(weights.len() >= 2, "branches have at least two successors");
+        let i32 = self.module.types.i32();
+        let mut operands = vec![Some(self.module.md_string("branch_weights"))];
+        for weight in weights {
+            operands.push(Some(self.int(i32, *weight as i128)));
+        }
+        self.module.md_node(&operands)
+    }
+
+    /// `!prof` of a function, how many times it's called. `imports` are the
+    /// GUIDs of the functions imported by ThinLTO when it's hot, as a set.
+    pub fn create_function_entry_count(
+        &mut self,
+        count: u64,
+        synthetic: bool,
+        imports: &[u64],
+    ) -> MetadataId {
+        let name = if synthetic {
+            "synthetic_function_entry_count"
+        } else {
+            "function_entry_count"
+        };
+        let mut operands = vec![Some(self.module.md_string(name))];
+        operands.push(Some(self.i64(count)));
+        // Sorted so the node doesn't depend on the order of the set.
+        // https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/lib/IR/MDBuilder.cpp
+        let mut imports = imports.to_vec();
+        imports.sort_unstable();
+        imports.dedup();
+        for guid in imports {
+            operands.push(Some(self.i64(guid)));
+        }
+        self.module.md_node(&operands)
+    }
+
+    /// The root of a TBAA type tree, types of different trees may alias.
+    // https://llvm.org/docs/LangRef.html#tbaa-metadata
+    pub fn create_tbaa_root(&mut self, name: &str) -> MetadataId {
+        let name = self.module.md_string(name);
+        self.module.md_node(&[Some(name)])
+    }
+
+    /// A scalar type, it aliases its ancestors but not its siblings, like `int`
+    /// and `float` both children of `omnipotent char`.
+    pub fn create_tbaa_scalar_type_node(&mut self, name: &str, parent: MetadataId) -> MetadataId {
+        self.check_node(parent, "a TBAA type");
+        let name = self.module.md_string(name);
+        let offset = self.i64(0);
+        self.module
+            .md_node(&[Some(name), Some(parent), Some(offset)])
+    }
+
+    /// A struct type, the type and byte offset of each field.
+    pub fn create_tbaa_struct_type_node(
+        &mut self,
+        name: &str,
+        fields: &[(MetadataId, u64)],
+    ) -> MetadataId {
+        let mut operands = vec![Some(self.module.md_string(name))];
+        for (ty, offset) in fields {
+            self.check_node(*ty, "a TBAA type");
+            operands.push(Some(*ty));
+            operands.push(Some(self.i64(*offset)));
+        }
+        self.module.md_node(&operands)
+    }
+
+    /// The `!tbaa` of an access of the scalar `access_type`, at `offset` in
+    /// `base_type`. Both are the same scalar for accesses outside of structs.
+    /// Constant memory is never modified.
+    pub fn create_tbaa_struct_tag_node(
+        &mut self,
+        base_type: MetadataId,
+        access_type: MetadataId,
+        offset: u64,
+        constant: bool,
+    ) -> MetadataId {
+        self.check_node(base_type, "a TBAA type");
+        self.check_node(access_type, "a TBAA type");
+        let mut operands = vec![Some(base_type), Some(access_type), Some(self.i64(offset))];
+        if constant {
+            operands.push(Some(self.i64(1)));
+        }
+        self.module.md_node(&operands)
+    }
+
+    /// A domain of alias scopes, usually one per inlined function.
+    // https://llvm.org/docs/LangRef.html#noalias-and-alias-scope-metadata
+    pub fn create_alias_scope_domain(&mut self, name: &str) -> MetadataId {
+        let mut operands = Vec::new();
+        if !name.is_empty() {
+            operands.push(Some(self.module.md_string(name)));
+        }
+        self.self_referencing_node(&operands)
+    }
+
+    /// A scope of `domain`, like a `noalias` parameter.
+    pub fn create_alias_scope(&mut self, name: &str, domain: MetadataId) -> MetadataId {
+        self.check_node(domain, "an alias scope domain");
+        let mut operands = vec![Some(domain)];
+        if !name.is_empty() {
+            operands.push(Some(self.module.md_string(name)));
+        }
+        self.self_referencing_node(&operands)
+    }
+
+    /// A group of memory accesses, attach it as `!llvm.access.group` to the
+    /// accesses and list it in [`LoopHint::ParallelAccesses`].
+    pub fn create_access_group(&mut self) -> MetadataId {
+        self.module.md_distinct_node(&[])
+    }
+
+    /// The `!llvm.loop` ID of a loop, attach it to its latch branches with
+    /// [`Builder::set_loop`](super::builder::Builder::set_loop).
+    pub fn create_loop_id(&mut self, hints: &[LoopHint]) -> MetadataId {
+        let i1 = self.module.types.i1();
+        let i32 = self.module.types.i32();
+        let mut operands = Vec::new();
+        for hint in hints {
+            let (name, value) = match hint {
+                LoopHint::UnrollCount(count) => (
+                    "llvm.loop.unroll.count",
+                    vec![self.int(i32, *count as i128)],
+                ),
+                LoopHint::VectorizeEnable(enable) => (
+                    "llvm.loop.vectorize.enable",
+                    vec![self.int(i1, *enable as i128)],
+                ),
+                LoopHint::VectorizeWidth(width) => (
+                    "llvm.loop.vectorize.width",
+                    vec![self.int(i32, *width as i128)],
+                ),
+                LoopHint::MustProgress => ("llvm.loop.mustprogress", vec![]),
+                LoopHint::DistributeEnable(enable) => (
+                    "llvm.loop.distribute.enable",
+                    vec![self.int(i1, *enable as i128)],
+                ),
+                LoopHint::ParallelAccesses(groups) => {
+                    for group in groups {
+                        assert!(
+                            matches!(
+                                self.module.metadata.get(*group),
+                                Metadata::Node(node) if node.distinct && node.operands.is_empty()
+                            ),
+                            "expected an access group"
+                        );
+                    }
+                    ("llvm.loop.parallel_accesses", groups.clone())
+                }
+            };
+            let mut hint = vec![Some(self.module.md_string(name))];
+            hint.extend(value.into_iter().map(Some));
+            operands.push(Some(self.module.md_node(&hint)));
+        }
+        self.self_referencing_node(&operands)
+    }
+
+    /// The `!alias.scope` of the accesses made through the scopes, or the
+    /// `!noalias` of those not aliasing them.
+    pub fn create_alias_scope_list(&mut self, scopes: &[MetadataId]) -> MetadataId {
+        for scope in scopes {
+            self.check_node(*scope, "an alias scope");
+        }
+        let operands: Vec<_> = scopes.iter().copied().map(Some).collect();
+        self.module.md_node(&operands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::{BlockId, MetadataCode},
+        bitstream::reader::read,
+        ir::{
+            builder::Builder, instructions::IntPredicate, metadata::MetadataKind, module::Module,
+        },
+    };
+
+    use super::{LoopHint, MDBuilder};
+
+    // define i32 @f(ptr %p, ptr %q, i1 %c), with both pointers as
+    // `struct S { int a; float b; }` and hints on each access.
+    #[test]
+    fn build_optimization_hints() {
+        let mut module = Module::new("test");
+        let i1 = module.types.i1();
+        let i32 = module.types.i32();
+        let ptr = module.types.ptr();
+        let fn_ty = module.types.function(i32, &[ptr, ptr, i1], false);
+        let f = module.add_function("f", fn_ty);
+        let [p, q, c] = [0, 1, 2].map(|x| module.function(f).arg(x));
+        let one = module.const_int(i32, 1);
+
+        let mut md = MDBuilder::new(&mut module);
+        let root = md.create_tbaa_root("Simple C/C++ TBAA");
+        let char = md.create_tbaa_scalar_type_node("omnipotent char", root);
+        let int = md.create_tbaa_scalar_type_node("int", char);
+        let float = md.create_tbaa_scalar_type_node("float", char);
+        let s = md.create_tbaa_struct_type_node("S", &[(int, 0), (float, 4)]);
+        let s_a = md.create_tbaa_struct_tag_node(s, int, 0, false);
+        let any_ptr = md.create_tbaa_scalar_type_node("any pointer", char);
+        let ptr_tag = md.create_tbaa_struct_tag_node(any_ptr, any_ptr, 0, true);
+        let domain = md.create_alias_scope_domain("f");
+        let scope = md.create_alias_scope("f: %p", domain);
+        let scopes = md.create_alias_scope_list(&[scope]);
+        let range = md.create_range(i32, 0, 10);
+        let empty = md.create_empty();
+        let nontemporal = md.create_nontemporal();
+        let bytes = md.create_bytes(8);
+        let weights = md.create_branch_weights(&[1, 2000]);
+        let entry_count = md.create_function_entry_count(100, false, &[]);
+        module
+            .function_mut(f)
+            .metadata
+            .set(MetadataKind::PROF, entry_count);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        let then = builder.append_block("then");
+        let exit = builder.append_block("exit");
+        builder.position_at_end(entry);
+        let r = builder.load(ptr, q, "r");
+        let r_inst = r.instruction().unwrap();
+        builder.set_metadata(r_inst, MetadataKind::TBAA, ptr_tag);
+        builder.set_metadata(r_inst, MetadataKind::NONNULL, empty);
+        builder.set_metadata(r_inst, MetadataKind::ALIGN, bytes);
+        builder.set_metadata(r_inst, MetadataKind::DEREFERENCEABLE, bytes);
+        builder.set_metadata(r_inst, MetadataKind::INVARIANT_LOAD, empty);
+        let store = builder.store(one, p);
+        builder.set_metadata(store, MetadataKind::TBAA, s_a);
+        builder.set_metadata(store, MetadataKind::ALIAS_SCOPE, scopes);
+        builder.set_metadata(store, MetadataKind::NONTEMPORAL, nontemporal);
+        let br = builder.cond_br(c, then, exit);
+        builder.set_metadata(br, MetadataKind::PROF, weights);
+        builder.set_metadata(br, MetadataKind::UNPREDICTABLE, empty);
+
+        builder.position_at_end(then);
+        let x = builder.load(i32, r, "x");
+        let x_inst = x.instruction().unwrap();
+        builder.set_metadata(x_inst, MetadataKind::TBAA, s_a);
+        builder.set_metadata(x_inst, MetadataKind::NOALIAS, scopes);
+        builder.set_metadata(x_inst, MetadataKind::RANGE, range);
+        builder.ret(x);
+
+        builder.position_at_end(exit);
+        builder.ret(one);
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let metadata = module_block.block(BlockId::Metadata as u32);
+        let strings = metadata
+            .record(MetadataCode::STRINGS as u32)
+            .metadata_strings();
+        assert_eq!(
+            strings,
+            [
+                "function_entry_count",
+                "any pointer",
+                "omnipotent char",
+                "Simple C/C++ TBAA",
+                "S",
+                "int",
+                "float",
+                "f",
+                "f: %p",
+                "branch_weights"
+            ]
+        );
+
+        // The values are the metadata 10 to 18: i64 100, 0, 1, 8 and 4, then
+        // i32 1, 2000, 0 and 10. The operands are shifted by one.
+        let values = metadata.ops(MetadataCode::VALUE as u32);
+        assert_eq!(values.len(), 9);
+        // The domain 19 and the scope 20 refer to themselves.
+        let distinct = metadata.ops(MetadataCode::DISTINCT_NODE as u32);
+        assert_eq!(distinct, [vec![20, 8], vec![21, 20, 9]]);
+        // The entry count 21, the TBAA root 22, omnipotent char 23, any
+        // pointer 24 and its tag 25, the empty node 26, the bytes 27, int 28,
+        // float 29, S 30 and its tag 31, the scopes 32, nontemporal 33, the
+        // weights 34 and the range 35.
+        let nodes = metadata.ops(MetadataCode::NODE as u32);
+        assert_eq!(
+            nodes,
+            [
+                vec![1, 11],
+                vec![4],
+                vec![3, 23, 12],
+                vec![2, 24, 12],
+                vec![25, 25, 12, 13],
+                vec![],
+                vec![14],
+                vec![6, 24, 12],
+                vec![7, 24, 12],
+                vec![5, 29, 12, 30, 15],
+                vec![31, 29, 12],
+                vec![21],
+                vec![16],
+                vec![10, 16, 17],
+                vec![18, 19],
+            ]
+        );
+
+        // [kind, node] for f and [instruction, n x [kind, node]] for r, the
+        // store, the branch and x, sorted by kind.
+        let attachments = module_block
+            .block(BlockId::Function as u32)
+            .block(BlockId::MetadataAttachment as u32)
+            .ops(MetadataCode::ATTACHMENT as u32);
+        let kind = |x: MetadataKind| x.0 as u64;
+        let (prof, tbaa) = (kind(MetadataKind::PROF), kind(MetadataKind::TBAA));
+        assert_eq!(
+            attachments,
+            [
+                vec![prof, 21],
+                vec![
+                    0,
+                    tbaa,
+                    25,
+                    kind(MetadataKind::INVARIANT_LOAD),
+                    26,
+                    kind(MetadataKind::NONNULL),
+                    26,
+                    kind(MetadataKind::DEREFERENCEABLE),
+                    27,
+                    kind(MetadataKind::ALIGN),
+                    27
+                ],
+                vec![
+                    1,
+                    tbaa,
+                    31,
+                    kind(MetadataKind::ALIAS_SCOPE),
+                    32,
+                    kind(MetadataKind::NONTEMPORAL),
+                    33
+                ],
+                vec![2, prof, 34, kind(MetadataKind::UNPREDICTABLE), 26],
+                vec![
+                    3,
+                    tbaa,
+                    31,
+                    kind(MetadataKind::RANGE),
+                    35,
+                    kind(MetadataKind::NOALIAS),
+                    32
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "empty and full ranges are invalid")]
+    fn truncated_full_range() {
+        let mut module = Module::new("test");
+        let i8 = module.types.i8();
+        MDBuilder::new(&mut module).create_range(i8, 0, 256);
+    }
+
+    #[test]
+    fn sort_entry_count_imports() {
+        let mut module = Module::new("test");
+        let mut md = MDBuilder::new(&mut module);
+        let sorted = md.create_function_entry_count(100, true, &[1, 2, 3]);
+        let unsorted = md.create_function_entry_count(100, true, &[3, 1, 2, 1]);
+        assert_eq!(unsorted, sorted);
+    }
+
+    // for (i = 0; i != n; i++) a[i] = 0;
+    #[test]
+    fn build_loop_metadata() {
+        let mut module = Module::new("test");
+        let void = module.types.void();
+        let i64 = module.types.i64();
+        let i32 = module.types.i32();
+        let ptr = module.types.ptr();
+        let fn_ty = module.types.function(void, &[ptr, i64], false);
+        let f = module.add_function("f", fn_ty);
+        let [a, n] = [0, 1].map(|x| module.function(f).arg(x));
+        let [zero, one] = [0, 1].map(|x| module.const_int(i64, x));
+        let zero_i32 = module.const_int(i32, 0);
+
+        let mut md = MDBuilder::new(&mut module);
+        let group = md.create_access_group();
+        let loop_id = md.create_loop_id(&[
+            LoopHint::MustProgress,
+            LoopHint::UnrollCount(4),
+            LoopHint::VectorizeEnable(true),
+            LoopHint::VectorizeWidth(8),
+            LoopHint::DistributeEnable(false),
+            LoopHint::ParallelAccesses(vec![group]),
+        ]);
+
+        let mut builder = Builder::new(&mut module, f);
+        let entry = builder.append_block("entry");
+        let body = builder.append_block("body");
+        let exit = builder.append_block("exit");
+        builder.position_at_end(entry);
+        builder.br(body);
+
+        builder.position_at_end(body);
+        let i = builder.phi(i64, "i");
+        let p = builder.gep(i32, a, &[i], &[], "p");
+        let store = builder.store(zero_i32, p);
+        builder.set_metadata(store, MetadataKind::ACCESS_GROUP, group);
+        let next = builder.add(i, one, "next");
+        builder.add_incoming(i, &[(zero, entry), (next, body)]);
+        let done = builder.icmp(IntPredicate::Eq, next, n, "done");
+        let latch = builder.cond_br(done, exit, body);
+        builder.set_loop(latch, loop_id);
+
+        builder.position_at_end(exit);
+        builder.ret_void();
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        let metadata = module_block.block(BlockId::Metadata as u32);
+        let strings = metadata
+            .record(MetadataCode::STRINGS as u32)
+            .metadata_strings();
+        assert_eq!(
+            strings,
+            [
+                "llvm.loop.mustprogress",
+                "llvm.loop.unroll.count",
+                "llvm.loop.vectorize.enable",
+                "llvm.loop.vectorize.width",
+                "llvm.loop.distribute.enable",
+                "llvm.loop.parallel_accesses"
+            ]
+        );
+
+        // The values 6 to 9 are i32 4, i1 true, i32 8 and i1 false. The
+        // operands are shifted by one.
+        let values = metadata.ops(MetadataCode::VALUE as u32);
+        assert_eq!(values.len(), 4);
+        // The access group 10 is empty, the loop 11 refers to itself and then
+        // to its hints 12 to 17.
+        let distinct = metadata.ops(MetadataCode::DISTINCT_NODE as u32);
+        assert_eq!(distinct, [vec![], vec![12, 13, 14, 15, 16, 17, 18]]);
+        let hints = metadata.ops(MetadataCode::NODE as u32);
+        assert_eq!(
+            hints,
+            [
+                vec![1],
+                vec![2, 7],
+                vec![3, 8],
+                vec![4, 9],
+                vec![5, 10],
+                vec![6, 11]
+            ]
+        );
+
+        // [instruction, n x [kind, node]] for the store and the latch.
+        let attachments = module_block
+            .block(BlockId::Function as u32)
+            .block(BlockId::MetadataAttachment as u32)
+            .ops(MetadataCode::ATTACHMENT as u32);
+        let (access_group, loop_kind) = (MetadataKind::ACCESS_GROUP.0, MetadataKind::LOOP.0);
+        assert_eq!(
+            attachments,
+            [[3, access_group as u64, 10], [6, loop_kind as u64, 11]]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::ops::{BitAnd, BitOr};
+
+use crate::bitcode::constants::AttributeKindCode;
+
+use super::attributes::Attribute;
+
+/// Whether memory may be read (ref) and/or written (mod).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ModRef {
+    NoModRef = 0,
+    Ref = 1,
+    Mod = 2,
+    ModRef = 3,
+}
+
+impl ModRef {
+    fn from_bits(bits: u32) -> Self {
+        match bits & 0b11 {
+            0 => ModRef::NoModRef,
+            1 => ModRef::Ref,
+            2 => ModRef::Mod,
+            _ => ModRef::ModRef,
+        }
+    }
+}
+
+/// The memory locations tracked by [`MemoryEffects`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryLocation {
+    /// Memory pointed to by pointer arguments.
+    ArgMem = 0,
+    /// Memory not accessible by the module being compiled.
+    InaccessibleMem = 1,
+    /// Any other memory.
+    Other = 2,
+}
+
+impl MemoryLocation {
+    pub const ALL: [MemoryLocation; 3] = [
+        MemoryLocation::ArgMem,
+        MemoryLocation::InaccessibleMem,
+        MemoryLocation::Other,
+    ];
+
+    fn shift(self) -> u32 {
+        self as u32 * BITS_PER_LOCATION
+    }
+}
+
+const BITS_PER_LOCATION: u32 = 2;
+
+/// The `memory(...)` function attribute, packed as 2 mod/ref bits per location
+/// exactly like LLVM's `MemoryEffects::toIntValue`.
+///
+/// `memory(read, argmem: readwrite)` is
+/// `MemoryEffects::read_only().with(MemoryLocation::ArgMem, ModRef::ModRef)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryEffects {
+    data: u32,
+}
+
+impl MemoryEffects {
+    /// Same effect on every location.
+    pub fn new(mr: ModRef) -> Self {
+        let mut data = 0;
+        for loc in MemoryLocation::ALL {
+            data |= (mr as u32) << loc.shift();
+        }
+        Self { data }
+    }
+
+    /// Only the given location is accessed.
+    pub fn only(loc: MemoryLocation, mr: ModRef) -> Self {
+        Self {
+            data: (mr as u32) << loc.shift(),
+        }
+    }
+
+    /// May read and write any memory, the same as not having the attribute.
+    pub fn unknown() -> Self {
+        Self::new(ModRef::ModRef)
+    }
+
+    /// `memory(none)`
+    pub fn none() -> Self {
+        Self::new(ModRef::NoModRef)
+    }
+
+    /// `memory(read)`
+    pub fn read_only() -> Self {
+        Self::new(ModRef::Ref)
+    }
+
+    /// `memory(write)`
+    pub fn write_only() -> Self {
+        Self::new(ModRef::Mod)
+    }
+
+    /// `memory(argmem: <mr>)`
+    pub fn arg_mem_only(mr: ModRef) -> Self {
+        Self::only(MemoryLocation::ArgMem, mr)
+    }
+
+    /// `memory(inaccessiblemem: <mr>)`
+    pub fn inaccessible_mem_only(mr: ModRef) -> Self {
+        Self::only(MemoryLocation::InaccessibleMem, mr)
+    }
+
+    /// `memory(argmem: <mr>, inaccessiblemem: <mr>)`
+    pub fn inaccessible_or_arg_mem_only(mr: ModRef) -> Self {
+        Self::arg_mem_only(mr) | Self::inaccessible_mem_only(mr)
+    }
+
+    /// Decodes the integer value of the attribute.
+    pub fn from_int(value: u64) -> Self {
+        assert!(
+            value < 1 << (BITS_PER_LOCATION * MemoryLocation::ALL.len() as u32),
+            "invalid memory effects"
+        );
+        Self { data: value as u32 }
+    }
+
+    /// The integer value of the attribute.
+    pub fn to_int(self) -> u64 {
+        self.data as u64
+    }
+
+    /// Returns a copy with the effect on the given location replaced.
+    pub fn with(self, loc: MemoryLocation, mr: ModRef) -> Self {
+        let mask = 0b11 << loc.shift();
+        Self {
+            data: (self.data & !mask) | ((mr as u32) << loc.shift()),
+        }
+    }
+
+    /// Returns a copy with no effect on the given location.
+    pub fn without(self, loc: MemoryLocation) -> Self {
+        self.with(loc, ModRef::NoModRef)
+    }
+
+    pub fn get(self, loc: MemoryLocation) -> ModRef {
+        ModRef::from_bits(self.data >> loc.shift())
+    }
+
+    pub fn does_not_access_memory(self) -> bool {
+        self.data == 0
+    }
+
+    pub fn only_reads_memory(self) -> bool {
+        MemoryLocation::ALL
+            .iter()
+            .all(|loc| (self.get(*loc) as u32) & (ModRef::Mod as u32) == 0)
+    }
+
+    pub fn only_writes_memory(self) -> bool {
+        MemoryLocation::ALL
+            .iter()
+            .all(|loc| (self.get(*loc) as u32) & (ModRef::Ref as u32) == 0)
+    }
+}
+
+impl BitOr for MemoryEffects {
+    type Output = Self;
+
+    /// Union of the effects.
+    fn bitor(self, rhs: Self) -> Self {
+        Self {
+            data: self.data | rhs.data,
+        }
+    }
+}
+
+impl BitAnd for MemoryEffects {
+    type Output = Self;
+
+    /// Intersection of the effects.
+    fn bitand(self, rhs: Self) -> Self {
+        Self {
+            data: self.data & rhs.data,
+        }
+    }
+}
+
+impl From<MemoryEffects> for Attribute {
+    fn from(effects: MemoryEffects) -> Self {
+        Attribute::Int(AttributeKindCode::MEMORY, effects.to_int())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bitcode::constants::AttributeKindCode, ir::attributes::Attribute};
+
+    use super::{MemoryEffects, MemoryLocation, ModRef};
+
+    #[test]
+    fn encoding() {
+        assert_eq!(MemoryEffects::none().to_int(), 0);
+        assert_eq!(MemoryEffects::unknown().to_int(), 63);
+        assert_eq!(MemoryEffects::read_only().to_int(), 21);
+        assert_eq!(MemoryEffects::write_only().to_int(), 42);
+        assert_eq!(MemoryEffects::arg_mem_only(ModRef::ModRef).to_int(), 3);
+        assert_eq!(MemoryEffects::arg_mem_only(ModRef::Ref).to_int(), 1);
+        assert_eq!(
+            MemoryEffects::inaccessible_mem_only(ModRef::ModRef).to_int(),
+            12
+        );
+        assert_eq!(
+            MemoryEffects::inaccessible_or_arg_mem_only(ModRef::Mod).to_int(),
+            10
+        );
+        // memory(write, argmem: none)
+        assert_eq!(
+            MemoryEffects::write_only()
+                .without(MemoryLocation::ArgMem)
+                .to_int(),
+            40
+        );
+    }
+
+    #[test]
+    fn queries() {
+        let effects = MemoryEffects::read_only().with(MemoryLocation::Other, ModRef::NoModRef);
+        assert_eq!(effects.get(MemoryLocation::ArgMem), ModRef::Ref);
+        assert_eq!(effects.get(MemoryLocation::Other), ModRef::NoModRef);
+        assert!(effects.only_reads_memory());
+        assert!(!effects.only_writes_memory());
+        assert!(MemoryEffects::none().does_not_access_memory());
+        assert_eq!(MemoryEffects::from_int(effects.to_int()), effects);
+        assert_eq!(
+            MemoryEffects::read_only() & MemoryEffects::arg_mem_only(ModRef::ModRef),
+            MemoryEffects::arg_mem_only(ModRef::Ref)
+        );
+    }
+
+    #[test]
+    fn as_attribute() {
+        assert_eq!(
+            Attribute::from(MemoryEffects::read_only()),
+            Attribute::Int(AttributeKindCode::MEMORY, 21)
+        );
+    }
+}
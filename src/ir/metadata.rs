@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+
+use super::{
+    debug_info::{
+        DIArgList, DIBasicType, DICommonBlock, DICompileUnit, DICompositeType, DIDerivedType,
+        DIEnumerator, DIExpression, DIFile, DIGenericSubrange, DIGlobalVariable,
+        DIGlobalVariableExpression, DIImportedEntity, DILabel, DILexicalBlock, DILexicalBlockFile,
+        DILocalVariable, DILocation, DIMacro, DIMacroFile, DIModule, DINamespace, DIObjCProperty,
+        DIStringType, DISubprogram, DISubrange, DISubroutineType, DITemplateTypeParameter,
+        DITemplateValueParameter,
+    },
+    value::Value,
+};
+
+/// Handle to a metadata of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MetadataId(pub(crate) u32);
+
+impl MetadataId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// https://llvm.org/docs/LangRef.html#metadata
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Metadata {
+    /// `!"..."`
+    String(String),
+    /// A value used as metadata, like the `i32 1` of `!{i32 1}`. Arguments and
+    /// instructions are local to their function, they can only be used by its
+    /// debug records and calls.
+    Value(Value),
+    /// `!{...}`, `None` operands are `null`.
+    Node(MDNode),
+    File(DIFile),
+    CompileUnit(DICompileUnit),
+    Subprogram(DISubprogram),
+    LexicalBlock(DILexicalBlock),
+    LexicalBlockFile(DILexicalBlockFile),
+    Location(DILocation),
+    BasicType(DIBasicType),
+    DerivedType(DIDerivedType),
+    CompositeType(DICompositeType),
+    SubroutineType(DISubroutineType),
+    Subrange(DISubrange),
+    GenericSubrange(DIGenericSubrange),
+    Enumerator(DIEnumerator),
+    StringType(DIStringType),
+    TemplateTypeParameter(DITemplateTypeParameter),
+    TemplateValueParameter(DITemplateValueParameter),
+    LocalVariable(DILocalVariable),
+    GlobalVariable(DIGlobalVariable),
+    GlobalVariableExpression(DIGlobalVariableExpression),
+    Label(DILabel),
+    Expression(DIExpression),
+    /// Local to the function using it, like local values.
+    ArgList(DIArgList),
+    /// Links a `dbg_assign` record to the stores it describes, always distinct.
+    AssignId,
+    Namespace(DINamespace),
+    Module(DIModule),
+    ImportedEntity(DIImportedEntity),
+    Macro(DIMacro),
+    MacroFile(DIMacroFile),
+    CommonBlock(DICommonBlock),
+    ObjCProperty(DIObjCProperty),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MDNode {
+    pub operands: Vec<Option<MetadataId>>,
+    /// Distinct nodes are never merged with equal nodes.
+    pub distinct: bool,
+}
+
+impl Metadata {
+    /// The metadata referenced by this one.
+    pub fn operands(&self) -> Vec<MetadataId> {
+        let operands = match self {
+            Metadata::String(_)
+            | Metadata::Value(_)
+            | Metadata::Expression(_)
+            | Metadata::AssignId => return Vec::new(),
+            Metadata::ArgList(x) => return x.args.clone(),
+            Metadata::Node(node) => node.operands.clone(),
+            Metadata::File(x) => vec![
+                x.filename,
+                x.directory,
+                x.checksum.map(|(_, x)| x),
+                x.source,
+            ],
+            Metadata::CompileUnit(x) => vec![
+                x.file,
+                x.producer,
+                x.flags,
+                x.split_debug_filename,
+                x.enum_types,
+                x.retained_types,
+                x.global_variables,
+                x.imported_entities,
+                x.macros,
+                x.sysroot,
+                x.sdk,
+            ],
+            Metadata::Subprogram(x) => vec![
+                x.scope,
+                x.name,
+                x.linkage_name,
+                x.file,
+                x.ty,
+                x.containing_type,
+                x.unit,
+                x.template_params,
+                x.declaration,
+                x.retained_nodes,
+                x.thrown_types,
+                x.annotations,
+                x.target_func_name,
+            ],
+            Metadata::LexicalBlock(x) => vec![x.scope, x.file],
+            Metadata::LexicalBlockFile(x) => vec![x.scope, x.file],
+            Metadata::Location(x) => vec![Some(x.scope), x.inlined_at],
+            Metadata::BasicType(x) => vec![x.name],
+            Metadata::DerivedType(x) => vec![
+                x.name,
+                x.file,
+                x.scope,
+                x.base_type,
+                x.extra_data,
+                x.annotations,
+            ],
+            Metadata::CompositeType(x) => vec![
+                x.name,
+                x.file,
+                x.scope,
+                x.base_type,
+                x.elements,
+                x.vtable_holder,
+                x.template_params,
+                x.identifier,
+                x.discriminator,
+                x.data_location,
+                x.associated,
+                x.allocated,
+                x.rank,
+                x.annotations,
+            ],
+            Metadata::SubroutineType(x) => vec![x.types],
+            Metadata::Subrange(x) => vec![x.count, x.lower_bound, x.upper_bound, x.stride],
+            Metadata::GenericSubrange(x) => {
+                vec![x.count, x.lower_bound, x.upper_bound, x.stride]
+            }
+            Metadata::Enumerator(x) => vec![x.name],
+            Metadata::StringType(x) => vec![
+                x.name,
+                x.string_length,
+                x.string_length_exp,
+                x.string_location_exp,
+            ],
+            Metadata::TemplateTypeParameter(x) => vec![x.name, x.ty],
+            Metadata::TemplateValueParameter(x) => vec![x.name, x.ty, x.value],
+            Metadata::LocalVariable(x) => vec![x.scope, x.name, x.file, x.ty, x.annotations],
+            Metadata::GlobalVariable(x) => vec![
+                x.scope,
+                x.name,
+                x.linkage_name,
+                x.file,
+                x.ty,
+                x.static_data_member_declaration,
+                x.template_params,
+                x.annotations,
+            ],
+            Metadata::GlobalVariableExpression(x) => vec![Some(x.variable), Some(x.expression)],
+            Metadata::Label(x) => vec![x.scope, x.name, x.file],
+            Metadata::Namespace(x) => vec![x.scope, x.name],
+            Metadata::Module(x) => vec![
+                x.file,
+                x.scope,
+                x.name,
+                x.configuration_macros,
+                x.include_path,
+                x.api_notes_file,
+            ],
+            Metadata::ImportedEntity(x) => vec![x.scope, x.entity, x.name, x.file, x.elements],
+            Metadata::Macro(x) => vec![x.name, x.value],
+            Metadata::MacroFile(x) => vec![x.file, x.elements],
+            Metadata::CommonBlock(x) => vec![x.scope, x.declaration, x.name, x.file],
+            Metadata::ObjCProperty(x) => vec![x.name, x.file, x.getter_name, x.setter_name, x.ty],
+        };
+        operands.into_iter().flatten().collect()
+    }
+
+    pub fn is_distinct(&self) -> bool {
+        match self {
+            Metadata::String(_) | Metadata::Value(_) => false,
+            Metadata::Node(node) => node.distinct,
+            Metadata::File(x) => x.distinct,
+            Metadata::CompileUnit(_) => true,
+            Metadata::Subprogram(x) => x.distinct,
+            Metadata::LexicalBlock(x) => x.distinct,
+            Metadata::LexicalBlockFile(x) => x.distinct,
+            Metadata::Location(x) => x.distinct,
+            Metadata::BasicType(x) => x.distinct,
+            Metadata::DerivedType(x) => x.distinct,
+            Metadata::CompositeType(x) => x.distinct,
+            Metadata::SubroutineType(x) => x.distinct,
+            Metadata::Subrange(x) => x.distinct,
+            Metadata::GenericSubrange(x) => x.distinct,
+            Metadata::Enumerator(x) => x.distinct,
+            Metadata::StringType(x) => x.distinct,
+            Metadata::TemplateTypeParameter(x) => x.distinct,
+            Metadata::TemplateValueParameter(x) => x.distinct,
+            Metadata::LocalVariable(x) => x.distinct,
+            Metadata::GlobalVariable(x) => x.distinct,
+            Metadata::GlobalVariableExpression(x) => x.distinct,
+            Metadata::Label(x) => x.distinct,
+            Metadata::Expression(x) => x.distinct,
+            Metadata::ArgList(_) => false,
+            Metadata::AssignId => true,
+            Metadata::Namespace(x) => x.distinct,
+            Metadata::Module(x) => x.distinct,
+            Metadata::ImportedEntity(x) => x.distinct,
+            Metadata::Macro(x) => x.distinct,
+            Metadata::MacroFile(x) => x.distinct,
+            Metadata::CommonBlock(x) => x.distinct,
+            Metadata::ObjCProperty(x) => x.distinct,
+        }
+    }
+
+    pub fn is_node(&self) -> bool {
+        !matches!(
+            self,
+            Metadata::String(_) | Metadata::Value(_) | Metadata::ArgList(_)
+        )
+    }
+
+    /// Local values and the arg lists, which can't be used by module metadata.
+    pub fn is_function_local(&self) -> bool {
+        match self {
+            Metadata::Value(value) => value.kind.is_local(),
+            Metadata::ArgList(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The kind of a metadata attachment, custom kinds are registered with
+/// [`Module::md_kind`](super::module::Module::md_kind).
+// https://github.com/llvm/llvm-project/blob/llvmorg-19.1.7/llvm/include/llvm/IR/FixedMetadataKinds.def
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MetadataKind(pub(crate) u32);
+
+impl MetadataKind {
+    pub const DBG: MetadataKind = MetadataKind(0);
+    pub const TBAA: MetadataKind = MetadataKind(1);
+    pub const PROF: MetadataKind = MetadataKind(2);
+    pub const FPMATH: MetadataKind = MetadataKind(3);
+    pub const RANGE: MetadataKind = MetadataKind(4);
+    pub const TBAA_STRUCT: MetadataKind = MetadataKind(5);
+    pub const INVARIANT_LOAD: MetadataKind = MetadataKind(6);
+    pub const ALIAS_SCOPE: MetadataKind = MetadataKind(7);
+    pub const NOALIAS: MetadataKind = MetadataKind(8);
+    pub const NONTEMPORAL: MetadataKind = MetadataKind(9);
+    pub const MEM_PARALLEL_LOOP_ACCESS: MetadataKind = MetadataKind(10);
+    pub const NONNULL: MetadataKind = MetadataKind(11);
+    pub const DEREFERENCEABLE: MetadataKind = MetadataKind(12);
+    pub const DEREFERENCEABLE_OR_NULL: MetadataKind = MetadataKind(13);
+    pub const MAKE_IMPLICIT: MetadataKind = MetadataKind(14);
+    pub const UNPREDICTABLE: MetadataKind = MetadataKind(15);
+    pub const INVARIANT_GROUP: MetadataKind = MetadataKind(16);
+    pub const ALIGN: MetadataKind = MetadataKind(17);
+    pub const LOOP: MetadataKind = MetadataKind(18);
+    pub const TYPE: MetadataKind = MetadataKind(19);
+    pub const SECTION_PREFIX: MetadataKind = MetadataKind(20);
+    pub const ABSOLUTE_SYMBOL: MetadataKind = MetadataKind(21);
+    pub const ASSOCIATED: MetadataKind = MetadataKind(22);
+    pub const CALLEES: MetadataKind = MetadataKind(23);
+    pub const IRR_LOOP: MetadataKind = MetadataKind(24);
+    pub const ACCESS_GROUP: MetadataKind = MetadataKind(25);
+    pub const CALLBACK: MetadataKind = MetadataKind(26);
+    pub const PRESERVE_ACCESS_INDEX: MetadataKind = MetadataKind(27);
+    pub const VCALL_VISIBILITY: MetadataKind = MetadataKind(28);
+    pub const NOUNDEF: MetadataKind = MetadataKind(29);
+    pub const ANNOTATION: MetadataKind = MetadataKind(30);
+    pub const NOSANITIZE: MetadataKind = MetadataKind(31);
+    pub const FUNC_SANITIZE: MetadataKind = MetadataKind(32);
+    pub const EXCLUDE: MetadataKind = MetadataKind(33);
+    pub const MEMPROF: MetadataKind = MetadataKind(34);
+    pub const CALLSITE: MetadataKind = MetadataKind(35);
+    pub const KCFI_TYPE: MetadataKind = MetadataKind(36);
+    pub const PCSECTIONS: MetadataKind = MetadataKind(37);
+    pub const DI_ASSIGN_ID: MetadataKind = MetadataKind(38);
+    pub const CORO_OUTSIDE_FRAME: MetadataKind = MetadataKind(39);
+    pub const MMRA: MetadataKind = MetadataKind(40);
+
+    /// The kinds every LLVM context knows, in the order of their ids.
+    pub(crate) const FIXED: [&'static str; 41] = [
+        "dbg",
+        "tbaa",
+        "prof",
+        "fpmath",
+        "range",
+        "tbaa.struct",
+        "invariant.load",
+        "alias.scope",
+        "noalias",
+        "nontemporal",
+        "llvm.mem.parallel_loop_access",
+        "nonnull",
+        "dereferenceable",
+        "dereferenceable_or_null",
+        "make.implicit",
+        "unpredictable",
+        "invariant.group",
+        "align",
+        "llvm.loop",
+        "type",
+        "section_prefix",
+        "absolute_symbol",
+        "associated",
+        "callees",
+        "irr_loop",
+        "llvm.access.group",
+        "callback",
+        "llvm.preserve.access.index",
+        "vcall_visibility",
+        "noundef",
+        "annotation",
+        "nosanitize",
+        "func_sanitize",
+        "exclude",
+        "memprof",
+        "callsite",
+        "kcfi_type",
+        "pcsections",
+        "DIAssignID",
+        "coro.outside.frame",
+        "mmra",
+    ];
+}
+
+/// The nodes attached to an instruction, function or global, at most one per kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attachments(Vec<(MetadataKind, MetadataId)>);
+
+impl Attachments {
+    /// Attaches the node, replacing the one of the same kind.
+    pub fn set(&mut self, kind: MetadataKind, node: MetadataId) {
+        match self.0.iter_mut().find(|(x, _)| *x == kind) {
+            Some(entry) => entry.1 = node,
+            None => self.0.push((kind, node)),
+        }
+    }
+
+    pub fn get(&self, kind: MetadataKind) -> Option<MetadataId> {
+        self.0.iter().find(|(x, _)| *x == kind).map(|(_, x)| *x)
+    }
+
+    pub fn remove(&mut self, kind: MetadataKind) -> Option<MetadataId> {
+        let index = self.0.iter().position(|(x, _)| *x == kind)?;
+        Some(self.0.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MetadataKind, MetadataId)> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// `!name = !{...}`, the operands are nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedMetadata {
+    pub name: String,
+    pub operands: Vec<MetadataId>,
+}
+
+/// The metadata of a module, uniqued except for distinct nodes.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataTable {
+    items: Vec<Metadata>,
+    interned: HashMap<Metadata, MetadataId>,
+}
+
+impl MetadataTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: MetadataId) -> &Metadata {
+        &self.items[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MetadataId, &Metadata)> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (MetadataId(i as u32), x))
+    }
+
+    /// Replaces a distinct metadata, which can't be merged with another one so
+    /// changing it is safe.
+    pub(crate) fn replace(&mut self, id: MetadataId, metadata: Metadata) {
+        assert!(
+            self.get(id).is_distinct() && metadata.is_distinct(),
+            "only distinct metadata can be changed"
+        );
+        self.items[id.index()] = metadata;
+    }
+
+    pub fn intern(&mut self, metadata: Metadata) -> MetadataId {
+        if let Some(id) = self.interned.get(&metadata) {
+            return *id;
+        }
+
+        let id = MetadataId(self.items.len() as u32);
+        if !metadata.is_distinct() {
+            self.interned.insert(metadata.clone(), id);
+        }
+        self.items.push(metadata);
+        id
+    }
+}
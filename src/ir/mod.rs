@@ -0,0 +1,16 @@
+pub mod attributes;
+pub mod builder;
+pub mod constants;
+pub mod debug_info;
+pub mod di_builder;
+pub mod dwarf;
+pub mod function;
+pub mod global;
+pub mod instructions;
+pub mod md_builder;
+pub mod memory_effects;
+pub mod metadata;
+pub mod module;
+pub mod module_flags;
+pub mod types;
+pub mod value;
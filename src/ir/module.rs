@@ -0,0 +1,1027 @@
+use std::collections::HashMap;
+
+use crate::bitcode::constants::{
+    BinaryOpCode, CastCodes, ComdatSelectionKindCode, GetElementPtrOptionalFlag,
+};
+
+use super::{
+    constants::{Constant, ConstantKind, Constants, InlineAsm},
+    function::Function,
+    global::{
+        Comdat, ComdatId, DllStorageClass, GlobalAlias, GlobalIFunc, GlobalVariable, Linkage,
+        ThreadLocalMode, UnnamedAddr, Visibility,
+    },
+    instructions::{BinOpFlags, BundleTag, SyncScope},
+    metadata::{
+        Attachments, MDNode, Metadata, MetadataId, MetadataKind, MetadataTable, NamedMetadata,
+    },
+    types::{Type, TypeId, Types},
+    value::{AliasId, BasicBlockId, FunctionId, GlobalId, IFuncId, Value, ValueRef},
+};
+
+/// A LLVM module, the unit written to a bitcode file.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub source_filename: String,
+    pub triple: Option<String>,
+    pub data_layout: Option<String>,
+    /// The `module asm` blocks, one line each, see [`Module::append_module_asm`].
+    pub module_asm: String,
+    pub types: Types,
+    pub(crate) constants: Constants,
+    pub(crate) globals: Vec<GlobalVariable>,
+    pub(crate) functions: Vec<Function>,
+    pub(crate) aliases: Vec<GlobalAlias>,
+    pub(crate) ifuncs: Vec<GlobalIFunc>,
+    pub(crate) comdats: Vec<Comdat>,
+    pub(crate) metadata: MetadataTable,
+    pub(crate) named_metadata: Vec<NamedMetadata>,
+    pub(crate) md_kinds: Vec<String>,
+    pub(crate) sync_scopes: Vec<String>,
+    pub(crate) bundle_tags: Vec<String>,
+    /// Variable locations are built as calls to the `llvm.dbg.*` intrinsics
+    /// instead of debug records, for readers older than LLVM 19.
+    pub debug_intrinsics: bool,
+    names: HashMap<String, ValueRef>,
+}
+
+impl Module {
+    pub fn new(source_filename: &str) -> Self {
+        Self {
+            source_filename: source_filename.to_string(),
+            triple: None,
+            data_layout: None,
+            module_asm: String::new(),
+            types: Types::new(),
+            constants: Constants::new(),
+            globals: Vec::new(),
+            functions: Vec::new(),
+            aliases: Vec::new(),
+            ifuncs: Vec::new(),
+            comdats: Vec::new(),
+            metadata: MetadataTable::new(),
+            named_metadata: Vec::new(),
+            md_kinds: MetadataKind::FIXED.iter().map(|x| x.to_string()).collect(),
+            // The ids of the builtin scopes are fixed.
+            sync_scopes: vec!["singlethread".to_string(), String::new()],
+            bundle_tags: BundleTag::FIXED.iter().map(|x| x.to_string()).collect(),
+            debug_intrinsics: false,
+            names: HashMap::new(),
+        }
+    }
+
+    /// Adds a line of module level assembly.
+    pub fn append_module_asm(&mut self, asm: &str) {
+        self.module_asm.push_str(asm);
+        if !self.module_asm.ends_with('\n') {
+            self.module_asm.push('\n');
+        }
+    }
+
+    /// The bit width of the GEP indices of pointers in the address space, from the
+    /// `p[n]:<size>:<abi>[:<pref>[:<idx>]]` spec of the data layout. Address spaces
+    /// without one use the spec of the address space 0, 64 bits by default.
+    // https://llvm.org/docs/LangRef.html#data-layout
+    pub fn index_width(&self, address_space: u32) -> u32 {
+        let specs: Vec<(u32, u32)> = self
+            .data_layout
+            .iter()
+            .flat_map(|x| x.split('-'))
+            .filter_map(|spec| {
+                let mut fields = spec.strip_prefix('p')?.split(':');
+                let space = match fields.next()? {
+                    "" => 0,
+                    x => x.parse().ok()?,
+                };
+                let size = fields.next()?.parse().ok()?;
+                let index = fields.nth(2).map_or(Some(size), |x| x.parse().ok())?;
+                Some((space, index))
+            })
+            .collect();
+
+        [address_space, 0]
+            .iter()
+            .find_map(|space| specs.iter().find(|x| x.0 == *space))
+            .map_or(64, |x| x.1)
+    }
+
+    fn add_name(&mut self, name: &str, value: ValueRef) {
+        // Unnamed globals are allowed and numbered.
+        if name.is_empty() {
+            return;
+        }
+
+        let prev = self.names.insert(name.to_string(), value);
+        assert!(prev.is_none(), "global value {name} already exists");
+    }
+
+    /// Adds a global variable declaration, set an initializer to define it.
+    pub fn add_global(&mut self, name: &str, value_type: TypeId) -> GlobalId {
+        self.add_global_in(name, value_type, 0)
+    }
+
+    pub fn add_global_in(
+        &mut self,
+        name: &str,
+        value_type: TypeId,
+        address_space: u32,
+    ) -> GlobalId {
+        let id = GlobalId(self.globals.len() as u32);
+        self.add_name(name, ValueRef::Global(id));
+
+        let ptr_ty = self.types.ptr_in(address_space);
+        self.globals.push(GlobalVariable {
+            name: name.to_string(),
+            value_type,
+            constant: false,
+            initializer: None,
+            linkage: Linkage::External,
+            alignment: None,
+            section: None,
+            visibility: Visibility::Default,
+            thread_local: ThreadLocalMode::NotThreadLocal,
+            unnamed_addr: UnnamedAddr::None,
+            externally_initialized: false,
+            dll_storage_class: DllStorageClass::Default,
+            dso_local: false,
+            comdat: None,
+            metadata: Attachments::default(),
+            address_space,
+            ptr_ty,
+        });
+        id
+    }
+
+    pub fn global(&self, id: GlobalId) -> &GlobalVariable {
+        &self.globals[id.index()]
+    }
+
+    pub fn global_mut(&mut self, id: GlobalId) -> &mut GlobalVariable {
+        &mut self.globals[id.index()]
+    }
+
+    /// The pointer to the global.
+    pub fn global_value(&self, id: GlobalId) -> Value {
+        Value::new(ValueRef::Global(id), self.global(id).ptr_ty)
+    }
+
+    pub fn globals(&self) -> impl Iterator<Item = (GlobalId, &GlobalVariable)> {
+        self.globals
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (GlobalId(i as u32), x))
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<GlobalId> {
+        match self.names.get(name) {
+            Some(ValueRef::Global(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Adds a function declaration, append blocks to define it.
+    pub fn add_function(&mut self, name: &str, ty: TypeId) -> FunctionId {
+        let Type::Function(fn_ty) = self.types.get(ty).clone() else {
+            panic!("{name} doesn't have a function type");
+        };
+
+        let id = FunctionId(self.functions.len() as u32);
+        self.add_name(name, ValueRef::Function(id));
+
+        let ptr_ty = self.types.ptr();
+        self.functions.push(Function::new(
+            id,
+            name,
+            ty,
+            ptr_ty,
+            fn_ty.ret,
+            fn_ty.params,
+            fn_ty.vararg,
+        ));
+        id
+    }
+
+    pub fn function(&self, id: FunctionId) -> &Function {
+        &self.functions[id.index()]
+    }
+
+    pub fn function_mut(&mut self, id: FunctionId) -> &mut Function {
+        &mut self.functions[id.index()]
+    }
+
+    /// The pointer to the function.
+    pub fn function_value(&self, id: FunctionId) -> Value {
+        Value::new(ValueRef::Function(id), self.function(id).ptr_ty)
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = (FunctionId, &Function)> {
+        self.functions
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (FunctionId(i as u32), x))
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<FunctionId> {
+        match self.names.get(name) {
+            Some(ValueRef::Function(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Adds an alias of `aliasee`, which must be a global value or a constant
+    /// expression of one.
+    pub fn add_alias(&mut self, name: &str, value_type: TypeId, aliasee: Value) -> AliasId {
+        assert!(!aliasee.kind.is_local(), "the aliasee must be a constant");
+        assert!(
+            self.types.get(aliasee.ty).is_pointer(),
+            "the aliasee must be a pointer"
+        );
+
+        let id = AliasId(self.aliases.len() as u32);
+        self.add_name(name, ValueRef::Alias(id));
+
+        self.aliases.push(GlobalAlias {
+            name: name.to_string(),
+            value_type,
+            aliasee,
+            linkage: Linkage::External,
+            visibility: Visibility::Default,
+            dll_storage_class: DllStorageClass::Default,
+            thread_local: ThreadLocalMode::NotThreadLocal,
+            unnamed_addr: UnnamedAddr::None,
+            dso_local: false,
+            partition: None,
+            ptr_ty: aliasee.ty,
+        });
+        id
+    }
+
+    pub fn alias(&self, id: AliasId) -> &GlobalAlias {
+        &self.aliases[id.index()]
+    }
+
+    pub fn alias_mut(&mut self, id: AliasId) -> &mut GlobalAlias {
+        &mut self.aliases[id.index()]
+    }
+
+    pub fn alias_value(&self, id: AliasId) -> Value {
+        Value::new(ValueRef::Alias(id), self.alias(id).ptr_ty)
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = (AliasId, &GlobalAlias)> {
+        self.aliases
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (AliasId(i as u32), x))
+    }
+
+    pub fn get_alias(&self, name: &str) -> Option<AliasId> {
+        match self.names.get(name) {
+            Some(ValueRef::Alias(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Adds an ifunc of type `ty`, whose implementation is returned by `resolver`.
+    pub fn add_ifunc(&mut self, name: &str, ty: TypeId, resolver: FunctionId) -> IFuncId {
+        assert!(
+            matches!(self.types.get(ty), Type::Function(_)),
+            "{name} doesn't have a function type"
+        );
+        let resolver_ret = self.function(resolver).return_type();
+        assert!(
+            self.types.get(resolver_ret).is_pointer(),
+            "the resolver must return a pointer"
+        );
+
+        let id = IFuncId(self.ifuncs.len() as u32);
+        self.add_name(name, ValueRef::IFunc(id));
+
+        let ptr_ty = self.types.ptr();
+        let resolver = self.function_value(resolver);
+        self.ifuncs.push(GlobalIFunc {
+            name: name.to_string(),
+            value_type: ty,
+            resolver,
+            linkage: Linkage::External,
+            visibility: Visibility::Default,
+            dso_local: false,
+            partition: None,
+            ptr_ty,
+        });
+        id
+    }
+
+    pub fn ifunc(&self, id: IFuncId) -> &GlobalIFunc {
+        &self.ifuncs[id.index()]
+    }
+
+    pub fn ifunc_mut(&mut self, id: IFuncId) -> &mut GlobalIFunc {
+        &mut self.ifuncs[id.index()]
+    }
+
+    pub fn ifunc_value(&self, id: IFuncId) -> Value {
+        Value::new(ValueRef::IFunc(id), self.ifunc(id).ptr_ty)
+    }
+
+    pub fn ifuncs(&self) -> impl Iterator<Item = (IFuncId, &GlobalIFunc)> {
+        self.ifuncs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (IFuncId(i as u32), x))
+    }
+
+    pub fn get_ifunc(&self, name: &str) -> Option<IFuncId> {
+        match self.names.get(name) {
+            Some(ValueRef::IFunc(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Adds a comdat, set it as the `comdat` of the globals and functions in it.
+    pub fn add_comdat(&mut self, name: &str, selection_kind: ComdatSelectionKindCode) -> ComdatId {
+        assert!(
+            self.get_comdat(name).is_none(),
+            "comdat {name} already exists"
+        );
+
+        let id = ComdatId(self.comdats.len() as u32);
+        self.comdats.push(Comdat {
+            name: name.to_string(),
+            selection_kind,
+        });
+        id
+    }
+
+    pub fn comdat(&self, id: ComdatId) -> &Comdat {
+        &self.comdats[id.index()]
+    }
+
+    pub fn comdat_mut(&mut self, id: ComdatId) -> &mut Comdat {
+        &mut self.comdats[id.index()]
+    }
+
+    pub fn comdats(&self) -> impl Iterator<Item = (ComdatId, &Comdat)> {
+        self.comdats
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (ComdatId(i as u32), x))
+    }
+
+    pub fn get_comdat(&self, name: &str) -> Option<ComdatId> {
+        self.comdats
+            .iter()
+            .position(|x| x.name == name)
+            .map(|i| ComdatId(i as u32))
+    }
+
+    /// Returns the handle of the named sync scope, registering it if needed. The
+    /// empty name is the system scope.
+    pub fn sync_scope(&mut self, name: &str) -> SyncScope {
+        match self.sync_scopes.iter().position(|x| x == name) {
+            Some(i) => SyncScope(i as u32),
+            None => {
+                self.sync_scopes.push(name.to_string());
+                SyncScope(self.sync_scopes.len() as u32 - 1)
+            }
+        }
+    }
+
+    pub fn sync_scope_name(&self, scope: SyncScope) -> &str {
+        &self.sync_scopes[scope.0 as usize]
+    }
+
+    pub fn sync_scopes(&self) -> impl Iterator<Item = (SyncScope, &str)> {
+        self.sync_scopes
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (SyncScope(i as u32), x.as_str()))
+    }
+
+    /// Returns the handle of the operand bundle tag, registering it if needed.
+    pub fn bundle_tag(&mut self, name: &str) -> BundleTag {
+        match self.bundle_tags.iter().position(|x| x == name) {
+            Some(i) => BundleTag(i as u32),
+            None => {
+                self.bundle_tags.push(name.to_string());
+                BundleTag(self.bundle_tags.len() as u32 - 1)
+            }
+        }
+    }
+
+    pub fn bundle_tag_name(&self, tag: BundleTag) -> &str {
+        &self.bundle_tags[tag.0 as usize]
+    }
+
+    pub fn bundle_tags(&self) -> impl Iterator<Item = (BundleTag, &str)> {
+        self.bundle_tags
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (BundleTag(i as u32), x.as_str()))
+    }
+
+    /// Returns the handle of the attachment kind, registering it if needed.
+    pub fn md_kind(&mut self, name: &str) -> MetadataKind {
+        match self.md_kinds.iter().position(|x| x == name) {
+            Some(i) => MetadataKind(i as u32),
+            None => {
+                self.md_kinds.push(name.to_string());
+                MetadataKind(self.md_kinds.len() as u32 - 1)
+            }
+        }
+    }
+
+    pub fn md_kind_name(&self, kind: MetadataKind) -> &str {
+        &self.md_kinds[kind.0 as usize]
+    }
+
+    pub fn md_kinds(&self) -> impl Iterator<Item = (MetadataKind, &str)> {
+        self.md_kinds
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (MetadataKind(i as u32), x.as_str()))
+    }
+
+    pub fn constants(&self) -> &Constants {
+        &self.constants
+    }
+
+    fn add_constant(&mut self, ty: TypeId, kind: ConstantKind) -> Value {
+        let id = self.constants.intern(Constant { ty, kind });
+        Value::new(ValueRef::Constant(id), ty)
+    }
+
+    /// An integer constant, truncated to the width of the type.
+    pub fn const_int(&mut self, ty: TypeId, value: i128) -> Value {
+        let Type::Integer(bits) = self.types.get(ty) else {
+            panic!("const_int needs an integer type");
+        };
+        assert!(
+            *bits <= 128,
+            "integers wider than 128 bits are not supported"
+        );
+
+        let value = if *bits == 128 {
+            value as u128
+        } else {
+            value as u128 & ((1 << bits) - 1)
+        };
+        self.add_constant(ty, ConstantKind::Int(value))
+    }
+
+    /// A `float` or `double` constant.
+    pub fn const_float(&mut self, ty: TypeId, value: f64) -> Value {
+        let bits = match self.types.get(ty) {
+            Type::Float => (value as f32).to_bits() as u128,
+            Type::Double => value.to_bits() as u128,
+            _ => panic!("const_float needs a float or double type, use const_float_bits"),
+        };
+        self.add_constant(ty, ConstantKind::Float(bits))
+    }
+
+    /// A floating point constant from its raw bits.
+    pub fn const_float_bits(&mut self, ty: TypeId, bits: u128) -> Value {
+        assert!(
+            matches!(
+                self.types.get(ty),
+                Type::Half
+                    | Type::BFloat
+                    | Type::Float
+                    | Type::Double
+                    | Type::X86Fp80
+                    | Type::Fp128
+                    | Type::PpcFp128
+            ),
+            "const_float_bits needs a floating point type"
+        );
+        self.add_constant(ty, ConstantKind::Float(bits))
+    }
+
+    /// `null` for pointers, `zeroinitializer` for everything else.
+    pub fn const_null(&mut self, ty: TypeId) -> Value {
+        self.add_constant(ty, ConstantKind::Null)
+    }
+
+    /// The `none` token, the parent pad of funclets that aren't nested.
+    pub fn const_none(&mut self) -> Value {
+        let token = self.types.token();
+        self.add_constant(token, ConstantKind::Null)
+    }
+
+    pub fn const_undef(&mut self, ty: TypeId) -> Value {
+        self.add_constant(ty, ConstantKind::Undef)
+    }
+
+    pub fn const_poison(&mut self, ty: TypeId) -> Value {
+        self.add_constant(ty, ConstantKind::Poison)
+    }
+
+    /// A struct, array or vector constant.
+    pub fn const_aggregate(&mut self, ty: TypeId, elements: &[Value]) -> Value {
+        assert!(
+            elements.iter().all(|x| !x.kind.is_local()),
+            "constants can't use local values"
+        );
+
+        match self.types.get(ty) {
+            Type::Struct(st) => {
+                assert!(!st.opaque, "opaque structs can't be constants");
+                assert!(
+                    st.fields.len() == elements.len()
+                        && st.fields.iter().zip(elements).all(|(f, x)| *f == x.ty),
+                    "struct constant elements don't match the fields"
+                );
+            }
+            Type::Array { element, len } => {
+                assert_eq!(*len, elements.len() as u64, "wrong number of elements");
+                assert!(
+                    elements.iter().all(|x| x.ty == *element),
+                    "array constant element type mismatch"
+                );
+            }
+            Type::Vector {
+                element,
+                len,
+                scalable,
+            } => {
+                assert!(!scalable, "scalable vector constants can only be splats");
+                assert_eq!(*len, elements.len() as u32, "wrong number of elements");
+                assert!(
+                    elements.iter().all(|x| x.ty == *element),
+                    "vector constant element type mismatch"
+                );
+            }
+            _ => panic!("const_aggregate needs a struct, array or vector type"),
+        }
+
+        // The reader has no empty aggregates, they're zeroinitializer.
+        if elements.is_empty() {
+            return self.const_null(ty);
+        }
+        self.add_constant(ty, ConstantKind::Aggregate(elements.to_vec()))
+    }
+
+    /// A literal struct constant with the types of the elements.
+    pub fn const_struct(&mut self, elements: &[Value], packed: bool) -> Value {
+        let fields: Vec<_> = elements.iter().map(|x| x.ty).collect();
+        let ty = self.types.structure(&fields, packed);
+        self.const_aggregate(ty, elements)
+    }
+
+    /// An `[n x i8]` constant, optionally with a trailing null byte.
+    pub fn const_string(&mut self, value: &[u8], null_terminated: bool) -> Value {
+        let mut data: Vec<u64> = value.iter().map(|x| *x as u64).collect();
+        if null_terminated {
+            data.push(0);
+        }
+
+        let i8 = self.types.i8();
+        let ty = self.types.array(i8, data.len() as u64);
+        if data.is_empty() {
+            return self.const_null(ty);
+        }
+        self.add_constant(ty, ConstantKind::Data(data))
+    }
+
+    /// An array or vector of integers or floating point values, from their raw bits.
+    pub fn const_data(&mut self, ty: TypeId, elements: &[u64]) -> Value {
+        let (element, len) = match self.types.get(ty) {
+            Type::Array { element, len } => (*element, *len),
+            Type::Vector {
+                element,
+                len,
+                scalable: false,
+            } => (*element, *len as u64),
+            _ => panic!("const_data needs an array or fixed vector type"),
+        };
+
+        assert_eq!(len, elements.len() as u64, "wrong number of elements");
+        assert!(
+            matches!(
+                self.types.get(element),
+                Type::Integer(8 | 16 | 32 | 64)
+                    | Type::Half
+                    | Type::BFloat
+                    | Type::Float
+                    | Type::Double
+            ),
+            "const_data elements must be i8, i16, i32, i64 or floating point"
+        );
+
+        if elements.is_empty() {
+            return self.const_null(ty);
+        }
+        self.add_constant(ty, ConstantKind::Data(elements.to_vec()))
+    }
+
+    fn check_constant_operand(value: Value) {
+        assert!(!value.kind.is_local(), "constants can't use local values");
+    }
+
+    /// A cast constant expression, LLVM 19 only keeps `trunc`, `ptrtoint`,
+    /// `inttoptr`, `bitcast` and `addrspacecast` as constant expressions.
+    // https://llvm.org/docs/LangRef.html#constant-expressions
+    pub fn const_cast(&mut self, op: CastCodes, value: Value, ty: TypeId) -> Value {
+        Self::check_constant_operand(value);
+        assert!(
+            matches!(
+                op,
+                CastCodes::Trunc
+                    | CastCodes::PtrToInt
+                    | CastCodes::IntToPtr
+                    | CastCodes::Bitcast
+                    | CastCodes::AddspaceCast
+            ),
+            "{op:?} is not a constant expression"
+        );
+        self.check_cast(op, value.ty, ty);
+
+        self.add_constant(ty, ConstantKind::Cast { op, value })
+    }
+
+    /// `add`, `sub` or `xor` of integer constants.
+    pub fn const_binop(
+        &mut self,
+        op: BinaryOpCode,
+        lhs: Value,
+        rhs: Value,
+        flags: BinOpFlags,
+    ) -> Value {
+        Self::check_constant_operand(lhs);
+        Self::check_constant_operand(rhs);
+        assert!(
+            matches!(
+                op,
+                BinaryOpCode::Add | BinaryOpCode::Sub | BinaryOpCode::Xor
+            ),
+            "{op:?} is not a constant expression"
+        );
+        assert_eq!(lhs.ty, rhs.ty, "operands have different types");
+        assert!(
+            self.types.get(self.types.scalar_type(lhs.ty)).is_integer(),
+            "constant binary operators need integer operands"
+        );
+
+        let flags = flags.encode(op);
+        self.add_constant(
+            lhs.ty,
+            ConstantKind::BinOp {
+                op,
+                lhs,
+                rhs,
+                flags,
+            },
+        )
+    }
+
+    /// A GEP constant expression, `inrange` restricts the accesses through the
+    /// result to the offsets `lower..upper` from it.
+    pub fn const_gep(
+        &mut self,
+        source_type: TypeId,
+        ptr: Value,
+        indices: &[Value],
+        flags: &[GetElementPtrOptionalFlag],
+        inrange: Option<(i64, i64)>,
+    ) -> Value {
+        Self::check_constant_operand(ptr);
+        for index in indices {
+            Self::check_constant_operand(*index);
+        }
+        if let Some((lower, upper)) = inrange {
+            assert!(lower <= upper, "invalid inrange");
+        }
+
+        let ty = self.gep_type(source_type, ptr, indices);
+        let flags = flags.iter().fold(0, |acc, x| acc | 1 << *x as u64);
+        self.add_constant(
+            ty,
+            ConstantKind::GetElementPtr {
+                source_type,
+                ptr,
+                indices: indices.to_vec(),
+                flags,
+                inrange,
+            },
+        )
+    }
+
+    /// The address of a basic block, only usable by `indirectbr` and calls to
+    /// inline asm.
+    pub fn const_blockaddress(&mut self, function: FunctionId, block: BasicBlockId) -> Value {
+        let func = self.function(function);
+        assert!(
+            block.index() < func.blocks.len(),
+            "the block doesn't belong to the function"
+        );
+        assert!(
+            block.index() != 0,
+            "the entry block can't have its address taken"
+        );
+
+        let ty = self.types.ptr();
+        let function = self.function_value(function);
+        self.add_constant(ty, ConstantKind::BlockAddress { function, block })
+    }
+
+    /// An inline asm value, the callee of the calls running it.
+    // https://llvm.org/docs/LangRef.html#inline-assembler-expressions
+    pub fn const_inline_asm(&mut self, asm: InlineAsm) -> Value {
+        assert!(
+            matches!(self.types.get(asm.function_type), Type::Function(_)),
+            "inline asm needs a function type"
+        );
+
+        let ptr = self.types.ptr();
+        self.add_constant(ptr, ConstantKind::InlineAsm(asm))
+    }
+
+    /// A function equivalent to `function` which is local to the linkage unit,
+    /// possibly a PLT entry.
+    pub fn const_dso_local_equivalent(&mut self, function: FunctionId) -> Value {
+        let function = self.function_value(function);
+        self.add_constant(function.ty, ConstantKind::DsoLocalEquivalent(function))
+    }
+
+    /// The address of `function` itself, never replaced by a CFI jump table entry.
+    pub fn const_no_cfi(&mut self, function: FunctionId) -> Value {
+        let function = self.function_value(function);
+        self.add_constant(function.ty, ConstantKind::NoCfi(function))
+    }
+
+    /// A signed pointer, `addr_discriminator` is the address blended into the
+    /// discriminator.
+    // https://llvm.org/docs/LangRef.html#pointer-authentication-constants
+    pub fn const_ptrauth(
+        &mut self,
+        ptr: Value,
+        key: u32,
+        discriminator: u64,
+        addr_discriminator: Option<Value>,
+    ) -> Value {
+        Self::check_constant_operand(ptr);
+        assert!(
+            self.types.get(ptr.ty).is_pointer(),
+            "ptrauth signs pointers"
+        );
+
+        let i32 = self.types.i32();
+        let i64 = self.types.i64();
+        let key = self.const_int(i32, key as i128);
+        let discriminator = self.const_int(i64, discriminator as i128);
+        let addr_discriminator = match addr_discriminator {
+            Some(x) => {
+                Self::check_constant_operand(x);
+                assert!(
+                    self.types.get(x.ty).is_pointer(),
+                    "the address discriminator must be a pointer"
+                );
+                x
+            }
+            None => {
+                let ptr = self.types.ptr();
+                self.const_null(ptr)
+            }
+        };
+
+        self.add_constant(
+            ptr.ty,
+            ConstantKind::PtrAuth {
+                ptr,
+                key,
+                discriminator,
+                addr_discriminator,
+            },
+        )
+    }
+
+    pub fn metadata(&self) -> &MetadataTable {
+        &self.metadata
+    }
+
+    /// Adds the metadata, uniqued unless it is distinct.
+    pub fn add_metadata(&mut self, metadata: Metadata) -> MetadataId {
+        if let Metadata::ArgList(list) = &metadata {
+            assert!(
+                list.args
+                    .iter()
+                    .all(|x| matches!(self.metadata.get(*x), Metadata::Value(_))),
+                "arg lists are made of values"
+            );
+        } else {
+            assert!(
+                metadata
+                    .operands()
+                    .iter()
+                    .all(|x| !self.metadata.get(*x).is_function_local()),
+                "module metadata can't use function local metadata"
+            );
+        }
+        self.metadata.intern(metadata)
+    }
+
+    pub fn md_string(&mut self, value: &str) -> MetadataId {
+        self.metadata.intern(Metadata::String(value.to_string()))
+    }
+
+    /// Wraps a value as metadata, arguments and instructions can only be used by
+    /// debug records and calls of their function.
+    pub fn md_value(&mut self, value: Value) -> MetadataId {
+        assert!(
+            !matches!(value.kind, ValueRef::Metadata(_)),
+            "metadata can't be wrapped twice"
+        );
+        self.metadata.intern(Metadata::Value(value))
+    }
+
+    /// Uses the metadata as a `metadata` argument of a call, like the operands of
+    /// the `llvm.dbg.*` intrinsics.
+    pub fn metadata_as_value(&mut self, metadata: MetadataId) -> Value {
+        let ty = self.types.metadata();
+        Value::new(ValueRef::Metadata(metadata), ty)
+    }
+
+    /// `!{...}`, uniqued with equal nodes.
+    pub fn md_node(&mut self, operands: &[Option<MetadataId>]) -> MetadataId {
+        self.add_node(operands, false)
+    }
+
+    /// `distinct !{...}`
+    pub fn md_distinct_node(&mut self, operands: &[Option<MetadataId>]) -> MetadataId {
+        self.add_node(operands, true)
+    }
+
+    fn add_node(&mut self, operands: &[Option<MetadataId>], distinct: bool) -> MetadataId {
+        self.add_metadata(Metadata::Node(MDNode {
+            operands: operands.to_vec(),
+            distinct,
+        }))
+    }
+
+    /// Appends a node to the named metadata, creating it if needed.
+    pub fn add_named_metadata_operand(&mut self, name: &str, node: MetadataId) {
+        assert!(
+            self.metadata.get(node).is_node(),
+            "named metadata operands must be nodes"
+        );
+
+        match self.named_metadata.iter_mut().find(|x| x.name == name) {
+            Some(named) => named.operands.push(node),
+            None => self.named_metadata.push(NamedMetadata {
+                name: name.to_string(),
+                operands: vec![node],
+            }),
+        }
+    }
+
+    pub fn named_metadata(&self, name: &str) -> Option<&NamedMetadata> {
+        self.named_metadata.iter().find(|x| x.name == name)
+    }
+
+    pub fn named_metadatas(&self) -> impl Iterator<Item = &NamedMetadata> {
+        self.named_metadata.iter()
+    }
+
+    // https://llvm.org/docs/LangRef.html#conversion-operations
+    pub(crate) fn check_cast(&self, op: CastCodes, src: TypeId, dest: TypeId) {
+        let types = &self.types;
+
+        // Bitcasts can reshape vectors, as long as the size is the same.
+        if !matches!(op, CastCodes::Bitcast) {
+            assert_eq!(
+                types.element_count(src),
+                types.element_count(dest),
+                "{op:?} can't change the number of elements"
+            );
+        }
+        let is_scalable = |ty| types.element_count(ty).is_some_and(|x| x.scalable);
+
+        let src_scalar = types.get(types.scalar_type(src));
+        let dest_scalar = types.get(types.scalar_type(dest));
+        let src_bits = types.primitive_size_in_bits(types.scalar_type(src));
+        let dest_bits = types.primitive_size_in_bits(types.scalar_type(dest));
+
+        let valid = match op {
+            CastCodes::Trunc => {
+                src_scalar.is_integer() && dest_scalar.is_integer() && src_bits > dest_bits
+            }
+            CastCodes::Zext | CastCodes::Sext => {
+                src_scalar.is_integer() && dest_scalar.is_integer() && src_bits < dest_bits
+            }
+            CastCodes::FpTrunc => {
+                src_scalar.is_floating_point()
+                    && dest_scalar.is_floating_point()
+                    && src_bits > dest_bits
+            }
+            CastCodes::FpExt => {
+                src_scalar.is_floating_point()
+                    && dest_scalar.is_floating_point()
+                    && src_bits < dest_bits
+            }
+            CastCodes::FpToUi | CastCodes::FpToSi => {
+                src_scalar.is_floating_point() && dest_scalar.is_integer()
+            }
+            CastCodes::UiToFp | CastCodes::SiToFp => {
+                src_scalar.is_integer() && dest_scalar.is_floating_point()
+            }
+            CastCodes::PtrToInt => src_scalar.is_pointer() && dest_scalar.is_integer(),
+            CastCodes::IntToPtr => src_scalar.is_integer() && dest_scalar.is_pointer(),
+            CastCodes::Bitcast => match (src_scalar, dest_scalar) {
+                (Type::Pointer { address_space: a }, Type::Pointer { address_space: b }) => a == b,
+                (Type::Pointer { .. }, _) | (_, Type::Pointer { .. }) => false,
+                _ => {
+                    // Vectors can be bitcast to scalars of the same size.
+                    let src_bits = types.primitive_size_in_bits(src);
+                    src_bits != 0
+                        && src_bits == types.primitive_size_in_bits(dest)
+                        && is_scalable(src) == is_scalable(dest)
+                }
+            },
+            CastCodes::AddspaceCast => match (src_scalar, dest_scalar) {
+                (Type::Pointer { address_space: a }, Type::Pointer { address_space: b }) => a != b,
+                _ => false,
+            },
+        };
+
+        assert!(
+            valid,
+            "invalid {op:?} from {src_scalar:?} to {dest_scalar:?}"
+        );
+    }
+
+    /// Checks the operands of a GEP and returns the type of the address, a vector
+    /// of pointers if the pointer or any index is a vector.
+    // https://llvm.org/docs/LangRef.html#getelementptr-instruction
+    pub(crate) fn gep_type(
+        &mut self,
+        source_type: TypeId,
+        ptr: Value,
+        indices: &[Value],
+    ) -> TypeId {
+        assert!(
+            self.types.is_sized(source_type),
+            "{:?} has no size",
+            self.types.get(source_type)
+        );
+        assert!(
+            self.types.get(self.types.scalar_type(ptr.ty)).is_pointer(),
+            "gep needs a pointer or a vector of pointers"
+        );
+
+        // A vector of pointers or vector indices make a vector of addresses.
+        let mut count = self.types.element_count(ptr.ty);
+
+        // The first index steps over the pointer, the rest go into the type.
+        let mut current = source_type;
+        for (i, index) in indices.iter().enumerate() {
+            assert!(
+                self.types
+                    .get(self.types.scalar_type(index.ty))
+                    .is_integer(),
+                "gep indices must be integers"
+            );
+            if let Some(index_count) = self.types.element_count(index.ty) {
+                assert!(
+                    count.is_none_or(|x| x == index_count),
+                    "gep vector operands have different lengths"
+                );
+                count = Some(index_count);
+            }
+
+            if i == 0 {
+                continue;
+            }
+
+            current = match self.types.get(current) {
+                Type::Array { element, .. } | Type::Vector { element, .. } => *element,
+                Type::Struct(st) => {
+                    let field = match index.kind {
+                        ValueRef::Constant(id) => match self.constants.get(id).kind {
+                            ConstantKind::Int(x) => x as usize,
+                            _ => panic!("struct indices must be integer constants"),
+                        },
+                        _ => panic!("struct indices must be constants"),
+                    };
+                    assert_eq!(
+                        self.types.get(index.ty),
+                        &Type::Integer(32),
+                        "struct indices must be i32"
+                    );
+                    *st.fields.get(field).expect("struct index out of range")
+                }
+                ty => panic!("can't index into {ty:?}"),
+            };
+        }
+
+        let ptr_ty = self.types.scalar_type(ptr.ty);
+        match count {
+            Some(count) => self.types.vector_of(ptr_ty, count),
+            None => ptr_ty,
+        }
+    }
+
+    /// Writes the module as LLVM bitcode.
+    pub fn to_bitcode(&self) -> Vec<u8> {
+        crate::bitcode::writer::write_module(self)
+    }
+}
@@ -0,0 +1,285 @@
+use super::{
+    constants::ConstantKind,
+    metadata::{Metadata, MetadataId},
+    module::Module,
+    value::ValueRef,
+};
+
+const MODULE_FLAGS: &str = "llvm.module.flags";
+
+/// The version of the debug info metadata format, modules with another version
+/// have their debug info dropped.
+pub const DEBUG_METADATA_VERSION: u32 = 3;
+
+/// How flags with the same key are merged when linking modules.
+// https://llvm.org/docs/LangRef.html#module-flags-metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModuleFlagBehavior {
+    /// Different values are an error.
+    Error = 1,
+    /// Different values are a warning, the value of the first module is kept.
+    Warning = 2,
+    /// The value is a `!{!"key", value}` pair the flag `key` must have.
+    Require = 3,
+    /// Replaces the value of the other module, two overrides with different
+    /// values are an error.
+    Override = 4,
+    /// The values are nodes, their operands are concatenated.
+    Append = 5,
+    /// Like `Append`, without duplicates.
+    AppendUnique = 6,
+    Max = 7,
+    Min = 8,
+}
+
+impl ModuleFlagBehavior {
+    fn from_code(code: u128) -> Self {
+        match code {
+            1 => Self::Error,
+            2 => Self::Warning,
+            3 => Self::Require,
+            4 => Self::Override,
+            5 => Self::Append,
+            6 => Self::AppendUnique,
+            7 => Self::Max,
+            8 => Self::Min,
+            _ => panic!("invalid module flag behavior {code}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PicLevel {
+    Small = 1,
+    Big = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieLevel {
+    Small = 1,
+    Large = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UWTableKind {
+    Sync = 1,
+    Async = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FramePointerKind {
+    None = 0,
+    NonLeaf = 1,
+    All = 2,
+}
+
+/// An entry of `!llvm.module.flags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModuleFlag<'a> {
+    pub behavior: ModuleFlagBehavior,
+    pub key: &'a str,
+    pub value: MetadataId,
+}
+
+impl Module {
+    /// Sets the flag `key`, replacing its previous entry.
+    pub fn set_module_flag(&mut self, behavior: ModuleFlagBehavior, key: &str, value: MetadataId) {
+        let i32 = self.types.i32();
+        let behavior = self.const_int(i32, behavior as i128);
+        let behavior = self.md_value(behavior);
+        let key_md = self.md_string(key);
+        let node = self.md_node(&[Some(behavior), Some(key_md), Some(value)]);
+
+        let index = self.module_flags().position(|x| x.key == key);
+        match index {
+            Some(i) => {
+                let named = self
+                    .named_metadata
+                    .iter_mut()
+                    .find(|x| x.name == MODULE_FLAGS)
+                    .unwrap();
+                named.operands[i] = node;
+            }
+            None => self.add_named_metadata_operand(MODULE_FLAGS, node),
+        }
+    }
+
+    /// Sets a flag with an `i32` value.
+    pub fn set_module_flag_u32(&mut self, behavior: ModuleFlagBehavior, key: &str, value: u32) {
+        let i32 = self.types.i32();
+        let value = self.const_int(i32, value as i128);
+        let value = self.md_value(value);
+        self.set_module_flag(behavior, key, value);
+    }
+
+    pub fn module_flag(&self, key: &str) -> Option<ModuleFlag<'_>> {
+        self.module_flags().find(|x| x.key == key)
+    }
+
+    pub fn module_flags(&self) -> impl Iterator<Item = ModuleFlag<'_>> {
+        let operands = match self.named_metadata(MODULE_FLAGS) {
+            Some(named) => named.operands.as_slice(),
+            None => &[],
+        };
+
+        operands.iter().map(|node| {
+            let Metadata::Node(node) = self.metadata.get(*node) else {
+                unreachable!("named metadata operands are nodes");
+            };
+            let [Some(behavior), Some(key), Some(value)] = node.operands[..] else {
+                panic!("invalid module flag");
+            };
+
+            let behavior = match self.metadata.get(behavior) {
+                Metadata::Value(value) => match value.kind {
+                    ValueRef::Constant(id) => match self.constants.get(id).kind {
+                        ConstantKind::Int(x) => ModuleFlagBehavior::from_code(x),
+                        _ => panic!("invalid module flag behavior"),
+                    },
+                    _ => panic!("invalid module flag behavior"),
+                },
+                _ => panic!("invalid module flag behavior"),
+            };
+            let Metadata::String(key) = self.metadata.get(key) else {
+                panic!("invalid module flag key");
+            };
+
+            ModuleFlag {
+                behavior,
+                key,
+                value,
+            }
+        })
+    }
+
+    pub fn set_dwarf_version(&mut self, version: u32) {
+        self.set_module_flag_u32(ModuleFlagBehavior::Max, "Dwarf Version", version);
+    }
+
+    /// Sets "Debug Info Version" to [`DEBUG_METADATA_VERSION`], needed for the debug
+    /// info to be kept.
+    pub fn set_debug_info_version(&mut self) {
+        self.set_module_flag_u32(
+            ModuleFlagBehavior::Warning,
+            "Debug Info Version",
+            DEBUG_METADATA_VERSION,
+        );
+    }
+
+    /// Emits CodeView instead of DWARF, for Windows targets.
+    pub fn set_code_view(&mut self) {
+        self.set_module_flag_u32(ModuleFlagBehavior::Warning, "CodeView", 1);
+    }
+
+    pub fn set_pic_level(&mut self, level: PicLevel) {
+        self.set_module_flag_u32(ModuleFlagBehavior::Min, "PIC Level", level as u32);
+    }
+
+    pub fn set_pie_level(&mut self, level: PieLevel) {
+        self.set_module_flag_u32(ModuleFlagBehavior::Max, "PIE Level", level as u32);
+    }
+
+    /// The size of `wchar_t` in bytes.
+    pub fn set_wchar_size(&mut self, size: u32) {
+        self.set_module_flag_u32(ModuleFlagBehavior::Error, "wchar_size", size);
+    }
+
+    pub fn set_uwtable(&mut self, kind: UWTableKind) {
+        self.set_module_flag_u32(ModuleFlagBehavior::Max, "uwtable", kind as u32);
+    }
+
+    pub fn set_frame_pointer(&mut self, kind: FramePointerKind) {
+        self.set_module_flag_u32(ModuleFlagBehavior::Max, "frame-pointer", kind as u32);
+    }
+
+    /// AArch64 BTI, linked modules keep the smallest value.
+    pub fn set_branch_target_enforcement(&mut self, enabled: bool) {
+        self.set_module_flag_u32(
+            ModuleFlagBehavior::Min,
+            "branch-target-enforcement",
+            enabled as u32,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitcode::constants::{BlockId, ConstantsCode, MetadataCode},
+        bitstream::reader::read,
+        ir::module::Module,
+    };
+
+    use super::{FramePointerKind, ModuleFlagBehavior, PicLevel, PieLevel, UWTableKind};
+
+    #[test]
+    fn write_module_flags() {
+        let mut module = Module::new("test.c");
+        module.set_dwarf_version(4);
+        module.set_debug_info_version();
+        module.set_wchar_size(4);
+        module.set_pic_level(PicLevel::Big);
+        module.set_pie_level(PieLevel::Large);
+        module.set_uwtable(UWTableKind::Async);
+        module.set_frame_pointer(FramePointerKind::NonLeaf);
+        module.set_branch_target_enforcement(true);
+
+        // Replaces the previous entry.
+        module.set_dwarf_version(5);
+
+        let flag = module.module_flag("Dwarf Version").unwrap();
+        assert_eq!(flag.behavior, ModuleFlagBehavior::Max);
+        assert_eq!(module.module_flags().count(), 8);
+
+        let bitcode = read(&module.to_bitcode());
+        let module_block = bitcode.block(BlockId::Module as u32);
+        // The integers are signed VBRs.
+        let ints: Vec<_> = module_block
+            .block(BlockId::Constants as u32)
+            .records(ConstantsCode::Integer as u32)
+            .iter()
+            .map(|x| x.ops[0] >> 1)
+            .collect();
+        let metadata = module_block.block(BlockId::Metadata as u32);
+        let strings = metadata
+            .record(MetadataCode::STRINGS as u32)
+            .metadata_strings();
+        let values: Vec<_> = metadata
+            .records(MetadataCode::VALUE as u32)
+            .iter()
+            .map(|x| ints[x.ops[1] as usize])
+            .collect();
+
+        // The values follow the strings and the operands are shifted by one.
+        let flags: Vec<_> = metadata
+            .records(MetadataCode::NODE as u32)
+            .iter()
+            .map(|x| {
+                let value = |id: u64| values[id as usize - 1 - strings.len()];
+                let name = strings[x.ops[1] as usize - 1].as_str();
+                (value(x.ops[0]), name, value(x.ops[2]))
+            })
+            .collect();
+        let (max, min) = (
+            ModuleFlagBehavior::Max as u64,
+            ModuleFlagBehavior::Min as u64,
+        );
+        assert_eq!(
+            flags,
+            [
+                (max, "Dwarf Version", 5),
+                (ModuleFlagBehavior::Warning as u64, "Debug Info Version", 3),
+                (ModuleFlagBehavior::Error as u64, "wchar_size", 4),
+                (min, "PIC Level", 2),
+                (max, "PIE Level", 2),
+                (max, "uwtable", 2),
+                (max, "frame-pointer", 1),
+                (min, "branch-target-enforcement", 1),
+            ]
+        );
+        let name = metadata.record(MetadataCode::NAME as u32);
+        assert_eq!(name.string(0), "llvm.module.flags");
+        let named = metadata.record(MetadataCode::NAMED_NODE as u32);
+        assert_eq!(named.ops.len(), 8);
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+
+/// Handle to a type in a [`Types`] table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId(pub(crate) u32);
+
+impl TypeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// https://llvm.org/docs/LangRef.html#type-system
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Void,
+    Half,
+    BFloat,
+    Float,
+    Double,
+    X86Fp80,
+    Fp128,
+    PpcFp128,
+    Label,
+    Metadata,
+    Token,
+    Integer(u32),
+    Pointer {
+        address_space: u32,
+    },
+    Array {
+        element: TypeId,
+        len: u64,
+    },
+    /// Scalable vectors have `len * vscale` elements, `vscale` being a runtime
+    /// constant of the target.
+    Vector {
+        element: TypeId,
+        len: u32,
+        scalable: bool,
+    },
+    Struct(StructType),
+    Function(FunctionType),
+}
+
+impl Type {
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Integer(_))
+    }
+
+    pub fn is_floating_point(&self) -> bool {
+        matches!(
+            self,
+            Type::Half
+                | Type::BFloat
+                | Type::Float
+                | Type::Double
+                | Type::X86Fp80
+                | Type::Fp128
+                | Type::PpcFp128
+        )
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, Type::Pointer { .. })
+    }
+
+    /// Types values can have.
+    pub fn is_first_class(&self) -> bool {
+        !matches!(self, Type::Void | Type::Function(_))
+    }
+}
+
+/// The number of elements of a vector, a multiple of `vscale` for scalable vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ElementCount {
+    pub min: u32,
+    pub scalable: bool,
+}
+
+impl ElementCount {
+    pub fn fixed(len: u32) -> Self {
+        Self {
+            min: len,
+            scalable: false,
+        }
+    }
+
+    pub fn scalable(min: u32) -> Self {
+        Self {
+            min,
+            scalable: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StructType {
+    /// Identified structs have a name, literal structs don't.
+    pub name: Option<String>,
+    pub fields: Vec<TypeId>,
+    pub packed: bool,
+    /// Identified structs are opaque until their body is set.
+    pub opaque: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionType {
+    pub ret: TypeId,
+    pub params: Vec<TypeId>,
+    pub vararg: bool,
+}
+
+/// The type table, types are uniqued except for identified structs.
+#[derive(Debug, Clone, Default)]
+pub struct Types {
+    types: Vec<Type>,
+    interned: HashMap<Type, TypeId>,
+}
+
+impl Types {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: TypeId) -> &Type {
+        &self.types[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TypeId, &Type)> {
+        self.types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| (TypeId(i as u32), ty))
+    }
+
+    /// Returns the id of the given type, adding it if it's not in the table yet.
+    pub fn intern(&mut self, ty: Type) -> TypeId {
+        if let Type::Struct(StructType { name: Some(_), .. }) = ty {
+            panic!("identified structs must be created with named_struct");
+        }
+
+        if let Some(id) = self.interned.get(&ty) {
+            return *id;
+        }
+
+        let id = self.push(ty.clone());
+        self.interned.insert(ty, id);
+        id
+    }
+
+    fn push(&mut self, ty: Type) -> TypeId {
+        let id = TypeId(self.types.len() as u32);
+        self.types.push(ty);
+        id
+    }
+
+    pub fn void(&mut self) -> TypeId {
+        self.intern(Type::Void)
+    }
+
+    pub fn int(&mut self, bits: u32) -> TypeId {
+        assert!((1..=(1 << 23)).contains(&bits), "invalid integer bit width");
+        self.intern(Type::Integer(bits))
+    }
+
+    pub fn i1(&mut self) -> TypeId {
+        self.int(1)
+    }
+
+    pub fn i8(&mut self) -> TypeId {
+        self.int(8)
+    }
+
+    pub fn i16(&mut self) -> TypeId {
+        self.int(16)
+    }
+
+    pub fn i32(&mut self) -> TypeId {
+        self.int(32)
+    }
+
+    pub fn i64(&mut self) -> TypeId {
+        self.int(64)
+    }
+
+    pub fn i128(&mut self) -> TypeId {
+        self.int(128)
+    }
+
+    pub fn half(&mut self) -> TypeId {
+        self.intern(Type::Half)
+    }
+
+    pub fn bfloat(&mut self) -> TypeId {
+        self.intern(Type::BFloat)
+    }
+
+    pub fn float(&mut self) -> TypeId {
+        self.intern(Type::Float)
+    }
+
+    pub fn double(&mut self) -> TypeId {
+        self.intern(Type::Double)
+    }
+
+    pub fn x86_fp80(&mut self) -> TypeId {
+        self.intern(Type::X86Fp80)
+    }
+
+    pub fn fp128(&mut self) -> TypeId {
+        self.intern(Type::Fp128)
+    }
+
+    pub fn ppc_fp128(&mut self) -> TypeId {
+        self.intern(Type::PpcFp128)
+    }
+
+    pub fn label(&mut self) -> TypeId {
+        self.intern(Type::Label)
+    }
+
+    pub fn metadata(&mut self) -> TypeId {
+        self.intern(Type::Metadata)
+    }
+
+    pub fn token(&mut self) -> TypeId {
+        self.intern(Type::Token)
+    }
+
+    /// An opaque pointer in the default address space.
+    pub fn ptr(&mut self) -> TypeId {
+        self.ptr_in(0)
+    }
+
+    pub fn ptr_in(&mut self, address_space: u32) -> TypeId {
+        self.intern(Type::Pointer { address_space })
+    }
+
+    pub fn array(&mut self, element: TypeId, len: u64) -> TypeId {
+        self.intern(Type::Array { element, len })
+    }
+
+    pub fn vector(&mut self, element: TypeId, len: u32) -> TypeId {
+        self.vector_of(element, ElementCount::fixed(len))
+    }
+
+    /// A `<vscale x len x element>` vector.
+    pub fn scalable_vector(&mut self, element: TypeId, len: u32) -> TypeId {
+        self.vector_of(element, ElementCount::scalable(len))
+    }
+
+    pub fn vector_of(&mut self, element: TypeId, count: ElementCount) -> TypeId {
+        assert!(count.min > 0, "vectors must have at least one element");
+        assert!(
+            matches!(self.get(element), Type::Integer(_) | Type::Pointer { .. })
+                || self.get(element).is_floating_point(),
+            "invalid vector element type"
+        );
+        self.intern(Type::Vector {
+            element,
+            len: count.min,
+            scalable: count.scalable,
+        })
+    }
+
+    /// A literal struct.
+    pub fn structure(&mut self, fields: &[TypeId], packed: bool) -> TypeId {
+        self.intern(Type::Struct(StructType {
+            name: None,
+            fields: fields.to_vec(),
+            packed,
+            opaque: false,
+        }))
+    }
+
+    /// An identified struct, opaque until [`Types::set_body`] is called.
+    pub fn named_struct(&mut self, name: &str) -> TypeId {
+        assert!(!name.is_empty(), "identified structs need a name");
+        assert!(
+            self.struct_by_name(name).is_none(),
+            "struct {name} already exists"
+        );
+
+        self.push(Type::Struct(StructType {
+            name: Some(name.to_string()),
+            fields: Vec::new(),
+            packed: false,
+            opaque: true,
+        }))
+    }
+
+    pub fn set_body(&mut self, id: TypeId, fields: &[TypeId], packed: bool) {
+        match &mut self.types[id.index()] {
+            Type::Struct(ty) if ty.name.is_some() => {
+                ty.fields = fields.to_vec();
+                ty.packed = packed;
+                ty.opaque = false;
+            }
+            _ => panic!("only identified structs can have their body set"),
+        }
+    }
+
+    pub fn struct_by_name(&self, name: &str) -> Option<TypeId> {
+        self.iter().find_map(|(id, ty)| match ty {
+            Type::Struct(StructType {
+                name: Some(ty_name),
+                ..
+            }) if ty_name == name => Some(id),
+            _ => None,
+        })
+    }
+
+    pub fn function(&mut self, ret: TypeId, params: &[TypeId], vararg: bool) -> TypeId {
+        self.intern(Type::Function(FunctionType {
+            ret,
+            params: params.to_vec(),
+            vararg,
+        }))
+    }
+
+    /// The element type of vectors, the type itself otherwise.
+    pub fn scalar_type(&self, id: TypeId) -> TypeId {
+        match self.get(id) {
+            Type::Vector { element, .. } => *element,
+            _ => id,
+        }
+    }
+
+    /// Types with a size in memory.
+    pub fn is_sized(&self, id: TypeId) -> bool {
+        match self.get(id) {
+            Type::Void | Type::Label | Type::Metadata | Type::Token | Type::Function(_) => false,
+            Type::Struct(st) => !st.opaque,
+            _ => true,
+        }
+    }
+
+    /// The type at `indices` inside a struct or array, `None` if they don't index
+    /// into the type.
+    pub fn indexed_type(&self, id: TypeId, indices: &[u32]) -> Option<TypeId> {
+        indices
+            .iter()
+            .try_fold(id, |current, index| match self.get(current) {
+                Type::Struct(st) => st.fields.get(*index as usize).copied(),
+                Type::Array { element, len } => (u64::from(*index) < *len).then_some(*element),
+                _ => None,
+            })
+    }
+
+    /// The number of elements of vectors.
+    pub fn element_count(&self, id: TypeId) -> Option<ElementCount> {
+        match self.get(id) {
+            Type::Vector { len, scalable, .. } => Some(ElementCount {
+                min: *len,
+                scalable: *scalable,
+            }),
+            _ => None,
+        }
+    }
+
+    /// A type with the same shape as `id` (a vector of the same length or a scalar)
+    /// with `scalar` as its scalar type.
+    pub fn with_scalar_type(&mut self, id: TypeId, scalar: TypeId) -> TypeId {
+        match self.element_count(id) {
+            Some(count) => self.vector_of(scalar, count),
+            None => scalar,
+        }
+    }
+
+    /// The size in bits of integers, floating point types and vectors of them, 0 for
+    /// anything else.
+    pub fn primitive_size_in_bits(&self, id: TypeId) -> u64 {
+        match self.get(id) {
+            Type::Integer(bits) => *bits as u64,
+            Type::Half | Type::BFloat => 16,
+            Type::Float => 32,
+            Type::Double => 64,
+            Type::X86Fp80 => 80,
+            Type::Fp128 | Type::PpcFp128 => 128,
+            // The minimum size of scalable vectors.
+            Type::Vector { element, len, .. } => {
+                self.primitive_size_in_bits(*element) * *len as u64
+            }
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Type, Types};
+
+    #[test]
+    fn types_are_uniqued() {
+        let mut types = Types::new();
+
+        let i32 = types.i32();
+        assert_eq!(types.int(32), i32);
+        assert_ne!(types.i64(), i32);
+
+        let ptr = types.ptr();
+        let fn_ty = types.function(i32, &[ptr, i32], false);
+        assert_eq!(types.function(i32, &[ptr, i32], false), fn_ty);
+        assert_ne!(types.function(i32, &[ptr, i32], true), fn_ty);
+
+        let literal = types.structure(&[i32, ptr], false);
+        assert_eq!(types.structure(&[i32, ptr], false), literal);
+    }
+
+    #[test]
+    fn identified_structs() {
+        let mut types = Types::new();
+
+        let a = types.named_struct("a");
+        let b = types.named_struct("b");
+        assert_ne!(a, b);
+        assert_eq!(types.struct_by_name("b"), Some(b));
+
+        let i8 = types.i8();
+        types.set_body(a, &[i8], true);
+
+        match types.get(a) {
+            Type::Struct(ty) => {
+                assert!(!ty.opaque);
+                assert!(ty.packed);
+                assert_eq!(ty.fields, vec![i8]);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use super::{metadata::MetadataId, types::TypeId};
+
+/// Handle to a global variable of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GlobalId(pub(crate) u32);
+
+/// Handle to a function of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FunctionId(pub(crate) u32);
+
+/// Handle to a global alias of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AliasId(pub(crate) u32);
+
+/// Handle to a global ifunc of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IFuncId(pub(crate) u32);
+
+/// Handle to a constant of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConstantId(pub(crate) u32);
+
+/// Handle to an instruction, local to its function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstId(pub(crate) u32);
+
+/// Handle to a basic block, local to its function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BasicBlockId(pub(crate) u32);
+
+macro_rules! impl_index {
+    ($($name:ident),*) => {
+        $(
+            impl $name {
+                pub fn index(self) -> usize {
+                    self.0 as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_index!(
+    GlobalId,
+    FunctionId,
+    AliasId,
+    IFuncId,
+    ConstantId,
+    InstId,
+    BasicBlockId
+);
+
+/// What a [`Value`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueRef {
+    Global(GlobalId),
+    Function(FunctionId),
+    Alias(AliasId),
+    IFunc(IFuncId),
+    Constant(ConstantId),
+    /// A function argument, by position.
+    Argument(u32),
+    Instruction(InstId),
+    /// Metadata used as a call argument.
+    Metadata(MetadataId),
+}
+
+impl ValueRef {
+    /// Arguments and instructions only exist inside a function.
+    pub fn is_local(self) -> bool {
+        matches!(self, ValueRef::Argument(_) | ValueRef::Instruction(_))
+    }
+}
+
+/// A typed handle to a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Value {
+    pub(crate) kind: ValueRef,
+    pub(crate) ty: TypeId,
+    // The function of arguments and instructions.
+    pub(crate) function: Option<FunctionId>,
+}
+
+impl Value {
+    pub(crate) fn new(kind: ValueRef, ty: TypeId) -> Self {
+        assert!(!kind.is_local(), "local values belong to a function");
+        Self {
+            kind,
+            ty,
+            function: None,
+        }
+    }
+
+    pub(crate) fn local(kind: ValueRef, ty: TypeId, function: FunctionId) -> Self {
+        assert!(kind.is_local(), "only arguments and instructions are local");
+        Self {
+            kind,
+            ty,
+            function: Some(function),
+        }
+    }
+
+    pub fn kind(self) -> ValueRef {
+        self.kind
+    }
+
+    pub fn ty(self) -> TypeId {
+        self.ty
+    }
+
+    /// The function of an argument or instruction.
+    pub fn function(self) -> Option<FunctionId> {
+        self.function
+    }
+
+    /// The instruction producing the value, if any.
+    pub fn instruction(self) -> Option<InstId> {
+        match self.kind {
+            ValueRef::Instruction(id) => Some(id),
+            _ => None,
+        }
+    }
+}
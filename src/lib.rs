@@ -1,2 +1,3 @@
 pub mod bitcode;
 pub mod bitstream;
+pub mod ir;